@@ -2,45 +2,660 @@
 //!
 //! An Abstract Syntax Tree consists of [`Node`]s, which are built by a
 //! [`Parser`](crate::Parser). AST can be evaluated or used to generate code.
-use std::{
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
+    any::Any,
+    cmp::Ordering,
     fmt::{Debug, Display},
-    ops::{Add, Div, Mul, Neg, Sub},
+    hash::{Hash, Hasher},
+    mem,
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
 };
+#[cfg(feature = "std")]
+use std::{cell::Cell, collections::HashMap, time::Instant};
 
+#[cfg(feature = "bigint")]
+use num_bigint::{BigInt, Sign};
+#[cfg(feature = "bigint")]
+use num_traits::ToPrimitive;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "decimal")]
+use rust_decimal::{prelude::*, Decimal, MathematicalOps};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::Function;
+use crate::{math, EvalError, Function, Span};
 
 /// Internal representation of numbers.
-#[derive(Debug, Clone, Copy)]
+///
+/// Two integers are compared exactly. Otherwise, both operands are converted to
+/// floating-point numbers and compared using a combined absolute and relative
+/// tolerance, so that large magnitudes are compared as sensibly as small ones.
+///
+/// As with [`f64`], `NaN` is not equal to anything (including itself) and has
+/// no ordering, i.e. [`partial_cmp`](PartialOrd::partial_cmp) returns [`None`].
+/// Positive and negative zeros are considered equal.
+///
+/// With the `decimal` feature, [`Number::Dec`] stores exact decimal numbers.
+/// Arithmetic between decimals and integers stays exact (unless it overflows),
+/// while arithmetic involving floats falls back to floating-point numbers.
+///
+/// With the `bigint` feature, integer arithmetic that overflows [`i128`] is
+/// performed on [`Number::Big`] instead. Big integers that fit in [`i128`] are
+/// always stored as [`Number::Int`].
+///
+/// With the `vector` feature, [`Number::Vec`] stores a vector of numbers.
+/// Operations on vectors are performed elementwise, where a single number is
+/// combined with every element. Vectors of different lengths result in `NaN`,
+/// or in [`EvalError::LengthMismatch`] from [`Node::try_evaluate`], while
+/// vectors used as conditions result in `NaN`. Two vectors are equal if all of
+/// their elements are.
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Number {
     Int(i128),
     Flt(f64),
+    #[cfg(feature = "decimal")]
+    Dec(Decimal),
+    #[cfg(feature = "bigint")]
+    Big(BigInt),
+    #[cfg(feature = "vector")]
+    Vec(Vec<Number>),
+}
+
+/// Kind of a [`Number`], i.e. its variant without the value, returned by
+/// [`Number::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberKind {
+    Int,
+    Flt,
+    #[cfg(feature = "decimal")]
+    Dec,
+    #[cfg(feature = "bigint")]
+    Big,
+    #[cfg(feature = "vector")]
+    Vec,
+}
+
+/// Result of [`Node::evaluate_typed`], which tells exact values apart from
+/// approximate ones.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EvalResult {
+    /// Value computed without any lossy operation, e.g. `1 + 2`.
+    Exact(Number),
+
+    /// Value computed using floating-point numbers, e.g. `sin(1)`.
+    Approx(f64),
+}
+
+/// Name of the physical constant a value was taken from, standing in for its
+/// dimension, e.g. `c` for a velocity. See [`Node::evaluate_dimensioned`].
+#[cfg(feature = "dimensions")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DimensionTag(pub &'static str);
+
+/// Warning about two values of different dimensions being added, subtracted
+/// or compared, e.g. `c + NA`. See [`Node::evaluate_dimensioned`].
+#[cfg(feature = "dimensions")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DimensionMismatch {
+    /// Dimension of the left-hand side operand.
+    pub left: DimensionTag,
+
+    /// Dimension of the right-hand side operand.
+    pub right: DimensionTag,
+
+    /// Part of the source code with the mismatch, if known.
+    pub span: Option<Span>,
+}
+
+/// Precision of decimals, which are rounded after each action by
+/// [`Node::evaluate_with_context`], like the precision setting of a
+/// calculator.
+#[cfg(feature = "decimal")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecimalContext {
+    /// Maximum number of decimal places kept.
+    pub scale: u32,
+
+    /// How decimals with more decimal places are rounded.
+    pub rounding: DecimalRounding,
+}
+
+/// Rounding mode of a [`DecimalContext`].
+#[cfg(feature = "decimal")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecimalRounding {
+    /// Round to the nearest, with halves away from zero, e.g. `0.125` to
+    /// `0.13`.
+    #[default]
+    HalfUp,
+
+    /// Round to the nearest, with halves to the even neighbour, e.g. `0.125`
+    /// to `0.12`.
+    HalfEven,
+
+    /// Round towards zero, i.e. drop the remaining decimal places.
+    Truncate,
+
+    /// Round towards negative infinity.
+    Floor,
+
+    /// Round towards positive infinity.
+    Ceiling,
+}
+
+/// Order in which the operands of a [`Node`] are evaluated by
+/// [`Node::evaluate_trace`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum EvalOrder {
+    /// Operands are evaluated from left to right, as by [`Node::evaluate`].
+    #[default]
+    LeftToRight,
+
+    /// Operands are evaluated from right to left, except for the operands of
+    /// logical operators and conditionals, which short-circuit.
+    RightToLeft,
+}
+
+/// [`TotalNumber`] orders [`Number`]s totally, e.g. for sorting, as opposed to
+/// the partial order of [`Number`] itself. See [`Number::total_cmp`].
+#[derive(Debug, Clone)]
+pub struct TotalNumber(pub Number);
+
+/// Options for formatting [`Number`]s with
+/// [`to_string_with_opts`](Number::to_string_with_opts).
+///
+/// The default options format numbers the same way as [`Display`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    /// Text used for `NaN`.
+    pub nan: String,
+
+    /// Text used for positive infinity.
+    pub inf: String,
+
+    /// Text used for negative infinity.
+    pub neg_inf: String,
+}
+
+/// Layout of [`Number`]s formatted with
+/// [`to_string_with_layout`](Number::to_string_with_layout), e.g. their
+/// decimal places and digit grouping, set with builder methods such as
+/// [`with_decimals`](NumberLayout::with_decimals).
+///
+/// The default layout formats numbers the same way as [`Display`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct NumberLayout {
+    /// Number of decimal places, rounded as with
+    /// [`to_fixed`](Number::to_fixed). Numbers are written as short as
+    /// possible if [`None`].
+    pub decimals: Option<usize>,
+
+    /// Character separating groups of three digits in the integer part, e.g.
+    /// `,` for `1,000`. Digits are not grouped if [`None`].
+    pub group_separator: Option<char>,
+
+    /// Character separating the integer part from the decimal places.
+    pub decimal_separator: char,
+
+    /// Smallest exponent written in scientific notation, e.g. with `6`,
+    /// `1500000` is written as `1.5e+06` and `0.0000015` as `1.5e-06`. Numbers
+    /// are never written in scientific notation if [`None`].
+    pub scientific_threshold: Option<u32>,
+}
+
+/// Type of an expression generated by [`Node::to_rust_typed`] or
+/// [`Node::to_c_typed`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CodeType {
+    /// Integers, i.e. [`Number::Int`], which are `i128` in Rust and
+    /// `long long` in C.
+    Int,
+
+    /// Everything else, including the results of functions, which are `f64`
+    /// in Rust and `double` in C.
+    Float,
 }
 
 /// [`Node`] provides a blanket trait for both [`BinaryNode`] and [`UnaryNode`].
 #[cfg_attr(feature = "serde", typetag::serde(tag = "type"))]
-pub trait Node: Debug + Display {
-    /// Finds the value of this [`Node`].
+pub trait Node: Debug + Display + Send + Sync + AsNode {
+    /// Finds the value of this [`Node`]. Variables are unbound and evaluate to
+    /// `NaN`.
     fn evaluate(&self) -> Number;
 
+    /// Finds the value of this [`Node`], looking up variables in `vars`.
+    /// Unbound variables evaluate to `NaN`. By default, this [`Node`] does not
+    /// refer to any variables, so it is [`evaluate`](Node::evaluate)d.
+    fn evaluate_with(&self, _vars: &dyn Variables) -> Number {
+        self.evaluate()
+    }
+
+    /// Finds the value of this [`Node`], with variables bound by their indices
+    /// (see [`Parser::variables`](crate::Parser::variables)). This avoids
+    /// looking up variables by name.
+    fn evaluate_indexed(&self, vars: &[Number]) -> Number {
+        self.evaluate_with(&vars)
+    }
+
+    /// Finds the value of this [`Node`] like [`evaluate`](Node::evaluate), but
+    /// a float within `tol` of an integer becomes that integer, e.g.
+    /// `0.9999999999998` becomes `1` with a `tol` of `1e-9`. Vectors are
+    /// cleaned elementwise.
+    fn evaluate_clean(&self, tol: f64) -> Number {
+        self.evaluate().clean(tol)
+    }
+
+    /// Finds the value of this [`Node`] like [`evaluate`](Node::evaluate), but
+    /// fails instead of resulting in `NaN` if a function is applied outside its
+    /// domain.
+    fn try_evaluate(&self) -> Result<Number, EvalError> {
+        let vars: &[Number] = &[];
+        self.try_evaluate_with(&vars)
+    }
+
+    /// Finds the value of this [`Node`] like [`evaluate_with`](Node::evaluate_with),
+    /// but fails instead of resulting in `NaN` if a function is applied outside
+    /// its domain, e.g. `asin(2)` or `log10(0)`.
+    fn try_evaluate_with(&self, vars: &dyn Variables) -> Result<Number, EvalError> {
+        Ok(self.evaluate_with(vars))
+    }
+
+    /// Finds the values of this [`Node`] with the variable `var` bound to each
+    /// of `points` in turn, e.g. for plotting. Any other variable is unbound.
+    fn sample(&self, var: &str, points: &[f64]) -> Vec<Number> {
+        points
+            .iter()
+            .map(|x| self.evaluate_with(&Sample::new(var, *x)))
+            .collect()
+    }
+
+    /// Finds the same values as [`sample`](Node::sample), evaluating at many
+    /// points concurrently.
+    #[cfg(feature = "parallel")]
+    fn sample_par(&self, var: &str, points: &[f64]) -> Vec<Number> {
+        points
+            .par_iter()
+            .map(|x| self.evaluate_with(&Sample::new(var, *x)))
+            .collect()
+    }
+
+    /// Finds the value of this [`Node`] like [`try_evaluate`](Node::try_evaluate),
+    /// but fails with [`EvalError::Timeout`] if it is still running at
+    /// `deadline`. The clock is checked every few nodes, not continuously.
+    #[cfg(feature = "std")]
+    fn evaluate_deadline(&self, deadline: Instant) -> Result<Number, EvalError> {
+        let vars: &[Number] = &[];
+        self.try_evaluate_with(&Deadline::new(&vars, deadline))
+    }
+
+    /// Evaluates the parts of this [`Node`] that do not depend on unbound
+    /// variables, e.g. `x + 2*3` becomes `x + 6` if `x` is unbound. The result
+    /// is a single [`PlainNode`] if all variables are bound. By default, this
+    /// [`Node`] is evaluated in full.
+    fn partial_eval(&self, vars: &dyn Variables) -> NodeBox {
+        Box::new(PlainNode::new(self.evaluate_with(vars)))
+    }
+
+    /// Converts this [`Node`] to a canonical form, where the operands of
+    /// commutative actions (addition and multiplication) are sorted by
+    /// [`structural_cmp`](Node::structural_cmp), so that e.g. `b*a` and `a*b`
+    /// result in the same tree. Associativity is not taken into account, so
+    /// `(a + b) + c` and `a + (b + c)` stay different. Nodes without operands
+    /// are already canonical, which is the default.
+    fn canonicalize(self: Box<Self>) -> NodeBox {
+        self.into_node_box()
+    }
+
+    /// Compares the structure of this [`Node`] with another, i.e. their
+    /// actions, numbers and variables, ignoring spans. This is a total order:
+    /// nodes of different types are ordered by type, then by action in order
+    /// of declaration, then by operand from left to right, while numbers are
+    /// compared with [`Number::total_cmp`] and variables by name. Nodes
+    /// defined outside this crate come last, compared by
+    /// [`to_tree`](Node::to_tree) by default.
+    fn structural_cmp(&self, other: &dyn Node) -> Ordering {
+        node_rank(self.as_any())
+            .cmp(&node_rank(other.as_any()))
+            .then_with(|| self.to_tree().cmp(&other.to_tree()))
+    }
+
+    /// Feeds the content of this [`Node`] to `state`, i.e. its actions,
+    /// numbers and variables, but not its span. See
+    /// [`content_hash`](Node::content_hash). Nodes defined outside this crate
+    /// are fed by their [`to_tree`](Node::to_tree) by default.
+    fn hash_content(&self, state: &mut dyn Hasher) {
+        let tree = self.to_tree();
+        state.write_u8(u8::MAX);
+        state.write_u64(tree.len() as u64);
+        for line in tree {
+            hash_bytes(state, line.as_bytes());
+        }
+    }
+
+    /// Finds a hash of the content of this [`Node`], which is the same for
+    /// trees with the same structure and numbers, and stable across runs and
+    /// platforms, e.g. for caching results. Floats are hashed by their bit
+    /// patterns, so `1` and `1.0` differ. Trees that only differ by
+    /// commutativity can be [`canonicalize`](Node::canonicalize)d first.
+    fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::default();
+        self.hash_content(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Finds the value of this [`Node`] like [`evaluate_with`](Node::evaluate_with),
+    /// rounding decimals to the precision of `ctx` after each action, e.g.
+    /// `1.0/3` is `0.3333` with a scale of 4. Numbers other than decimals are
+    /// not rounded.
+    #[cfg(feature = "decimal")]
+    fn evaluate_with_context(&self, vars: &dyn Variables, ctx: &DecimalContext) -> Number {
+        ctx.apply(self.evaluate_with(vars))
+    }
+
+    /// Finds the value of this [`Node`] like [`evaluate`](Node::evaluate), along
+    /// with the steps taken in `order`, one for each action, e.g. `6*2 = 12`
+    /// followed by `7 + 6*2 = 19` for `7 + 6*2`.
+    fn evaluate_trace(&self, order: EvalOrder) -> (Number, Vec<String>) {
+        let vars: &[Number] = &[];
+        let mut steps = Vec::new();
+        let value = self.trace(&vars, order, &mut steps);
+        (value, steps)
+    }
+
+    /// Finds the value of this [`Node`] like [`evaluate_with`](Node::evaluate_with),
+    /// adding the steps taken in `order` to `steps`. See
+    /// [`evaluate_trace`](Node::evaluate_trace).
+    fn trace(&self, vars: &dyn Variables, _order: EvalOrder, _steps: &mut Vec<String>) -> Number {
+        self.evaluate_with(vars)
+    }
+
+    /// Finds the value of this [`Node`] like [`evaluate`](Node::evaluate), along
+    /// with warnings about values of different dimensions being added,
+    /// subtracted or compared, e.g. `c + NA`.
+    ///
+    /// This is a lightweight check rather than a unit system: only values
+    /// taken directly from physical constants have a dimension, which is kept
+    /// by signs, absolute values, and sums of values with the same dimension.
+    /// Any other action results in a value without a dimension.
+    #[cfg(feature = "dimensions")]
+    fn evaluate_dimensioned(&self) -> (Number, Vec<DimensionMismatch>) {
+        let mut mismatches = Vec::new();
+        self.dimension(&mut mismatches);
+        (self.evaluate(), mismatches)
+    }
+
+    /// Finds the dimension of the value of this [`Node`], if it has one, adding
+    /// the mismatches found in this [`Node`] and its operands to `mismatches`.
+    /// See [`evaluate_dimensioned`](Node::evaluate_dimensioned).
+    #[cfg(feature = "dimensions")]
+    fn dimension(&self, _mismatches: &mut Vec<DimensionMismatch>) -> Option<DimensionTag> {
+        None
+    }
+
+    /// Part of the source code this [`Node`] was parsed from, from its first
+    /// token to its last. Nodes that were not parsed have no span.
+    fn span(&self) -> Option<Span> {
+        None
+    }
+
+    /// Sets the part of the source code this [`Node`] was parsed from.
+    fn set_span(&mut self, _span: Span) {}
+
+    /// Gets the number stored in this [`Node`], if it is a [`PlainNode`].
+    fn as_number(&self) -> Option<&Number> {
+        None
+    }
+
+    /// Finds the value of this [`Node`] like [`evaluate`](Node::evaluate),
+    /// telling whether it is exact. The value is approximate if it is a float,
+    /// or if any lossy operation (such as a trigonometric function or a float
+    /// literal) is involved.
+    fn evaluate_typed(&self) -> EvalResult {
+        match self.evaluate() {
+            Number::Flt(n) => EvalResult::Approx(n),
+            n if self.is_exact() => EvalResult::Exact(n),
+            n => EvalResult::Approx(n.to_f64()),
+        }
+    }
+
+    /// Whether evaluating this [`Node`] involves no lossy operation, see
+    /// [`evaluate_typed`](Node::evaluate_typed).
+    fn is_exact(&self) -> bool {
+        true
+    }
+
     fn to_tree(&self) -> Vec<String>;
+
+    /// Converts this [`Node`] to a tree like [`to_tree`](Node::to_tree), but
+    /// only up to `max_depth` levels, where this [`Node`] is the first level.
+    /// Deeper operands are replaced by a line such as `... (3 nodes)`. Nodes
+    /// without operands are converted in full.
+    fn to_tree_limited(&self, max_depth: usize) -> Vec<String> {
+        self.to_tree_with(&|operand| to_tree_operand(operand, max_depth))
+    }
+
+    /// Converts this [`Node`] to a tree like [`to_tree`](Node::to_tree), with
+    /// each operand converted by `operand_tree` instead. Nodes without operands
+    /// are converted as usual.
+    fn to_tree_with(&self, _operand_tree: &dyn Fn(&dyn Node) -> Vec<String>) -> Vec<String> {
+        self.to_tree()
+    }
+
+    /// Converts this [`Node`] to a tree like [`to_tree`](Node::to_tree), with
+    /// every line followed by the value of its subtree, e.g. `Op(+) = 19`.
+    /// Each subtree is evaluated on its own, so this is meant for debugging.
+    fn to_tree_evaluated(&self) -> Vec<String> {
+        let mut tree = self.to_tree_with(&|operand| operand.to_tree_evaluated());
+        tree[0].push_str(&format!(" = {}", self.evaluate()));
+        tree
+    }
+
+    /// Number of nodes in this [`Node`], including itself.
+    fn node_count(&self) -> usize {
+        1
+    }
+
+    /// Number of levels of this [`Node`], i.e. one more than the depth of its
+    /// deepest operand.
+    fn depth(&self) -> usize {
+        1
+    }
+
+    /// Formats this [`Node`] as an infix expression on one line, adding
+    /// parentheses only where they are needed, e.g. `7 + 6*(2 - 1)`. By
+    /// default, this [`Node`] is written as its value.
+    fn to_infix(&self) -> String {
+        self.evaluate().to_string()
+    }
+
+    /// Generates a Rust expression computing the value of this [`Node`], e.g.
+    /// `(x.sin() + (2i128 as f64))` for `sin(x) + 2`. Integers are `i128` and
+    /// cast to `f64` where mixed with floats, functions become methods of
+    /// `f64`, and variables must be `f64` bindings with the same names.
+    ///
+    /// Unlike evaluation, integers that overflow or are divided by zero panic.
+    ///
+    /// Returns [`None`] if any part has no equivalent, e.g. vectors,
+    /// `round_sig`, `hist`, or the exclusive or of floats.
+    fn to_rust(&self) -> Option<String> {
+        self.to_rust_typed().map(|(code, _)| code)
+    }
+
+    /// Generates a Rust expression like [`to_rust`](Node::to_rust), along with
+    /// its type. Nodes defined outside this crate have no equivalent by
+    /// default.
+    fn to_rust_typed(&self) -> Option<(String, CodeType)> {
+        None
+    }
+
+    /// Generates a C expression computing the value of this [`Node`] with the
+    /// functions of `math.h`, e.g. `(sin(x) + pow(2LL, 3LL))` for
+    /// `sin(x) + 2^3`. Integers are written as `long long` literals, promoted
+    /// to `double` by C where mixed with floats, and variables must be
+    /// `double`s with the same names. Absolute values of integers use `llabs`,
+    /// which needs `stdlib.h` too.
+    ///
+    /// Unlike evaluation, integers that overflow `long long` or are divided by
+    /// zero are undefined behaviour, and integer literals beyond `long long`
+    /// are written as `double`s.
+    ///
+    /// Returns [`None`] if any part has no equivalent, e.g. vectors,
+    /// `round_sig`, `hist`, or the exclusive or of floats.
+    fn to_c(&self) -> Option<String> {
+        self.to_c_typed().map(|(code, _)| code)
+    }
+
+    /// Generates a C expression like [`to_c`](Node::to_c), along with its
+    /// type. Nodes defined outside this crate have no equivalent by default.
+    fn to_c_typed(&self) -> Option<(String, CodeType)> {
+        None
+    }
+
+    /// How tightly this [`Node`] binds when written as an infix expression.
+    /// Operands binding less tightly than their parent need parentheses.
+    fn precedence(&self) -> u8 {
+        ATOMIC_PRECEDENCE
+    }
+
+    /// Formats this [`Node`] and its value on one line, e.g. `7 + 6*2 = 19`.
+    fn summary(&self) -> String {
+        format!("{} = {}", self.to_infix(), self.evaluate())
+    }
+}
+
+/// [`AsNode`] provides conversions for every [`Node`], implemented for all of
+/// them at once.
+pub trait AsNode {
+    /// Converts this boxed [`Node`] to a [`NodeBox`].
+    fn into_node_box(self: Box<Self>) -> NodeBox;
+
+    /// Gets this [`Node`] as [`Any`], so that it can be downcast to its
+    /// concrete type, e.g. `node.as_any().downcast_ref::<BinaryNode>()`.
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Node + 'static> AsNode for T {
+    fn into_node_box(self: Box<Self>) -> NodeBox {
+        self
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
 
-/// Convenience type alias for a [`Node`] stored on the heap.
+/// Convenience type alias for a [`Node`] stored on the heap. As all [`Node`]s
+/// are [`Send`] and [`Sync`], so is a [`NodeBox`], e.g. to share a parsed
+/// expression across threads.
 pub type NodeBox = Box<dyn Node>;
 
+/// [`Variables`] provides values of variables to [`Node::evaluate_with`].
+pub trait Variables {
+    /// Gets the value of a variable, which is known both by its name and by the
+    /// index assigned by the [`Parser`](crate::Parser).
+    fn get(&self, name: &str, index: usize) -> Option<Number>;
+
+    /// Gets the `n`th earlier result, counting from `1`, for `hist(n)`. There
+    /// are no earlier results by default. Missing results are `NaN`, or
+    /// [`EvalError::HistoryOutOfRange`] from [`Node::try_evaluate_with`].
+    fn recall(&self, _n: usize) -> Option<Number> {
+        None
+    }
+
+    /// Called by [`Node::try_evaluate_with`] before evaluating each composite
+    /// [`Node`], so that evaluation can be stopped with an error. Evaluation
+    /// always continues by default.
+    fn interrupt(&self) -> Result<(), EvalError> {
+        Ok(())
+    }
+}
+
+/// Number of calls to [`Deadline::interrupt`] between checks of the clock.
+#[cfg(feature = "std")]
+const DEADLINE_INTERVAL: usize = 64;
+
+/// [`Variables`] that interrupt evaluation once a deadline has passed, used
+/// by [`Node::evaluate_deadline`].
+#[cfg(feature = "std")]
+struct Deadline<'a> {
+    /// The variables actually looked up.
+    vars: &'a dyn Variables,
+
+    /// Point in time after which evaluation is interrupted.
+    deadline: Instant,
+
+    /// Number of interruption points reached so far.
+    count: Cell<usize>,
+}
+
+/// Binds a single variable by name, for [`Node::sample`] and
+/// [`numerically_equivalent`].
+struct Sample<'a> {
+    /// Name of the variable.
+    name: &'a str,
+
+    /// Value of the variable.
+    value: Number,
+}
+
 /// [`BinaryAction`] is an action done by a [`Node`] using two operands.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum BinaryAction {
     Add,
     Sub,
     Mul,
     Div,
+
+    /// Division where integers that do not divide evenly result in a float,
+    /// e.g. `7 fdiv 2` is `3.5`. It is written `fdiv`, or `/` when parsed with
+    /// [`IntDivMode::Float`](crate::IntDivMode::Float).
+    FloatDiv,
+
+    /// Division rounded toward negative infinity, unlike [`Div`](Self::Div) on
+    /// integers which truncates. Two integers result in an integer.
+    FloorDiv,
+
+    /// Remainder of [`FloorDiv`](Self::FloorDiv), which has the sign of the
+    /// right-hand side operand, e.g. `-7 mod 3` is `2`. A zero right-hand side
+    /// results in `NaN`.
+    Mod,
+
+    /// Exponentiation. A zero base with a negative exponent `-m` is evaluated
+    /// as the division `1 / 0^m`, so it follows the same division-by-zero
+    /// policy and results in infinity.
     Pow,
+
+    /// Bitwise exclusive or of two integers, written `xor`, or `^` when parsed
+    /// with [`CaretMeaning::Xor`](crate::CaretMeaning::Xor). Other numbers
+    /// result in `NaN`.
+    Xor,
+
+    /// Comparisons, resulting in `1` if true and `0` otherwise. Numbers are
+    /// compared as with [`PartialEq`] and [`PartialOrd`] for [`Number`].
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+
+    /// Logical operators, treating non-zero numbers as true and resulting in
+    /// `1` if true and `0` otherwise. The right-hand side is only evaluated if
+    /// needed (see [`BinaryAction::short_circuit`]).
+    And,
+    Or,
 }
 
 /// [`BinaryNode`] is a [`Node`] that performs an action on two operands.
@@ -55,14 +670,25 @@ pub struct BinaryNode {
 
     /// Right-hand side operand of this [`BinaryNode`].
     right: NodeBox,
+
+    /// Part of the source code this [`BinaryNode`] was parsed from.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    span: Option<Span>,
 }
 
 /// [`BinaryAction`] is an action done by a [`Node`] using one operand.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum UnaryAction {
     Neg,
     Iden,
+
+    /// Logical negation, resulting in `1` if the operand is zero and `0`
+    /// otherwise.
+    Not,
+
+    /// Absolute value, i.e. `|x|`.
+    Abs,
     Func(Function),
 }
 
@@ -75,12 +701,91 @@ pub struct UnaryNode {
 
     /// The sole operand of this [`UnaryNode`].
     operand: NodeBox,
+
+    /// Part of the source code this [`UnaryNode`] was parsed from.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    span: Option<Span>,
 }
 
-/// [`PlainNode`] simply stores the numbers without any action.
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// [`FunctionNode`] is a [`Node`] that applies a [`Function`] to any number of
+/// operands. Functions taking exactly one operand are represented by
+/// [`UnaryNode`] instead.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FunctionNode {
+    /// Function to be applied by this [`FunctionNode`].
+    func: Function,
+
+    /// Operands, i.e. arguments, of this [`FunctionNode`].
+    args: Vec<NodeBox>,
+
+    /// Part of the source code this [`FunctionNode`] was parsed from.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    span: Option<Span>,
+}
+
+/// [`PlainNode`] simply stores the numbers without any action. Two
+/// [`PlainNode`]s are equal if their numbers are, regardless of their spans.
+/// The default [`PlainNode`] stores an integer zero.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PlainNode(
+    Number,
+    #[cfg_attr(feature = "serde", serde(skip))] Option<Span>,
+    #[cfg(feature = "dimensions")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Option<DimensionTag>,
+);
+
+/// [`IfNode`] chooses between two operands based on a condition. Only the
+/// chosen operand is evaluated.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IfNode {
+    /// Condition, which is true if it is non-zero.
+    cond: NodeBox,
+
+    /// Operand chosen if the condition is true.
+    then: NodeBox,
+
+    /// Operand chosen if the condition is false.
+    otherwise: NodeBox,
+
+    /// Part of the source code this [`IfNode`] was parsed from.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    span: Option<Span>,
+}
+
+/// [`VectorNode`] builds a [`Number::Vec`] from its elements.
+#[cfg(feature = "vector")]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VectorNode {
+    /// Operands, i.e. elements, of this [`VectorNode`].
+    elements: Vec<NodeBox>,
+
+    /// Part of the source code this [`VectorNode`] was parsed from.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    span: Option<Span>,
+}
+
+/// [`VariableNode`] looks up the value of a variable during evaluation. Two
+/// [`VariableNode`]s are equal if their names and indices are, regardless of
+/// their spans.
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct PlainNode(Number);
+pub struct VariableNode {
+    /// Name of this variable.
+    name: String,
+
+    /// Index of this variable, assigned by the [`Parser`](crate::Parser) in
+    /// order of first appearance.
+    index: usize,
+
+    /// Part of the source code this [`VariableNode`] was parsed from.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    span: Option<Span>,
+}
 
 // -----------------------------------------------------------------------------
 // All impls onwards.
@@ -89,477 +794,3558 @@ pub struct PlainNode(Number);
 // -----------------------------------------------------------------------------
 // 1. impls for Number.
 // -----------------------------------------------------------------------------
-impl PartialEq for Number {
-    fn eq(&self, other: &Self) -> bool {
-        // Integer comparison.
-        if let Self::Int(a) = self {
-            if let Self::Int(b) = other {
-                return a == b;
-            }
-        }
+/// Absolute tolerance used when comparing floating-point [`Number`]s that are
+/// close to zero.
+const ABSOLUTE_EPSILON: f64 = f64::EPSILON * 1e3;
 
-        // Floating point comparison.
-        let epsilon = f64::EPSILON * 1e3;
-        let a = match self {
-            Self::Int(n) => *n as f64,
-            Self::Flt(n) => *n,
-        };
-        let b = match other {
-            Self::Int(n) => *n as f64,
-            Self::Flt(n) => *n,
-        };
+/// Relative tolerance used when comparing floating-point [`Number`]s, scaled by
+/// the magnitude of the larger operand.
+const RELATIVE_EPSILON: f64 = f64::EPSILON * 1e3;
+
+/// Precedence of logical negation, which binds less tightly than comparisons.
+const NOT_PRECEDENCE: u8 = 3;
+
+/// Precedence of unary negation, which binds less tightly than exponentiation.
+const NEG_PRECEDENCE: u8 = 7;
+
+/// Precedence of [`Node`]s that never need parentheses, e.g. numbers.
+const ATOMIC_PRECEDENCE: u8 = 9;
 
-        (a - b).abs() < epsilon
+/// Maximum number of bits of a big integer produced by exponentiation. Larger
+/// results are computed as floating-point numbers instead.
+#[cfg(feature = "bigint")]
+const MAX_BIGINT_BITS: u64 = 1 << 20;
+
+/// Checks if two floating-point numbers are close enough to be considered equal.
+///
+/// Two numbers are equal if their difference is within [`ABSOLUTE_EPSILON`]
+/// (for values near zero) or within [`RELATIVE_EPSILON`] of the larger
+/// magnitude (for everything else).
+fn approx_eq(a: f64, b: f64) -> bool {
+    // Exact match first, as infinities have no meaningful difference.
+    if a == b {
+        return true;
     }
+
+    let diff = (a - b).abs();
+    diff < ABSOLUTE_EPSILON || diff <= RELATIVE_EPSILON * a.abs().max(b.abs())
 }
 
-impl PartialOrd for Number {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        // Integer comparison.
-        if let Self::Int(a) = self {
-            if let Self::Int(b) = other {
-                return a.partial_cmp(b);
-            }
-        }
+/// FNV-1a offset basis and prime, see [`ContentHasher`].
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
 
-        // Floating point comparison.
-        let epsilon = f64::EPSILON * 1e3;
-        let a = match self {
-            Self::Int(n) => *n as f64,
-            Self::Flt(n) => *n,
-        };
-        let b = match other {
-            Self::Int(n) => *n as f64,
-            Self::Flt(n) => *n,
-        };
+/// Hasher for [`Node::content_hash`], using FNV-1a. Unlike the hasher of
+/// [`HashMap`](std::collections::HashMap), it is not randomly seeded, so
+/// hashes are the same across runs.
+struct ContentHasher(u64);
 
-        a.partial_cmp(&b).map(|o| {
-            if o == std::cmp::Ordering::Equal || (a - b).abs() < epsilon {
-                std::cmp::Ordering::Equal
-            } else {
-                o
-            }
-        })
+impl Default for ContentHasher {
+    fn default() -> Self {
+        Self(FNV_OFFSET)
     }
 }
 
-impl Add for Number {
-    type Output = Self;
-    fn add(self, rhs: Self) -> Self::Output {
-        match self {
-            Self::Int(a) => match rhs {
-                Self::Int(b) => Self::Int(a + b),
-                Self::Flt(b) => Self::Flt((a as f64) + b),
-            },
-            Self::Flt(a) => match rhs {
-                Self::Int(b) => Self::Flt(a + (b as f64)),
-                Self::Flt(b) => Self::Flt(a + b),
-            },
-        }
+impl Hasher for ContentHasher {
+    fn finish(&self) -> u64 {
+        self.0
     }
-}
 
-impl Sub for Number {
-    type Output = Self;
-    fn sub(self, rhs: Self) -> Self::Output {
-        match self {
-            Self::Int(a) => match rhs {
-                Self::Int(b) => Self::Int(a - b),
-                Self::Flt(b) => Self::Flt((a as f64) - b),
-            },
-            Self::Flt(a) => match rhs {
-                Self::Int(b) => Self::Flt(a - (b as f64)),
-                Self::Flt(b) => Self::Flt(a - b),
-            },
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(FNV_PRIME);
         }
     }
 }
 
-impl Mul for Number {
-    type Output = Self;
-    fn mul(self, rhs: Self) -> Self::Output {
-        match self {
-            Self::Int(a) => match rhs {
-                Self::Int(b) => Self::Int(a * b),
-                Self::Flt(b) => Self::Flt((a as f64) * b),
-            },
-            Self::Flt(a) => match rhs {
-                Self::Int(b) => Self::Flt(a * (b as f64)),
-                Self::Flt(b) => Self::Flt(a * b),
-            },
+/// Ranks a [`Node`] by its type for [`Node::structural_cmp`], in the order
+/// the types are declared. Nodes defined outside this crate come last.
+fn node_rank(node: &dyn Any) -> u8 {
+    if node.is::<BinaryNode>() {
+        0
+    } else if node.is::<UnaryNode>() {
+        1
+    } else if node.is::<FunctionNode>() {
+        2
+    } else if node.is::<PlainNode>() {
+        3
+    } else if node.is::<IfNode>() {
+        4
+    } else if node.is::<VariableNode>() {
+        6
+    } else {
+        #[cfg(feature = "vector")]
+        if node.is::<VectorNode>() {
+            return 5;
         }
+
+        u8::MAX
     }
 }
 
-impl Div for Number {
-    type Output = Self;
-    fn div(self, rhs: Self) -> Self::Output {
-        if rhs == Self::Int(0) || rhs == Self::Flt(0.0) {
-            return Self::Flt(f64::NAN);
-        }
+/// Compares two lists of operands with [`Node::structural_cmp`], one by one
+/// and then by length.
+fn structural_cmp_all(a: &[NodeBox], b: &[NodeBox]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| a.structural_cmp(b.as_ref()))
+        .find(|o| o.is_ne())
+        .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
 
-        match self {
-            Self::Int(a) => match rhs {
-                Self::Int(b) => Self::Int(a / b),
-                Self::Flt(b) => Self::Flt((a as f64) / b),
-            },
-            Self::Flt(a) => match rhs {
-                Self::Int(b) => Self::Flt(a / (b as f64)),
-                Self::Flt(b) => Self::Flt(a / b),
-            },
-        }
+/// Feeds `bytes` to `state` for [`Node::hash_content`], preceded by their
+/// length so that consecutive byte strings cannot be confused.
+fn hash_bytes(state: &mut dyn Hasher, bytes: &[u8]) {
+    state.write_u64(bytes.len() as u64);
+    state.write(bytes);
+}
+
+/// Casts a Rust expression generated by [`Node::to_rust_typed`] to `f64`.
+fn rust_f64((code, ty): (String, CodeType)) -> String {
+    match ty {
+        CodeType::Int => format!("({} as f64)", code),
+        CodeType::Float => code,
     }
 }
 
-impl Neg for Number {
-    type Output = Self;
-    fn neg(self) -> Self::Output {
-        match self {
-            Self::Int(a) => Self::Int(-a),
-            Self::Flt(a) => Self::Flt(-a),
-        }
+/// Converts a Rust expression generated by [`Node::to_rust_typed`] to `bool`,
+/// where non-zero numbers are true.
+fn rust_bool((code, ty): (String, CodeType)) -> String {
+    match ty {
+        CodeType::Int => format!("({} != 0)", code),
+        CodeType::Float => format!("({} != 0.0)", code),
     }
 }
 
-impl Display for Number {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Flt(n) => write!(f, "{}", n),
-            Self::Int(n) => write!(f, "{}", n),
-        }
+/// Casts two Rust expressions generated by [`Node::to_rust_typed`] to the same
+/// type, which is `i128` only if both are integers.
+fn rust_unify(a: (String, CodeType), b: (String, CodeType)) -> (String, String, CodeType) {
+    match (a, b) {
+        ((a, CodeType::Int), (b, CodeType::Int)) => (a, b, CodeType::Int),
+        (a, b) => (rust_f64(a), rust_f64(b), CodeType::Float),
     }
 }
 
-impl From<u8> for Number {
-    fn from(n: u8) -> Self {
-        Self::Int(n as i128)
+/// Casts a C expression generated by [`Node::to_c_typed`] to `double`, so
+/// that dividing it never truncates.
+fn c_double((code, ty): (String, CodeType)) -> String {
+    match ty {
+        CodeType::Int => format!("((double){})", code),
+        CodeType::Float => code,
     }
 }
 
-impl From<u16> for Number {
+/// Range of values of the variable sampled by [`numerically_equivalent`].
+const SAMPLE_RANGE: (f64, f64) = (-10.0, 10.0);
+
+/// Offset of the samples of [`numerically_equivalent`] within their share of
+/// [`SAMPLE_RANGE`], chosen so that samples avoid round numbers such as `0`.
+const SAMPLE_OFFSET: f64 = 0.381_966_011_250_105;
+
+/// Checks if two expressions agree within `tol` at `samples` values of the
+/// variable `var`, spread evenly over `[-10, 10]`, e.g. `sin(x)^2 + cos(x)^2`
+/// and `1`. Any other variable is unbound.
+///
+/// Samples where both expressions are `NaN`, e.g. outside their domain, are
+/// skipped, but at least one sample must be a number for them to agree.
+pub fn numerically_equivalent(
+    a: &dyn Node,
+    b: &dyn Node,
+    var: &str,
+    samples: usize,
+    tol: f64,
+) -> bool {
+    let (low, high) = SAMPLE_RANGE;
+    let mut agreed = false;
+
+    for i in 0..samples {
+        let x = low + (high - low) * (i as f64 + SAMPLE_OFFSET) / samples as f64;
+        let vars = Sample::new(var, x);
+        let (a, b) = (a.evaluate_with(&vars), b.evaluate_with(&vars));
+
+        match (a.is_nan(), b.is_nan()) {
+            (true, true) => continue,
+            (false, false) if (a.to_f64() - b.to_f64()).abs() <= tol => agreed = true,
+            _ => return false,
+        }
+    }
+
+    agreed
+}
+
+/// Rounds a number written in plain decimal notation, e.g. `-3.145`, to
+/// exactly `decimals` decimal places, with halves rounded away from zero.
+fn round_fixed(text: &str, decimals: usize) -> String {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let (int, frac) = text.split_once('.').unwrap_or((text, ""));
+
+    // Digits that are kept, padded with zeros, without the decimal point.
+    let mut digits = int.bytes().collect::<Vec<_>>();
+    digits.extend(frac.bytes().chain(core::iter::repeat(b'0')).take(decimals));
+
+    // Round up by carrying into the kept digits.
+    if frac.as_bytes().get(decimals).is_some_and(|d| *d >= b'5') {
+        let carried = digits.iter_mut().rev().all(|d| {
+            *d = if *d == b'9' { b'0' } else { *d + 1 };
+            *d == b'0'
+        });
+        if carried {
+            digits.insert(0, b'1');
+        }
+    }
+
+    let mut result = String::new();
+    if negative && digits.iter().any(|d| *d != b'0') {
+        result.push('-');
+    }
+
+    let point = digits.len() - decimals;
+    for (i, d) in digits.into_iter().enumerate() {
+        if i == point {
+            result.push('.');
+        }
+        result.push(d as char);
+    }
+
+    result
+}
+
+/// Writes `n` in scientific notation with `decimals` decimal places (as few
+/// as needed if [`None`]) and an exponent of at least two digits, e.g.
+/// `1.50e-07`.
+fn to_scientific(n: f64, decimals: Option<usize>) -> String {
+    let text = match decimals {
+        Some(decimals) => format!("{:.*e}", decimals, n),
+        None => format!("{:e}", n),
+    };
+    let (mantissa, exp) = text.split_once('e').unwrap_or((&text, "0"));
+    let (sign, exp) = match exp.strip_prefix('-') {
+        Some(exp) => ('-', exp),
+        None => ('+', exp),
+    };
+
+    format!("{}e{}{:0>2}", mantissa, sign, exp)
+}
+
+/// Inserts `separator` between groups of three digits of an integer written
+/// in plain decimal notation, e.g. `-1234567` becomes `-1,234,567`.
+fn group_digits(int: &str, separator: char) -> String {
+    let (sign, digits) = match int.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", int),
+    };
+
+    let mut result = String::from(sign);
+    for (i, d) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            result.push(separator);
+        }
+        result.push(d);
+    }
+
+    result
+}
+
+impl Number {
+    /// Creates an integer zero.
+    pub fn zero() -> Self {
+        Self::Int(0)
+    }
+
+    /// Creates an integer one.
+    pub fn one() -> Self {
+        Self::Int(1)
+    }
+
+    /// Checks if this [`Number`] is zero, using the same tolerance as
+    /// [`PartialEq`] for non-integers. Tiny non-zero floats such as `1e-14` are
+    /// zero too, so this is not a check for division by zero.
+    pub fn is_zero(&self) -> bool {
+        *self == Self::zero()
+    }
+
+    /// Checks if this [`Number`] is one, using the same tolerance as
+    /// [`PartialEq`] for non-integers.
+    pub fn is_one(&self) -> bool {
+        *self == Self::one()
+    }
+
+    /// Checks if this [`Number`] is exactly zero, without the tolerance of
+    /// [`is_zero`](Number::is_zero), e.g. to guard divisions, where tiny
+    /// divisors are still valid.
+    fn is_exact_zero(&self) -> bool {
+        match self {
+            Self::Int(n) => *n == 0,
+            Self::Flt(n) => *n == 0.0,
+            #[cfg(feature = "decimal")]
+            Self::Dec(n) => n.is_zero(),
+            #[cfg(feature = "bigint")]
+            Self::Big(n) => n.sign() == Sign::NoSign,
+            #[cfg(feature = "vector")]
+            Self::Vec(_) => false,
+        }
+    }
+
+    /// Checks if this [`Number`] is NaN. Only floats can be NaN, while a
+    /// vector is NaN if any of its elements is.
+    pub fn is_nan(&self) -> bool {
+        match self {
+            Self::Flt(n) => n.is_nan(),
+            #[cfg(feature = "vector")]
+            Self::Vec(v) => v.iter().any(Self::is_nan),
+            _ => false,
+        }
+    }
+
+    /// Checks if this [`Number`] is neither infinite nor NaN. Only floats can
+    /// be infinite or NaN, while a vector is finite if all of its elements are.
+    pub fn is_finite(&self) -> bool {
+        match self {
+            Self::Flt(n) => n.is_finite(),
+            #[cfg(feature = "vector")]
+            Self::Vec(v) => v.iter().all(Self::is_finite),
+            _ => true,
+        }
+    }
+
+    /// Checks if this [`Number`] is positive or negative infinity. Only floats
+    /// can be infinite, while a vector is infinite if any of its elements is.
+    pub fn is_infinite(&self) -> bool {
+        match self {
+            Self::Flt(n) => n.is_infinite(),
+            #[cfg(feature = "vector")]
+            Self::Vec(v) => v.iter().any(Self::is_infinite),
+            _ => false,
+        }
+    }
+
+    /// Divides this [`Number`] by `rhs` like [`Div`], but integers that do not
+    /// divide evenly result in a float instead of being truncated, e.g. `7 / 2`
+    /// is `3.5` while `6 / 2` is still the integer `3`.
+    pub fn float_div(self, rhs: Self) -> Self {
+        #[cfg(feature = "vector")]
+        if let Some(n) = self.elementwise(&rhs, Self::float_div) {
+            return n;
+        }
+
+        let is_integer = |n: &Self| match n {
+            Self::Int(_) => true,
+            #[cfg(feature = "bigint")]
+            Self::Big(_) => true,
+            _ => false,
+        };
+        if is_integer(&self)
+            && is_integer(&rhs)
+            && !self.clone().floor_mod(rhs.clone()).is_exact_zero()
+        {
+            return Self::Flt(self.to_f64() / rhs.to_f64());
+        }
+
+        self / rhs
+    }
+
+    /// Divides this [`Number`] by `rhs`, rounding the result toward negative
+    /// infinity. Division by zero behaves as with [`Div`].
+    pub fn floor_div(self, rhs: Self) -> Self {
+        // Floors the quotient of two integers, if it does not overflow.
+        fn floor_div_int(a: i128, b: i128) -> Option<i128> {
+            let q = a.checked_div(b)?;
+            if a % b != 0 && (a < 0) != (b < 0) {
+                Some(q - 1)
+            } else {
+                Some(q)
+            }
+        }
+
+        #[cfg(feature = "vector")]
+        if let Some(n) = self.elementwise(&rhs, Self::floor_div) {
+            return n;
+        }
+
+        if rhs.is_exact_zero() {
+            return self / rhs;
+        }
+
+        #[cfg(feature = "decimal")]
+        if let Some(n) = self.with_decimals(&rhs, |a, b| a.checked_div(b).map(|n| n.floor())) {
+            return Self::Dec(n);
+        }
+
+        #[cfg(feature = "bigint")]
+        if let Some(n) = self.with_bigints(&rhs, floor_div_int, |a, b| {
+            let q = &a / &b;
+            if &q * &b != a && (a.sign() == Sign::Minus) != (b.sign() == Sign::Minus) {
+                q - 1
+            } else {
+                q
+            }
+        }) {
+            return n;
+        }
+
+        match (self, rhs) {
+            // Integers that overflow fall back to float.
+            (Self::Int(a), Self::Int(b)) => {
+                floor_div_int(a, b).map_or(Self::Flt(math::floor(a as f64 / b as f64)), Self::Int)
+            }
+            (a, b) => Self::Flt(math::floor(a.to_f64() / b.to_f64())),
+        }
+    }
+
+    /// Raises this [`Number`] to the power of `exp`. An integer raised to a
+    /// non-negative integer stays an integer, and so does a decimal raised to
+    /// an integer, unless the result overflows. Anything else is computed with
+    /// floats. Zero to a negative power is a division by zero, resulting in
+    /// infinity.
+    pub fn pow(self, exp: Self) -> Self {
+        #[cfg(feature = "vector")]
+        if let Some(n) = self.elementwise(&exp, Self::pow) {
+            return n;
+        }
+
+        // Zero to a negative power is a division by zero.
+        if self.is_exact_zero() && exp < Self::zero() {
+            return Self::one() / self.pow(-exp);
+        }
+
+        // Integer base and exponent are kept as integer.
+        #[cfg(feature = "bigint")]
+        if let Some(n) = self.pow_bigint(&exp) {
+            return n;
+        }
+
+        // Results that overflow fall back to float below.
+        if let (Self::Int(n), Self::Int(m)) = (&self, &exp) {
+            if *m >= 0 {
+                if let Some(n) = u32::try_from(*m).ok().and_then(|m| n.checked_pow(m)) {
+                    return Self::Int(n);
+                }
+            } else {
+                return Self::Flt(math::pow(*n as f64, *m as f64));
+            }
+        }
+
+        // Decimal base and integer exponent are kept as decimal.
+        #[cfg(feature = "decimal")]
+        if let (Self::Dec(n), Self::Int(m)) = (&self, &exp) {
+            if let Some(n) = i64::try_from(*m).ok().and_then(|m| n.checked_powi(m)) {
+                return Self::Dec(n);
+            }
+        }
+
+        // Otherwise, both are converted to float.
+        Self::Flt(math::pow(self.to_f64(), exp.to_f64()))
+    }
+
+    /// Finds the bitwise exclusive or of two integers. Other numbers, including
+    /// floats without decimal places, result in `NaN`.
+    pub fn xor(self, rhs: Self) -> Self {
+        #[cfg(feature = "vector")]
+        if let Some(n) = self.elementwise(&rhs, Self::xor) {
+            return n;
+        }
+
+        #[cfg(feature = "bigint")]
+        if let Some(n) = self.with_bigints(&rhs, |a, b| Some(a ^ b), |a, b| a ^ b) {
+            return n;
+        }
+
+        match (self, rhs) {
+            (Self::Int(a), Self::Int(b)) => Self::Int(a ^ b),
+            _ => Self::Flt(f64::NAN),
+        }
+    }
+
+    /// Finds the remainder of [`floor_div`](Number::floor_div), which has the
+    /// sign of `rhs`. Division by zero results in `NaN`.
+    pub fn floor_mod(self, rhs: Self) -> Self {
+        // Moves a non-zero remainder to the sign of the divisor.
+        fn floor_mod_int(a: i128, b: i128) -> Option<i128> {
+            let r = a.checked_rem(b)?;
+            if r != 0 && (r < 0) != (b < 0) {
+                Some(r + b)
+            } else {
+                Some(r)
+            }
+        }
+
+        #[cfg(feature = "vector")]
+        if let Some(n) = self.elementwise(&rhs, Self::floor_mod) {
+            return n;
+        }
+
+        if rhs.is_exact_zero() {
+            return Self::Flt(f64::NAN);
+        }
+
+        #[cfg(feature = "decimal")]
+        if let Some(n) = self.with_decimals(&rhs, |a, b| {
+            let r = a.checked_rem(b)?;
+            if !r.is_zero() && r.is_sign_negative() != b.is_sign_negative() {
+                r.checked_add(b)
+            } else {
+                Some(r)
+            }
+        }) {
+            return Self::Dec(n);
+        }
+
+        #[cfg(feature = "bigint")]
+        if let Some(n) = self.with_bigints(&rhs, floor_mod_int, |a, b| {
+            let r = &a % &b;
+            if r.sign() != Sign::NoSign && (r.sign() == Sign::Minus) != (b.sign() == Sign::Minus) {
+                r + b
+            } else {
+                r
+            }
+        }) {
+            return n;
+        }
+
+        match (self, rhs) {
+            // Only `i128::MIN % -1` overflows, which has no remainder.
+            (Self::Int(a), Self::Int(b)) => Self::Int(floor_mod_int(a, b).unwrap_or(0)),
+            (a, b) => {
+                let (a, b) = (a.to_f64(), b.to_f64());
+                let r = a % b;
+                if r != 0.0 && (r < 0.0) != (b < 0.0) {
+                    Self::Flt(r + b)
+                } else {
+                    Self::Flt(r)
+                }
+            }
+        }
+    }
+
+    /// Formats this [`Number`] as a percentage with a fixed number of decimal
+    /// places, e.g. `0.25` is formatted as `25.0%` with one decimal place.
+    pub fn to_percent_string(&self, decimals: usize) -> String {
+        format!("{:.*}%", decimals, self.to_f64() * 100.0)
+    }
+
+    /// Formats this [`Number`] with exactly `decimals` decimal places, e.g. `3`
+    /// is formatted as `3.00` and `-2.675` as `-2.68` with two decimal places.
+    /// Halves are rounded away from zero, as the number is written, not as it
+    /// is stored. `NaN` and infinities are formatted as usual.
+    pub fn to_fixed(&self, decimals: usize) -> String {
+        match self {
+            Self::Flt(n) if !n.is_finite() => self.to_string(),
+            #[cfg(feature = "vector")]
+            Self::Vec(v) => {
+                let elements = v.iter().map(|n| n.to_fixed(decimals)).collect::<Vec<_>>();
+                format!("[{}]", elements.join(", "))
+            }
+            n => round_fixed(&n.to_string(), decimals),
+        }
+    }
+
+    /// Formats this [`Number`] using custom [`FormatOptions`], e.g. to print
+    /// `NaN` as `undefined` or infinity as `∞`.
+    pub fn to_string_with_opts(&self, opts: &FormatOptions) -> String {
+        self.to_string_with_layout(opts, &NumberLayout::default())
+    }
+
+    /// Formats this [`Number`] like [`to_string_with_opts`](Number::to_string_with_opts),
+    /// laid out as set by `layout`, e.g. to print one thousand as `1,000.00`.
+    pub fn to_string_with_layout(&self, opts: &FormatOptions, layout: &NumberLayout) -> String {
+        let text = match self {
+            Self::Flt(n) if n.is_nan() => return opts.nan.clone(),
+            Self::Flt(n) if *n == f64::INFINITY => return opts.inf.clone(),
+            Self::Flt(n) if *n == f64::NEG_INFINITY => return opts.neg_inf.clone(),
+            #[cfg(feature = "vector")]
+            Self::Vec(v) => {
+                let elements = v
+                    .iter()
+                    .map(|n| n.to_string_with_layout(opts, layout))
+                    .collect::<Vec<_>>();
+                return format!("[{}]", elements.join(", "));
+            }
+            n if n.is_scientific(layout.scientific_threshold) => {
+                return to_scientific(n.to_f64(), layout.decimals).replacen(
+                    '.',
+                    &layout.decimal_separator.to_string(),
+                    1,
+                );
+            }
+            n => match layout.decimals {
+                Some(decimals) => n.to_fixed(decimals),
+                None => n.to_string(),
+            },
+        };
+
+        let (int, frac) = match text.split_once('.') {
+            Some((int, frac)) => (int, Some(frac)),
+            None => (text.as_str(), None),
+        };
+        let mut result = match layout.group_separator {
+            Some(separator) => group_digits(int, separator),
+            None => int.to_string(),
+        };
+        if let Some(frac) = frac {
+            result.push(layout.decimal_separator);
+            result.push_str(frac);
+        }
+
+        result
+    }
+
+    /// Checks if this non-zero [`Number`] has an exponent of at least
+    /// `threshold` in magnitude, so that it is written in scientific notation.
+    fn is_scientific(&self, threshold: Option<u32>) -> bool {
+        let Some(threshold) = threshold else {
+            return false;
+        };
+        let n = self.to_f64().abs();
+        let limit = math::pow(10.0, f64::from(threshold));
+
+        n != 0.0 && (n >= limit || n < 1.0 / limit)
+    }
+
+    /// Converts this [`Number`] to a boolean, where non-zero numbers are true.
+    /// `NaN` is neither true nor false.
+    fn to_bool(&self) -> Option<bool> {
+        if self.to_f64().is_nan() {
+            None
+        } else {
+            Some(!self.is_exact_zero())
+        }
+    }
+
+    /// Converts this [`Number`] to a floating-point number.
+    fn to_f64(&self) -> f64 {
+        match self {
+            Self::Int(n) => *n as f64,
+            Self::Flt(n) => *n,
+            #[cfg(feature = "decimal")]
+            Self::Dec(n) => n.to_f64().unwrap_or(f64::NAN),
+            #[cfg(feature = "bigint")]
+            Self::Big(n) => n.to_f64().unwrap_or(f64::NAN),
+            #[cfg(feature = "vector")]
+            Self::Vec(_) => f64::NAN,
+        }
+    }
+
+    /// Converts this [`Number`] to the nearest integer, clamped to the bounds
+    /// of [`i128`]. Halves are rounded away from zero, `NaN` and vectors
+    /// become `0`.
+    fn to_i128_saturating(&self) -> i128 {
+        match self {
+            Self::Int(n) => *n,
+            #[cfg(feature = "decimal")]
+            Self::Dec(n) => n
+                .round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+                .to_i128()
+                .unwrap_or(0),
+            #[cfg(feature = "bigint")]
+            Self::Big(n) => n.to_i128().unwrap_or(match n.sign() {
+                Sign::Minus => i128::MIN,
+                _ => i128::MAX,
+            }),
+            // Float to integer casts saturate, with NaN becoming 0.
+            n => math::round(n.to_f64()) as i128,
+        }
+    }
+
+    /// Finds the absolute value of this [`Number`], which must not be a
+    /// vector.
+    fn abs(self) -> Self {
+        match self {
+            Self::Flt(n) => Self::Flt(n.abs()),
+            #[cfg(feature = "decimal")]
+            Self::Dec(n) => Self::Dec(n.abs()),
+            // Integers are compared exactly.
+            n if n < Self::zero() => -n,
+            n => n,
+        }
+    }
+
+    /// Snaps a float to the nearest integer if it is within `tol` of it, see
+    /// [`Node::evaluate_clean`].
+    fn clean(self, tol: f64) -> Self {
+        match self {
+            Self::Flt(n) => {
+                let rounded = math::round(n);
+                if (n - rounded).abs() <= tol && rounded.abs() < i128::MAX as f64 {
+                    Self::Int(rounded as i128)
+                } else {
+                    self
+                }
+            }
+            #[cfg(feature = "vector")]
+            Self::Vec(v) => Self::Vec(v.into_iter().map(|n| n.clean(tol)).collect()),
+            n => n,
+        }
+    }
+
+    /// Orders two [`Number`]s totally, unlike [`PartialOrd`]. Numbers are
+    /// compared by value without any tolerance, integers exactly and floats
+    /// as with [`f64::total_cmp`], while `NaN` is greater than anything else.
+    /// Equal values are ordered by kind, with integers before decimals before
+    /// floats, and vectors are ordered lexicographically after all numbers.
+    pub fn total_cmp(&self, other: &Self) -> Ordering {
+        // Vector comparison.
+        #[cfg(feature = "vector")]
+        match (self, other) {
+            (Self::Vec(a), Self::Vec(b)) => {
+                return a
+                    .iter()
+                    .zip(b)
+                    .map(|(a, b)| a.total_cmp(b))
+                    .find(|o| o.is_ne())
+                    .unwrap_or_else(|| a.len().cmp(&b.len()))
+            }
+            (Self::Vec(_), _) => return Ordering::Greater,
+            (_, Self::Vec(_)) => return Ordering::Less,
+            _ => (),
+        }
+
+        // Integer comparison.
+        if let (Self::Int(a), Self::Int(b)) = (self, other) {
+            return a.cmp(b);
+        }
+
+        // Big integer comparison.
+        #[cfg(feature = "bigint")]
+        if let (Some(a), Some(b)) = (self.to_bigint(), other.to_bigint()) {
+            return a.cmp(&b);
+        }
+
+        // Decimal comparison, which is exact for integers too.
+        #[cfg(feature = "decimal")]
+        if let Some(o) = self.with_decimals(other, |a, b| Some(a.cmp(&b))) {
+            return o.then_with(|| self.kind_rank().cmp(&other.kind_rank()));
+        }
+
+        // Floating point comparison, where all NaNs are the same.
+        let (a, b) = (self.to_f64(), other.to_f64());
+        let ordering = match (a.is_nan(), b.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => a.total_cmp(&b),
+        };
+
+        ordering.then_with(|| self.kind_rank().cmp(&other.kind_rank()))
+    }
+
+    /// Feeds the kind and exact value of this [`Number`] to `state`, for
+    /// [`Node::content_hash`]. Floats are fed by their bit patterns.
+    fn hash_content(&self, state: &mut dyn Hasher) {
+        // Kinds are tagged explicitly, as the discriminants of `NumberKind`
+        // depend on the features enabled.
+        match self {
+            Self::Int(n) => {
+                state.write_u8(0);
+                state.write_i128(*n);
+            }
+            Self::Flt(n) => {
+                state.write_u8(1);
+                state.write_u64(n.to_bits());
+            }
+            #[cfg(feature = "decimal")]
+            Self::Dec(n) => {
+                state.write_u8(2);
+                state.write(&n.serialize());
+            }
+            #[cfg(feature = "bigint")]
+            Self::Big(n) => {
+                state.write_u8(3);
+                hash_bytes(state, &n.to_signed_bytes_le());
+            }
+            #[cfg(feature = "vector")]
+            Self::Vec(v) => {
+                state.write_u8(4);
+                state.write_u64(v.len() as u64);
+                for n in v {
+                    n.hash_content(state);
+                }
+            }
+        }
+    }
+
+    /// Generates a Rust literal for this [`Number`], see [`Node::to_rust`].
+    /// Numbers other than integers are written as `f64`, while vectors have no
+    /// equivalent.
+    fn to_rust_typed(&self) -> Option<(String, CodeType)> {
+        let n = match self {
+            Self::Int(i128::MIN) => return Some((String::from("i128::MIN"), CodeType::Int)),
+            Self::Int(n) if *n < 0 => return Some((format!("({}i128)", n), CodeType::Int)),
+            Self::Int(n) => return Some((format!("{}i128", n), CodeType::Int)),
+            #[cfg(feature = "vector")]
+            Self::Vec(_) => return None,
+            n => n.to_f64(),
+        };
+
+        let code = if n.is_nan() {
+            String::from("f64::NAN")
+        } else if n == f64::INFINITY {
+            String::from("f64::INFINITY")
+        } else if n == f64::NEG_INFINITY {
+            String::from("f64::NEG_INFINITY")
+        } else if n < 0.0 {
+            format!("({:?}f64)", n)
+        } else {
+            format!("{:?}f64", n)
+        };
+
+        Some((code, CodeType::Float))
+    }
+
+    /// Generates a C literal for this [`Number`], see [`Node::to_c`]. Numbers
+    /// other than integers within `long long` are written as `double`, while
+    /// vectors have no equivalent.
+    fn to_c_typed(&self) -> Option<(String, CodeType)> {
+        let n = match self {
+            // The smallest `long long` cannot be written as a negated literal.
+            Self::Int(n) if *n > i64::MIN as i128 && *n < 0 => {
+                return Some((format!("({}LL)", n), CodeType::Int))
+            }
+            Self::Int(n) if *n >= 0 && *n <= i64::MAX as i128 => {
+                return Some((format!("{}LL", n), CodeType::Int))
+            }
+            #[cfg(feature = "vector")]
+            Self::Vec(_) => return None,
+            n => n.to_f64(),
+        };
+
+        let code = if n.is_nan() {
+            String::from("NAN")
+        } else if n == f64::INFINITY {
+            String::from("INFINITY")
+        } else if n == f64::NEG_INFINITY {
+            String::from("(-INFINITY)")
+        } else if n < 0.0 {
+            format!("({:?})", n)
+        } else {
+            format!("{:?}", n)
+        };
+
+        Some((code, CodeType::Float))
+    }
+
+    /// Returns the kind of this [`Number`], e.g. [`NumberKind::Int`] for
+    /// `Number::Int(2)`.
+    pub fn kind(&self) -> NumberKind {
+        match self {
+            Self::Int(_) => NumberKind::Int,
+            Self::Flt(_) => NumberKind::Flt,
+            #[cfg(feature = "decimal")]
+            Self::Dec(_) => NumberKind::Dec,
+            #[cfg(feature = "bigint")]
+            Self::Big(_) => NumberKind::Big,
+            #[cfg(feature = "vector")]
+            Self::Vec(_) => NumberKind::Vec,
+        }
+    }
+
+    /// Converts this [`Number`] to a float, e.g. `Number::Int(2)` becomes
+    /// `Number::Flt(2.0)`. Vectors are converted elementwise.
+    pub fn promote_to_float(self) -> Self {
+        match self {
+            #[cfg(feature = "vector")]
+            Self::Vec(v) => Self::Vec(v.into_iter().map(Self::promote_to_float).collect()),
+            n => Self::Flt(n.to_f64()),
+        }
+    }
+
+    /// Ranks the kind of this [`Number`] for [`total_cmp`](Number::total_cmp),
+    /// where all integers rank the same.
+    fn kind_rank(&self) -> u8 {
+        match self.kind() {
+            NumberKind::Int => 0,
+            #[cfg(feature = "bigint")]
+            NumberKind::Big => 0,
+            #[cfg(feature = "decimal")]
+            NumberKind::Dec => 1,
+            NumberKind::Flt => 2,
+            #[cfg(feature = "vector")]
+            NumberKind::Vec => 3,
+        }
+    }
+
+    /// Finds the Euclidean norm of this [`Number`], which is its absolute value
+    /// unless it is a vector.
+    #[cfg(feature = "vector")]
+    fn norm(&self) -> f64 {
+        match self {
+            Self::Vec(v) => v.iter().fold(0.0, |acc, n| math::hypot(acc, n.norm())),
+            n => n.to_f64().abs(),
+        }
+    }
+
+    /// Finds the dot product of two vectors, which must have the same length.
+    /// Otherwise, the result is `NaN`, see [`check_lengths`](Number::check_lengths).
+    #[cfg(feature = "vector")]
+    fn dot(&self, other: &Self) -> Self {
+        match (self, other) {
+            (Self::Vec(a), Self::Vec(b)) if a.len() == b.len() => {
+                a.iter().zip(b).fold(Self::zero(), |mut acc, (a, b)| {
+                    acc += a * b;
+                    acc
+                })
+            }
+            _ => Self::Flt(f64::NAN),
+        }
+    }
+
+    /// Performs an operation elementwise if either number is a vector, where a
+    /// single number is combined with every element of the vector.
+    ///
+    /// Returns [`None`] if neither number is a vector.
+    #[cfg(feature = "vector")]
+    fn elementwise(&self, other: &Self, op: impl Fn(Self, Self) -> Self) -> Option<Self> {
+        let elements = match (self, other) {
+            (Self::Vec(a), Self::Vec(b)) if a.len() != b.len() => return Some(Self::Flt(f64::NAN)),
+            (Self::Vec(a), Self::Vec(b)) => a
+                .iter()
+                .zip(b)
+                .map(|(a, b)| op(a.clone(), b.clone()))
+                .collect(),
+            (Self::Vec(a), b) => a.iter().map(|a| op(a.clone(), b.clone())).collect(),
+            (a, Self::Vec(b)) => b.iter().map(|b| op(a.clone(), b.clone())).collect(),
+            _ => return None,
+        };
+
+        Some(Self::Vec(elements))
+    }
+
+    /// Checks that two numbers can be combined elementwise, i.e. that vectors
+    /// combined with each other, including nested ones, have the same length.
+    #[cfg(feature = "vector")]
+    fn check_lengths(&self, other: &Self) -> Result<(), EvalError> {
+        match (self, other) {
+            (Self::Vec(a), Self::Vec(b)) if a.len() != b.len() => Err(EvalError::LengthMismatch {
+                left: a.len(),
+                right: b.len(),
+            }),
+            (Self::Vec(a), Self::Vec(b)) => {
+                a.iter().zip(b).try_for_each(|(a, b)| a.check_lengths(b))
+            }
+            (Self::Vec(a), b) => a.iter().try_for_each(|a| a.check_lengths(b)),
+            (a, Self::Vec(b)) => b.iter().try_for_each(|b| a.check_lengths(b)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Performs an operation on the decimal representations of two numbers.
+    ///
+    /// Returns [`None`] if neither number is a decimal, if either cannot be
+    /// represented as a decimal (e.g. a float), or if the operation itself
+    /// fails, e.g. due to overflow.
+    #[cfg(feature = "decimal")]
+    fn with_decimals<T>(
+        &self,
+        other: &Self,
+        op: impl FnOnce(Decimal, Decimal) -> Option<T>,
+    ) -> Option<T> {
+        let to_decimal = |n: &Self| match n {
+            Self::Int(n) => Decimal::from_i128(*n),
+            Self::Dec(n) => Some(*n),
+            _ => None,
+        };
+
+        match (self, other) {
+            (Self::Dec(_), _) | (_, Self::Dec(_)) => op(to_decimal(self)?, to_decimal(other)?),
+            _ => None,
+        }
+    }
+
+    /// Creates a [`Number`] from a big integer, which is stored as a normal
+    /// integer if it fits.
+    #[cfg(feature = "bigint")]
+    fn from_bigint(n: BigInt) -> Self {
+        match i128::try_from(&n) {
+            Ok(n) => Self::Int(n),
+            Err(_) => Self::Big(n),
+        }
+    }
+
+    /// Converts this [`Number`] to a big integer, if it is an integer.
+    #[cfg(feature = "bigint")]
+    fn to_bigint(&self) -> Option<BigInt> {
+        match self {
+            Self::Int(n) => Some(BigInt::from(*n)),
+            Self::Big(n) => Some(n.clone()),
+            _ => None,
+        }
+    }
+
+    /// Performs an operation on two integers, falling back to big integers if
+    /// the operation on normal integers overflows.
+    ///
+    /// Returns [`None`] if either number is not an integer.
+    #[cfg(feature = "bigint")]
+    fn with_bigints(
+        &self,
+        other: &Self,
+        op: impl FnOnce(i128, i128) -> Option<i128>,
+        big_op: impl FnOnce(BigInt, BigInt) -> BigInt,
+    ) -> Option<Self> {
+        if let (Self::Int(a), Self::Int(b)) = (self, other) {
+            if let Some(n) = op(*a, *b) {
+                return Some(Self::Int(n));
+            }
+        }
+
+        Some(Self::from_bigint(big_op(
+            self.to_bigint()?,
+            other.to_bigint()?,
+        )))
+    }
+
+    /// Raises an integer to a non-negative integer power, falling back to big
+    /// integers if the result overflows.
+    ///
+    /// Returns [`None`] if the operands are not suitable, or if the result
+    /// would exceed [`MAX_BIGINT_BITS`].
+    #[cfg(feature = "bigint")]
+    fn pow_bigint(&self, exp: &Self) -> Option<Self> {
+        let exp = match exp {
+            Self::Int(m) => u32::try_from(*m).ok()?,
+            _ => return None,
+        };
+
+        if let Self::Int(n) = self {
+            if let Some(n) = n.checked_pow(exp) {
+                return Some(Self::Int(n));
+            }
+        }
+
+        let base = self.to_bigint()?;
+        if base.bits().saturating_mul(exp as u64) > MAX_BIGINT_BITS {
+            return None;
+        }
+
+        Some(Self::from_bigint(base.pow(exp)))
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        // Vector comparison.
+        #[cfg(feature = "vector")]
+        match (self, other) {
+            (Self::Vec(a), Self::Vec(b)) => return a == b,
+            (Self::Vec(_), _) | (_, Self::Vec(_)) => return false,
+            _ => (),
+        }
+
+        // Integer comparison.
+        if let Self::Int(a) = self {
+            if let Self::Int(b) = other {
+                return a == b;
+            }
+        }
+
+        // Big integer comparison.
+        #[cfg(feature = "bigint")]
+        if let (Some(a), Some(b)) = (self.to_bigint(), other.to_bigint()) {
+            return a == b;
+        }
+
+        // Decimal comparison.
+        #[cfg(feature = "decimal")]
+        if let Some(eq) = self.with_decimals(other, |a, b| Some(a == b)) {
+            return eq;
+        }
+
+        // Floating point comparison.
+        approx_eq(self.to_f64(), other.to_f64())
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        // Vectors are only ordered if they are equal.
+        #[cfg(feature = "vector")]
+        if matches!(self, Self::Vec(_)) || matches!(other, Self::Vec(_)) {
+            return (self == other).then_some(Ordering::Equal);
+        }
+
+        // Integer comparison.
+        if let Self::Int(a) = self {
+            if let Self::Int(b) = other {
+                return a.partial_cmp(b);
+            }
+        }
+
+        // Big integer comparison.
+        #[cfg(feature = "bigint")]
+        if let (Some(a), Some(b)) = (self.to_bigint(), other.to_bigint()) {
+            return a.partial_cmp(&b);
+        }
+
+        // Decimal comparison.
+        #[cfg(feature = "decimal")]
+        if let Some(o) = self.with_decimals(other, |a, b| Some(a.cmp(&b))) {
+            return Some(o);
+        }
+
+        // Floating point comparison.
+        let a = self.to_f64();
+        let b = other.to_f64();
+
+        // Numbers within tolerance are equal, consistent with PartialEq.
+        a.partial_cmp(&b)
+            .map(|o| if approx_eq(a, b) { Ordering::Equal } else { o })
+    }
+}
+
+impl Add for Number {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "vector")]
+        if let Some(n) = self.elementwise(&rhs, Self::add) {
+            return n;
+        }
+
+        #[cfg(feature = "decimal")]
+        if let Some(n) = self.with_decimals(&rhs, |a, b| a.checked_add(b)) {
+            return Self::Dec(n);
+        }
+
+        #[cfg(feature = "bigint")]
+        if let Some(n) = self.with_bigints(&rhs, i128::checked_add, |a, b| a + b) {
+            return n;
+        }
+
+        match (self, rhs) {
+            // Integers that overflow fall back to float.
+            (Self::Int(a), Self::Int(b)) => a
+                .checked_add(b)
+                .map_or(Self::Flt(a as f64 + b as f64), Self::Int),
+            (a, b) => Self::Flt(a.to_f64() + b.to_f64()),
+        }
+    }
+}
+
+impl Sub for Number {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "vector")]
+        if let Some(n) = self.elementwise(&rhs, Self::sub) {
+            return n;
+        }
+
+        #[cfg(feature = "decimal")]
+        if let Some(n) = self.with_decimals(&rhs, |a, b| a.checked_sub(b)) {
+            return Self::Dec(n);
+        }
+
+        #[cfg(feature = "bigint")]
+        if let Some(n) = self.with_bigints(&rhs, i128::checked_sub, |a, b| a - b) {
+            return n;
+        }
+
+        match (self, rhs) {
+            // Integers that overflow fall back to float.
+            (Self::Int(a), Self::Int(b)) => a
+                .checked_sub(b)
+                .map_or(Self::Flt(a as f64 - b as f64), Self::Int),
+            (a, b) => Self::Flt(a.to_f64() - b.to_f64()),
+        }
+    }
+}
+
+impl Mul for Number {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "vector")]
+        if let Some(n) = self.elementwise(&rhs, Self::mul) {
+            return n;
+        }
+
+        #[cfg(feature = "decimal")]
+        if let Some(n) = self.with_decimals(&rhs, |a, b| a.checked_mul(b)) {
+            return Self::Dec(n);
+        }
+
+        #[cfg(feature = "bigint")]
+        if let Some(n) = self.with_bigints(&rhs, i128::checked_mul, |a, b| a * b) {
+            return n;
+        }
+
+        match (self, rhs) {
+            // Integers that overflow fall back to float.
+            (Self::Int(a), Self::Int(b)) => a
+                .checked_mul(b)
+                .map_or(Self::Flt(a as f64 * b as f64), Self::Int),
+            (a, b) => Self::Flt(a.to_f64() * b.to_f64()),
+        }
+    }
+}
+
+impl Div for Number {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "vector")]
+        if let Some(n) = self.elementwise(&rhs, Self::div) {
+            return n;
+        }
+
+        // Division by zero yields an infinity with the appropriate sign, except
+        // for 0/0 (and NaN/0) which is undefined.
+        if rhs.is_exact_zero() {
+            let (a, b) = (self.to_f64(), rhs.to_f64());
+            if self.is_exact_zero() || a.is_nan() {
+                return Self::Flt(f64::NAN);
+            }
+
+            return Self::Flt(f64::INFINITY.copysign(a.signum() * b.signum()));
+        }
+
+        #[cfg(feature = "decimal")]
+        if let Some(n) = self.with_decimals(&rhs, |a, b| a.checked_div(b)) {
+            return Self::Dec(n);
+        }
+
+        #[cfg(feature = "bigint")]
+        if let Some(n) = self.with_bigints(&rhs, i128::checked_div, |a, b| a / b) {
+            return n;
+        }
+
+        match (self, rhs) {
+            // Integers that overflow fall back to float.
+            (Self::Int(a), Self::Int(b)) => a
+                .checked_div(b)
+                .map_or(Self::Flt(a as f64 / b as f64), Self::Int),
+            (a, b) => Self::Flt(a.to_f64() / b.to_f64()),
+        }
+    }
+}
+
+impl Neg for Number {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        match self {
+            #[cfg(feature = "bigint")]
+            Self::Int(i128::MIN) => Self::Big(-BigInt::from(i128::MIN)),
+            Self::Int(a) => a.checked_neg().map_or(Self::Flt(-(a as f64)), Self::Int),
+            Self::Flt(a) => Self::Flt(-a),
+            #[cfg(feature = "decimal")]
+            Self::Dec(a) => Self::Dec(-a),
+            #[cfg(feature = "bigint")]
+            Self::Big(a) => Self::from_bigint(-a),
+            #[cfg(feature = "vector")]
+            Self::Vec(a) => Self::Vec(a.into_iter().map(Neg::neg).collect()),
+        }
+    }
+}
+
+/// Implements an arithmetic operator for borrowed [`Number`]s, as well as its
+/// compound assignment, in terms of the operator for owned [`Number`]s.
+macro_rules! impl_number_ops {
+    ($($op: ident, $method: ident, $assign_op: ident, $assign_method: ident;)*) => {
+        $(
+            impl $op<&Number> for &Number {
+                type Output = Number;
+                fn $method(self, rhs: &Number) -> Self::Output {
+                    self.clone().$method(rhs.clone())
+                }
+            }
+
+            impl $assign_op for Number {
+                fn $assign_method(&mut self, rhs: Self) {
+                    let lhs = mem::take(self);
+                    *self = lhs.$method(rhs);
+                }
+            }
+
+            impl $assign_op<&Number> for Number {
+                fn $assign_method(&mut self, rhs: &Number) {
+                    self.$assign_method(rhs.clone());
+                }
+            }
+        )*
+    };
+}
+
+impl_number_ops! {
+    Add, add, AddAssign, add_assign;
+    Sub, sub, SubAssign, sub_assign;
+    Mul, mul, MulAssign, mul_assign;
+    Div, div, DivAssign, div_assign;
+}
+
+/// Implements arithmetic between [`Number`]s and primitive numbers on either
+/// side, converting the primitive with [`From`], e.g. `n + 1` or `2.0 * n`.
+macro_rules! impl_primitive_ops {
+    ($($t: ty),*) => {
+        $(
+            impl_primitive_ops! {
+                $t;
+                Add, add, AddAssign, add_assign;
+                Sub, sub, SubAssign, sub_assign;
+                Mul, mul, MulAssign, mul_assign;
+                Div, div, DivAssign, div_assign;
+            }
+        )*
+    };
+    ($t: ty; $($op: ident, $method: ident, $assign_op: ident, $assign_method: ident;)*) => {
+        $(
+            impl $op<$t> for Number {
+                type Output = Number;
+                fn $method(self, rhs: $t) -> Self::Output {
+                    self.$method(Number::from(rhs))
+                }
+            }
+
+            impl $op<Number> for $t {
+                type Output = Number;
+                fn $method(self, rhs: Number) -> Self::Output {
+                    Number::from(self).$method(rhs)
+                }
+            }
+
+            impl $assign_op<$t> for Number {
+                fn $assign_method(&mut self, rhs: $t) {
+                    self.$assign_method(Number::from(rhs));
+                }
+            }
+        )*
+    };
+}
+
+impl_primitive_ops!(u8, u16, u32, u64, i8, i16, i32, i64, i128, f32, f64);
+
+/// Implements arithmetic on [`Number`]s that fails on overflow, instead of
+/// falling back to floats or big integers, in terms of the checked operations
+/// of integers and decimals and the operator for owned [`Number`]s.
+macro_rules! impl_checked_ops {
+    ($($method: ident, $op: ident, $symbol: literal;)*) => {
+        impl Number {
+            $(
+                #[doc = concat!(
+                    "Computes `self ", $symbol, " rhs`, returning [`None`] if integers or ",
+                    "decimals overflow. Floats never overflow, resulting in infinity instead. ",
+                    "Vectors are computed elementwise, failing if any element overflows."
+                )]
+                pub fn $method(self, rhs: Self) -> Option<Self> {
+                    #[cfg(feature = "vector")]
+                    {
+                        let overflowed = core::cell::Cell::new(false);
+                        let checked = |a: Self, b: Self| {
+                            a.$method(b).unwrap_or_else(|| {
+                                overflowed.set(true);
+                                Self::zero()
+                            })
+                        };
+                        if let Some(n) = self.elementwise(&rhs, checked) {
+                            return (!overflowed.get()).then_some(n);
+                        }
+                    }
+
+                    match (&self, &rhs) {
+                        (Self::Int(a), Self::Int(b)) => return a.$method(*b).map(Self::Int),
+                        #[cfg(feature = "decimal")]
+                        (Self::Dec(_), Self::Int(_) | Self::Dec(_)) | (Self::Int(_), Self::Dec(_)) => {
+                            return self.with_decimals(&rhs, |a, b| a.$method(b)).map(Self::Dec);
+                        }
+                        _ => (),
+                    }
+
+                    Some(self.$op(rhs))
+                }
+            )*
+        }
+    };
+}
+
+impl_checked_ops! {
+    checked_add, add, "+";
+    checked_sub, sub, "-";
+    checked_mul, mul, "*";
+}
+
+/// Implements conversions from [`Number`] to fixed-width integers that round,
+/// then clamp values out of range to the bounds of the integer type.
+macro_rules! impl_saturating_conversions {
+    ($($method: ident, $int: ty;)*) => {
+        impl Number {
+            $(
+                #[doc = concat!(
+                    "Converts this [`Number`] to the nearest [`", stringify!($int), "`], ",
+                    "clamping values out of range to its bounds. Halves are rounded away ",
+                    "from zero, `NaN` and vectors become `0`."
+                )]
+                pub fn $method(&self) -> $int {
+                    self.to_i128_saturating()
+                        .clamp(<$int>::MIN as i128, <$int>::MAX as i128) as $int
+                }
+            )*
+        }
+    };
+}
+
+impl_saturating_conversions! {
+    to_i8_saturating, i8;
+    to_i16_saturating, i16;
+    to_i32_saturating, i32;
+    to_i64_saturating, i64;
+    to_u8_saturating, u8;
+    to_u16_saturating, u16;
+    to_u32_saturating, u32;
+    to_u64_saturating, u64;
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Flt(n) => write!(f, "{}", n),
+            Self::Int(n) => write!(f, "{}", n),
+            #[cfg(feature = "decimal")]
+            Self::Dec(n) => write!(f, "{}", n),
+            #[cfg(feature = "bigint")]
+            Self::Big(n) => write!(f, "{}", n),
+            #[cfg(feature = "vector")]
+            Self::Vec(v) => {
+                write!(f, "[")?;
+                for (i, n) in v.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", n)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// The default [`Number`] is an integer zero, see [`Number::zero`].
+impl Default for Number {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            nan: f64::NAN.to_string(),
+            inf: f64::INFINITY.to_string(),
+            neg_inf: f64::NEG_INFINITY.to_string(),
+        }
+    }
+}
+
+impl Default for NumberLayout {
+    fn default() -> Self {
+        Self {
+            decimals: None,
+            group_separator: None,
+            decimal_separator: '.',
+            scientific_threshold: None,
+        }
+    }
+}
+
+impl NumberLayout {
+    /// Sets the number of decimal places, see [`NumberLayout::decimals`].
+    pub fn with_decimals(mut self, decimals: usize) -> NumberLayout {
+        self.decimals = Some(decimals);
+        self
+    }
+
+    /// Sets the character separating groups of three digits, see
+    /// [`NumberLayout::group_separator`].
+    pub fn with_group_separator(mut self, separator: char) -> NumberLayout {
+        self.group_separator = Some(separator);
+        self
+    }
+
+    /// Sets the character separating the integer part from the decimal
+    /// places, see [`NumberLayout::decimal_separator`].
+    pub fn with_decimal_separator(mut self, separator: char) -> NumberLayout {
+        self.decimal_separator = separator;
+        self
+    }
+
+    /// Sets the smallest exponent written in scientific notation, see
+    /// [`NumberLayout::scientific_threshold`].
+    pub fn with_scientific_threshold(mut self, threshold: u32) -> NumberLayout {
+        self.scientific_threshold = Some(threshold);
+        self
+    }
+}
+
+/// Booleans are converted to `1` (true) or `0` (false).
+impl From<bool> for Number {
+    fn from(b: bool) -> Self {
+        Self::Int(b as i128)
+    }
+}
+
+impl From<u8> for Number {
+    fn from(n: u8) -> Self {
+        Self::Int(n as i128)
+    }
+}
+
+impl From<u16> for Number {
     fn from(n: u16) -> Self {
         Self::Int(n as i128)
     }
-}
+}
+
+impl From<u32> for Number {
+    fn from(n: u32) -> Self {
+        Self::Int(n as i128)
+    }
+}
+
+impl From<u64> for Number {
+    fn from(n: u64) -> Self {
+        Self::Int(n as i128)
+    }
+}
+
+impl From<i8> for Number {
+    fn from(n: i8) -> Self {
+        Self::Int(n as i128)
+    }
+}
+
+impl From<i16> for Number {
+    fn from(n: i16) -> Self {
+        Self::Int(n as i128)
+    }
+}
+
+impl From<i32> for Number {
+    fn from(n: i32) -> Self {
+        Self::Int(n as i128)
+    }
+}
+
+impl From<i64> for Number {
+    fn from(n: i64) -> Self {
+        Self::Int(n as i128)
+    }
+}
+
+impl From<i128> for Number {
+    fn from(n: i128) -> Self {
+        Self::Int(n)
+    }
+}
+
+impl From<f32> for Number {
+    fn from(n: f32) -> Self {
+        Self::Flt(n as f64)
+    }
+}
+
+impl From<f64> for Number {
+    fn from(n: f64) -> Self {
+        Self::Flt(n)
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl From<BigInt> for Number {
+    fn from(n: BigInt) -> Self {
+        Self::from_bigint(n)
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<Decimal> for Number {
+    fn from(n: Decimal) -> Self {
+        Self::Dec(n)
+    }
+}
+
+impl PartialEq for TotalNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for TotalNumber {}
+
+impl PartialOrd for TotalNumber {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TotalNumber {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl From<Number> for TotalNumber {
+    fn from(n: Number) -> Self {
+        Self(n)
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl DecimalContext {
+    /// Rounds the decimals in `n`, including those in vectors, to the
+    /// precision of this [`DecimalContext`].
+    pub fn apply(&self, n: Number) -> Number {
+        let strategy = match self.rounding {
+            DecimalRounding::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            DecimalRounding::HalfEven => RoundingStrategy::MidpointNearestEven,
+            DecimalRounding::Truncate => RoundingStrategy::ToZero,
+            DecimalRounding::Floor => RoundingStrategy::ToNegativeInfinity,
+            DecimalRounding::Ceiling => RoundingStrategy::ToPositiveInfinity,
+        };
+
+        match n {
+            Number::Dec(n) => Number::Dec(n.round_dp_with_strategy(self.scale, strategy)),
+            #[cfg(feature = "vector")]
+            Number::Vec(v) => Number::Vec(v.into_iter().map(|n| self.apply(n)).collect()),
+            n => n,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// 2. impls for BinaryAction.
+// -----------------------------------------------------------------------------
+
+impl BinaryAction {
+    pub fn evaluate(&self, left: Number, right: Number) -> Number {
+        #[cfg(feature = "vector")]
+        if let Some(n) = left.elementwise(&right, |a, b| self.evaluate(a, b)) {
+            return n;
+        }
+
+        match self {
+            Self::Add => left + right,
+            Self::Sub => left - right,
+            Self::Mul => left * right,
+            Self::Div => left / right,
+            Self::FloatDiv => left.float_div(right),
+            Self::FloorDiv => left.floor_div(right),
+            Self::Mod => left.floor_mod(right),
+            Self::Less => Number::from(left < right),
+            Self::LessEqual => Number::from(left <= right),
+            Self::Greater => Number::from(left > right),
+            Self::GreaterEqual => Number::from(left >= right),
+            Self::Equal => Number::from(left == right),
+            Self::NotEqual => Number::from(left != right),
+            Self::And | Self::Or => self
+                .short_circuit(&left)
+                .unwrap_or_else(|| right.to_bool().map_or(Number::Flt(f64::NAN), Number::from)),
+            Self::Pow => left.pow(right),
+            Self::Xor => left.xor(right),
+        }
+    }
+
+    /// Symbol of the operator performing this [`BinaryAction`].
+    fn symbol(&self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::FloatDiv => "fdiv",
+            Self::FloorDiv => "//",
+            Self::Mod => "%",
+            Self::Pow => "^",
+            Self::Xor => "xor",
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterEqual => ">=",
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::And => "&&",
+            Self::Or => "||",
+        }
+    }
+
+    /// Finds the result of a logical operator from its left-hand side operand
+    /// alone, if possible. A `NaN` operand is neither true nor false, resulting
+    /// in `NaN`.
+    ///
+    /// Returns [`None`] if the right-hand side operand is needed, or if this
+    /// is not a logical operator.
+    pub fn short_circuit(&self, left: &Number) -> Option<Number> {
+        match (self, left.to_bool()) {
+            (Self::And | Self::Or, None) => Some(Number::Flt(f64::NAN)),
+            (Self::And, Some(false)) => Some(Number::from(false)),
+            (Self::Or, Some(true)) => Some(Number::from(true)),
+            _ => None,
+        }
+    }
+
+    /// How tightly this [`BinaryAction`] binds, see [`Node::precedence`].
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Or => 1,
+            Self::And => 2,
+            Self::Less
+            | Self::LessEqual
+            | Self::Greater
+            | Self::GreaterEqual
+            | Self::Equal
+            | Self::NotEqual => 4,
+            Self::Add | Self::Sub => 5,
+            Self::Mul | Self::Div | Self::FloatDiv | Self::FloorDiv | Self::Mod => 6,
+            Self::Pow | Self::Xor => 8,
+        }
+    }
+}
+
+impl Display for BinaryAction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Op({})", self.symbol())
+    }
+}
+
+// -----------------------------------------------------------------------------
+// 3. impls for BinaryNode.
+// -----------------------------------------------------------------------------
+
+/// Converts an operand to a tree for [`Node::to_tree_limited`], where
+/// `max_depth` is the limit of its parent.
+fn to_tree_operand(operand: &dyn Node, max_depth: usize) -> Vec<String> {
+    if max_depth > 1 {
+        return operand.to_tree_limited(max_depth - 1);
+    }
+
+    let count = operand.node_count();
+    vec![format!(
+        "... ({} node{})",
+        count,
+        if count == 1 { "" } else { "s" }
+    )]
+}
+
+/// Formats an operand as an infix expression, wrapped in parentheses if it
+/// binds less tightly than `precedence`.
+fn to_infix_operand(operand: &dyn Node, precedence: u8) -> String {
+    if operand.precedence() < precedence {
+        format!("({})", operand.to_infix())
+    } else {
+        operand.to_infix()
+    }
+}
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for BinaryNode {
+    fn evaluate(&self) -> Number {
+        self.evaluate_indexed(&[])
+    }
+
+    fn evaluate_with(&self, vars: &dyn Variables) -> Number {
+        // Evaluate both sub-nodes, unless the right-hand side is not needed.
+        let left = self.left.evaluate_with(vars);
+        if let Some(result) = self.actor.short_circuit(&left) {
+            return result;
+        }
+        let right = self.right.evaluate_with(vars);
+
+        // Then evalute this node.
+        self.actor.evaluate(left, right)
+    }
+
+    fn try_evaluate_with(&self, vars: &dyn Variables) -> Result<Number, EvalError> {
+        vars.interrupt()?;
+        let left = self.left.try_evaluate_with(vars)?;
+        if let Some(result) = self.actor.short_circuit(&left) {
+            return Ok(result);
+        }
+        let right = self.right.try_evaluate_with(vars)?;
+
+        #[cfg(feature = "vector")]
+        left.check_lengths(&right)?;
+
+        Ok(self.actor.evaluate(left, right))
+    }
+
+    fn is_exact(&self) -> bool {
+        self.left.is_exact() && self.right.is_exact()
+    }
+
+    fn hash_content(&self, state: &mut dyn Hasher) {
+        state.write_u8(0);
+        state.write_u8(self.actor as u8);
+        self.left.hash_content(state);
+        self.right.hash_content(state);
+    }
+
+    fn structural_cmp(&self, other: &dyn Node) -> Ordering {
+        match other.as_any().downcast_ref::<BinaryNode>() {
+            Some(other) => self
+                .actor
+                .cmp(&other.actor)
+                .then_with(|| self.left.structural_cmp(other.left.as_ref()))
+                .then_with(|| self.right.structural_cmp(other.right.as_ref())),
+            None => node_rank(self).cmp(&node_rank(other.as_any())),
+        }
+    }
+
+    fn canonicalize(self: Box<Self>) -> NodeBox {
+        let BinaryNode {
+            left,
+            actor,
+            right,
+            span,
+        } = *self;
+        let (mut left, mut right) = (left.canonicalize(), right.canonicalize());
+
+        if matches!(actor, BinaryAction::Add | BinaryAction::Mul)
+            && left.structural_cmp(right.as_ref()) == Ordering::Greater
+        {
+            mem::swap(&mut left, &mut right);
+        }
+
+        Box::new(BinaryNode {
+            left,
+            actor,
+            right,
+            span,
+        })
+    }
+
+    fn partial_eval(&self, vars: &dyn Variables) -> NodeBox {
+        let left = self.left.partial_eval(vars);
+        if let Some(result) = left.as_number().and_then(|l| self.actor.short_circuit(l)) {
+            return Box::new(PlainNode::new(result));
+        }
+        let right = self.right.partial_eval(vars);
+
+        match (left.as_number(), right.as_number()) {
+            (Some(l), Some(r)) => {
+                Box::new(PlainNode::new(self.actor.evaluate(l.clone(), r.clone())))
+            }
+            _ => Box::new(BinaryNode::new(left, self.actor, right)),
+        }
+    }
+
+    fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    fn set_span(&mut self, span: Span) {
+        self.span = Some(span);
+    }
+
+    fn to_tree(&self) -> Vec<String> {
+        self.to_tree_limited(usize::MAX)
+    }
+
+    fn to_tree_with(&self, operand_tree: &dyn Fn(&dyn Node) -> Vec<String>) -> Vec<String> {
+        // Get actor.
+        let actor = self.actor.to_string();
+
+        // Process left side.
+        let mut left_tree = operand_tree(self.left.as_ref());
+        left_tree[0].insert_str(0, "`-- ");
+        for line in left_tree.iter_mut().skip(1) {
+            line.insert_str(0, "|   ");
+        }
+
+        // Process right side.
+        let mut right_tree = operand_tree(self.right.as_ref());
+        right_tree[0].insert_str(0, "`-- ");
+        for line in right_tree.iter_mut().skip(1) {
+            line.insert_str(0, "    ");
+        }
+
+        // Combine all three.
+        let mut tree = vec![actor];
+        tree.extend(left_tree);
+        tree.extend(right_tree);
+
+        tree
+    }
+
+    fn node_count(&self) -> usize {
+        1 + self.left.node_count() + self.right.node_count()
+    }
+
+    #[cfg(feature = "decimal")]
+    fn evaluate_with_context(&self, vars: &dyn Variables, ctx: &DecimalContext) -> Number {
+        let left = self.left.evaluate_with_context(vars, ctx);
+        if let Some(result) = self.actor.short_circuit(&left) {
+            return result;
+        }
+        let right = self.right.evaluate_with_context(vars, ctx);
+
+        ctx.apply(self.actor.evaluate(left, right))
+    }
+
+    fn trace(&self, vars: &dyn Variables, order: EvalOrder, steps: &mut Vec<String>) -> Number {
+        // Logical operators always start on the left, to short-circuit.
+        let logical = matches!(self.actor, BinaryAction::And | BinaryAction::Or);
+        let (left, right) = if order == EvalOrder::RightToLeft && !logical {
+            let right = self.right.trace(vars, order, steps);
+            (self.left.trace(vars, order, steps), right)
+        } else {
+            let left = self.left.trace(vars, order, steps);
+            if let Some(result) = self.actor.short_circuit(&left) {
+                steps.push(format!("{} = {}", self.to_infix(), result));
+                return result;
+            }
+            (left, self.right.trace(vars, order, steps))
+        };
+
+        let result = self.actor.evaluate(left, right);
+        steps.push(format!("{} = {}", self.to_infix(), result));
+        result
+    }
+
+    #[cfg(feature = "dimensions")]
+    fn dimension(&self, mismatches: &mut Vec<DimensionMismatch>) -> Option<DimensionTag> {
+        let left = self.left.dimension(mismatches);
+        let right = self.right.dimension(mismatches);
+
+        match self.actor {
+            BinaryAction::Add
+            | BinaryAction::Sub
+            | BinaryAction::Less
+            | BinaryAction::LessEqual
+            | BinaryAction::Greater
+            | BinaryAction::GreaterEqual
+            | BinaryAction::Equal
+            | BinaryAction::NotEqual => (),
+            _ => return None,
+        }
+
+        if let (Some(l), Some(r)) = (left, right) {
+            if l != r {
+                mismatches.push(DimensionMismatch {
+                    left: l,
+                    right: r,
+                    span: self.span,
+                });
+                return None;
+            }
+        }
+
+        // Only sums have a dimension, comparisons result in booleans.
+        match self.actor {
+            BinaryAction::Add | BinaryAction::Sub => left.or(right),
+            _ => None,
+        }
+    }
+
+    fn to_rust_typed(&self) -> Option<(String, CodeType)> {
+        let (left, right) = (self.left.to_rust_typed()?, self.right.to_rust_typed()?);
+
+        let code = match self.actor {
+            BinaryAction::Add | BinaryAction::Sub | BinaryAction::Mul | BinaryAction::Div => {
+                let (left, right, ty) = rust_unify(left, right);
+                let symbol = self.actor.symbol();
+                (format!("({} {} {})", left, symbol, right), ty)
+            }
+            BinaryAction::FloatDiv => (
+                format!("({} / {})", rust_f64(left), rust_f64(right)),
+                CodeType::Float,
+            ),
+            BinaryAction::FloorDiv => (
+                format!("({} / {}).floor()", rust_f64(left), rust_f64(right)),
+                CodeType::Float,
+            ),
+            BinaryAction::Mod => {
+                let (a, b) = (rust_f64(left), rust_f64(right));
+                (
+                    format!("({0} - {1} * ({0} / {1}).floor())", a, b),
+                    CodeType::Float,
+                )
+            }
+            // Only exponents known to fit in `i32` can be passed to `powi`.
+            BinaryAction::Pow => match self.right.as_number() {
+                Some(Number::Int(exp)) if i32::try_from(*exp).is_ok() => {
+                    (format!("{}.powi({})", rust_f64(left), exp), CodeType::Float)
+                }
+                _ => (
+                    format!("{}.powf({})", rust_f64(left), rust_f64(right)),
+                    CodeType::Float,
+                ),
+            },
+            BinaryAction::Xor => match (left, right) {
+                ((a, CodeType::Int), (b, CodeType::Int)) => {
+                    (format!("({} ^ {})", a, b), CodeType::Int)
+                }
+                _ => return None,
+            },
+            BinaryAction::And | BinaryAction::Or => (
+                format!(
+                    "(({} {} {}) as i128)",
+                    rust_bool(left),
+                    self.actor.symbol(),
+                    rust_bool(right)
+                ),
+                CodeType::Int,
+            ),
+            actor => {
+                let (left, right, _) = rust_unify(left, right);
+                (
+                    format!("(({} {} {}) as i128)", left, actor.symbol(), right),
+                    CodeType::Int,
+                )
+            }
+        };
+
+        Some(code)
+    }
+
+    fn to_c_typed(&self) -> Option<(String, CodeType)> {
+        let (left, right) = (self.left.to_c_typed()?, self.right.to_c_typed()?);
+        let ty = match (left.1, right.1) {
+            (CodeType::Int, CodeType::Int) => CodeType::Int,
+            _ => CodeType::Float,
+        };
+
+        let code = match self.actor {
+            BinaryAction::Add | BinaryAction::Sub | BinaryAction::Mul | BinaryAction::Div => (
+                format!("({} {} {})", left.0, self.actor.symbol(), right.0),
+                ty,
+            ),
+            BinaryAction::FloatDiv => (
+                format!("({} / {})", c_double(left), right.0),
+                CodeType::Float,
+            ),
+            BinaryAction::FloorDiv => (
+                format!("floor({} / {})", c_double(left), right.0),
+                CodeType::Float,
+            ),
+            BinaryAction::Mod => {
+                let (a, b) = (c_double(left), right.0);
+                (
+                    format!("({0} - {1} * floor({0} / {1}))", a, b),
+                    CodeType::Float,
+                )
+            }
+            BinaryAction::Pow => (format!("pow({}, {})", left.0, right.0), CodeType::Float),
+            BinaryAction::Xor if ty == CodeType::Int => {
+                (format!("({} ^ {})", left.0, right.0), CodeType::Int)
+            }
+            BinaryAction::Xor => return None,
+            actor => (
+                format!("({} {} {})", left.0, actor.symbol(), right.0),
+                CodeType::Int,
+            ),
+        };
+
+        Some(code)
+    }
+
+    fn to_infix(&self) -> String {
+        // Both operands of exponentiation must be atomic. Comparisons cannot be
+        // chained. Other actions are left-associative, so the right-hand side
+        // needs parentheses on ties.
+        let precedence = self.precedence();
+        let (left, right) = match self.actor {
+            BinaryAction::Pow | BinaryAction::Xor => (ATOMIC_PRECEDENCE, ATOMIC_PRECEDENCE),
+            BinaryAction::Add
+            | BinaryAction::Sub
+            | BinaryAction::Mul
+            | BinaryAction::Div
+            | BinaryAction::FloatDiv
+            | BinaryAction::FloorDiv
+            | BinaryAction::Mod
+            | BinaryAction::And
+            | BinaryAction::Or => (precedence, precedence + 1),
+            _ => (precedence + 1, precedence + 1),
+        };
+        let left = to_infix_operand(self.left.as_ref(), left);
+        let right = to_infix_operand(self.right.as_ref(), right);
+
+        match self.actor {
+            BinaryAction::Add => format!("{} + {}", left, right),
+            BinaryAction::Sub => format!("{} - {}", left, right),
+            BinaryAction::Mul => format!("{}*{}", left, right),
+            BinaryAction::Div => format!("{}/{}", left, right),
+            BinaryAction::Pow => format!("{}^{}", left, right),
+            actor => format!("{} {} {}", left, actor.symbol(), right),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        self.actor.precedence()
+    }
+
+    fn depth(&self) -> usize {
+        1 + self.left.depth().max(self.right.depth())
+    }
+}
+
+impl Display for BinaryNode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.to_tree().join("\n"))
+    }
+}
+
+impl BinaryNode {
+    /// Creates a new [`BinaryNode`].
+    #[rustfmt::skip]
+    pub fn new(
+        left: NodeBox,
+        actor: BinaryAction,
+        right: NodeBox
+    ) -> BinaryNode {
+        Self {
+            left,
+            actor,
+            right,
+            span: None,
+        }
+    }
+
+    /// Returns the left-hand side operand of this [`BinaryNode`].
+    pub fn left(&self) -> &dyn Node {
+        self.left.as_ref()
+    }
+
+    /// Returns the action performed by this [`BinaryNode`].
+    pub fn actor(&self) -> BinaryAction {
+        self.actor
+    }
+
+    /// Returns the right-hand side operand of this [`BinaryNode`].
+    pub fn right(&self) -> &dyn Node {
+        self.right.as_ref()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// 4. impls for UnaryAction.
+// -----------------------------------------------------------------------------
+
+impl UnaryAction {
+    pub fn evaluate(&self, operand: Number) -> Number {
+        // Lengths are the only actions taking vectors as a whole.
+        #[cfg(feature = "vector")]
+        if let (Number::Vec(v), false) = (&operand, *self == Self::Func(Function::Len)) {
+            return Number::Vec(v.iter().map(|n| self.evaluate(n.clone())).collect());
+        }
+
+        match self {
+            Self::Neg => -operand,
+            Self::Iden => operand,
+            Self::Not => operand
+                .to_bool()
+                .map_or(Number::Flt(f64::NAN), |b| Number::from(!b)),
+            Self::Abs => operand.abs(),
+            Self::Func(f) => UnaryAction::evaluate_function(f, operand),
+        }
+    }
+
+    /// Checks that the operand is in the domain of this action, where
+    /// [`evaluate`](UnaryAction::evaluate) would otherwise result in `NaN`.
+    /// A `NaN` operand is not checked, as it is already undefined.
+    pub fn check_domain(&self, operand: &Number) -> Result<(), EvalError> {
+        let func = match self {
+            Self::Func(func) => *func,
+            _ => return Ok(()),
+        };
+
+        // Vectors are checked elementwise, as they are evaluated.
+        #[cfg(feature = "vector")]
+        if let (Number::Vec(v), false) = (operand, func == Function::Len) {
+            return v.iter().try_for_each(|n| self.check_domain(n));
+        }
+
+        let n = operand.to_f64();
+        let undefined = match func {
+            Function::Asin | Function::Acos => n.abs() > 1.0,
+            Function::Acsc | Function::Asec => n.abs() < 1.0,
+            Function::Log10 | Function::Log2 => n <= 0.0,
+            _ => false,
+        };
+
+        if undefined {
+            return Err(EvalError::DomainError { func, value: n });
+        }
+
+        Ok(())
+    }
+
+    fn evaluate_function(func: &Function, operand: Number) -> Number {
+        // All functions are evaluated on floating-point numbers.
+        let n = operand.to_f64();
+
+        match func {
+            Function::Sin => Number::Flt(math::sin(n)),
+            Function::Cos => Number::Flt(math::cos(n)),
+            Function::Tan => Number::Flt(math::tan(n)),
+            Function::Sec => Number::Flt(math::cos(n).recip()),
+            Function::Csc => Number::Flt(math::sin(n).recip()),
+            Function::Cot => Number::Flt(math::tan(n).recip()),
+            Function::Asin => Number::Flt(math::asin(n)),
+            Function::Acos => Number::Flt(math::acos(n)),
+            Function::Atan => Number::Flt(math::atan(n)),
+            Function::Asec => Number::Flt(math::acos(n.recip())),
+            Function::Acsc => Number::Flt(math::asin(n.recip())),
+            Function::Acot => Number::Flt(math::atan(n.recip())),
+
+            // Logarithms are undefined for non-positive numbers.
+            Function::Log10 if n > 0.0 => Number::Flt(math::log10(n)),
+            Function::Log2 if n > 0.0 => Number::Flt(math::log2(n)),
+            Function::Log10 | Function::Log2 => Number::Flt(f64::NAN),
+
+            // Unlike fractional powers, cube roots of negative numbers are real.
+            Function::Cbrt => Number::Flt(math::cbrt(n)),
+
+            // Angle conversions.
+            Function::Rad => Number::Flt(n.to_radians()),
+            Function::Deg => Number::Flt(n.to_degrees()),
+
+            // Functions taking more than one operand.
+            #[cfg(feature = "vector")]
+            Function::Len => Number::Flt(operand.norm()),
+            #[cfg(feature = "vector")]
+            Function::Dot => Number::Flt(f64::NAN),
+            // Earlier results are only known to the variables.
+            Function::Hist => Number::Flt(f64::NAN),
+            Function::Hypot | Function::RoundSig | Function::PctChange | Function::If => {
+                Number::Flt(f64::NAN)
+            }
+        }
+    }
+}
+
+impl Display for UnaryAction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Neg => write!(f, "Op(-)"),
+            Self::Iden => write!(f, "Op(+)"),
+            Self::Not => write!(f, "Op(!)"),
+            Self::Abs => write!(f, "Abs"),
+            Self::Func(func) => write!(f, "Func({})", func),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// 5. impls for UnaryNode.
+// -----------------------------------------------------------------------------
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for UnaryNode {
+    fn evaluate(&self) -> Number {
+        self.evaluate_indexed(&[])
+    }
+
+    fn evaluate_with(&self, vars: &dyn Variables) -> Number {
+        // Evaluate the operand.
+        let operand = self.operand.evaluate_with(vars);
+
+        // Then evaluate this node.
+        self.apply(operand, vars)
+    }
+
+    fn try_evaluate_with(&self, vars: &dyn Variables) -> Result<Number, EvalError> {
+        vars.interrupt()?;
+        let operand = self.operand.try_evaluate_with(vars)?;
+        self.actor.check_domain(&operand)?;
+
+        // Earlier results must exist to be recalled.
+        if self.actor == UnaryAction::Func(Function::Hist) {
+            return UnaryNode::recall(vars, &operand)
+                .ok_or_else(|| EvalError::HistoryOutOfRange(operand.to_f64()));
+        }
+
+        Ok(self.apply(operand, vars))
+    }
+
+    fn is_exact(&self) -> bool {
+        // All functions are computed with floating-point numbers.
+        !matches!(self.actor, UnaryAction::Func(_)) && self.operand.is_exact()
+    }
+
+    fn hash_content(&self, state: &mut dyn Hasher) {
+        state.write_u8(1);
+        match self.actor {
+            UnaryAction::Neg => state.write_u8(0),
+            UnaryAction::Iden => state.write_u8(1),
+            UnaryAction::Not => state.write_u8(2),
+            UnaryAction::Abs => state.write_u8(3),
+            UnaryAction::Func(func) => {
+                state.write_u8(4);
+                hash_bytes(state, func.name().as_bytes());
+            }
+        }
+        self.operand.hash_content(state);
+    }
+
+    fn structural_cmp(&self, other: &dyn Node) -> Ordering {
+        match other.as_any().downcast_ref::<UnaryNode>() {
+            Some(other) => self
+                .actor
+                .cmp(&other.actor)
+                .then_with(|| self.operand.structural_cmp(other.operand.as_ref())),
+            None => node_rank(self).cmp(&node_rank(other.as_any())),
+        }
+    }
+
+    fn canonicalize(self: Box<Self>) -> NodeBox {
+        let UnaryNode {
+            actor,
+            operand,
+            span,
+        } = *self;
+
+        Box::new(UnaryNode {
+            actor,
+            operand: operand.canonicalize(),
+            span,
+        })
+    }
+
+    fn partial_eval(&self, vars: &dyn Variables) -> NodeBox {
+        let operand = self.operand.partial_eval(vars);
+
+        // Earlier results stay symbolic unless they can be recalled.
+        let value = match (operand.as_number(), self.actor) {
+            (Some(n), UnaryAction::Func(Function::Hist)) => UnaryNode::recall(vars, n),
+            (Some(n), _) => Some(self.actor.evaluate(n.clone())),
+            (None, _) => None,
+        };
+
+        match value {
+            Some(n) => Box::new(PlainNode::new(n)),
+            None => Box::new(UnaryNode::new(self.actor, operand)),
+        }
+    }
+
+    fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    fn set_span(&mut self, span: Span) {
+        self.span = Some(span);
+    }
+
+    fn to_tree(&self) -> Vec<String> {
+        self.to_tree_limited(usize::MAX)
+    }
+
+    fn to_tree_with(&self, operand_tree: &dyn Fn(&dyn Node) -> Vec<String>) -> Vec<String> {
+        // Get actor.
+        let actor = self.actor.to_string();
+
+        // Process left side.
+        let mut left_tree = operand_tree(self.operand.as_ref());
+        left_tree[0].insert_str(0, "`-- ");
+        for line in left_tree.iter_mut().skip(1) {
+            line.insert_str(0, "|   ");
+        }
+
+        // Combine.
+        left_tree.insert(0, actor);
+
+        left_tree
+    }
+
+    fn node_count(&self) -> usize {
+        1 + self.operand.node_count()
+    }
+
+    #[cfg(feature = "decimal")]
+    fn evaluate_with_context(&self, vars: &dyn Variables, ctx: &DecimalContext) -> Number {
+        let operand = self.operand.evaluate_with_context(vars, ctx);
+        ctx.apply(self.apply(operand, vars))
+    }
+
+    fn trace(&self, vars: &dyn Variables, order: EvalOrder, steps: &mut Vec<String>) -> Number {
+        let operand = self.operand.trace(vars, order, steps);
+        let result = self.apply(operand, vars);
+        steps.push(format!("{} = {}", self.to_infix(), result));
+        result
+    }
+
+    #[cfg(feature = "dimensions")]
+    fn dimension(&self, mismatches: &mut Vec<DimensionMismatch>) -> Option<DimensionTag> {
+        let dimension = self.operand.dimension(mismatches);
+        match self.actor {
+            UnaryAction::Neg | UnaryAction::Iden | UnaryAction::Abs => dimension,
+            _ => None,
+        }
+    }
+
+    fn to_rust_typed(&self) -> Option<(String, CodeType)> {
+        let (operand, ty) = self.operand.to_rust_typed()?;
+
+        let func = match self.actor {
+            UnaryAction::Neg => return Some((format!("(-{})", operand), ty)),
+            UnaryAction::Iden => return Some((operand, ty)),
+            UnaryAction::Not => {
+                let operand = rust_bool((operand, ty));
+                return Some((format!("((!{}) as i128)", operand), CodeType::Int));
+            }
+            UnaryAction::Abs => return Some((format!("{}.abs()", operand), ty)),
+            UnaryAction::Func(func) => func,
+        };
+
+        let x = rust_f64((operand, ty));
+        let code = match func {
+            Function::Sin
+            | Function::Cos
+            | Function::Tan
+            | Function::Asin
+            | Function::Acos
+            | Function::Atan
+            | Function::Log10
+            | Function::Log2
+            | Function::Cbrt => format!("{}.{}()", x, func.name()),
+            Function::Csc => format!("(1.0 / {}.sin())", x),
+            Function::Sec => format!("(1.0 / {}.cos())", x),
+            Function::Cot => format!("(1.0 / {}.tan())", x),
+            Function::Acsc => format!("(1.0 / {}).asin()", x),
+            Function::Asec => format!("(1.0 / {}).acos()", x),
+            Function::Acot => format!("(1.0 / {}).atan()", x),
+            Function::Rad => format!("{}.to_radians()", x),
+            Function::Deg => format!("{}.to_degrees()", x),
+            _ => return None,
+        };
+
+        Some((code, CodeType::Float))
+    }
+
+    fn to_c_typed(&self) -> Option<(String, CodeType)> {
+        let (x, ty) = self.operand.to_c_typed()?;
+
+        let func = match self.actor {
+            UnaryAction::Neg => return Some((format!("(-{})", x), ty)),
+            UnaryAction::Iden => return Some((x, ty)),
+            UnaryAction::Not => return Some((format!("(!{})", x), CodeType::Int)),
+            UnaryAction::Abs if ty == CodeType::Int => return Some((format!("llabs({})", x), ty)),
+            UnaryAction::Abs => return Some((format!("fabs({})", x), ty)),
+            UnaryAction::Func(func) => func,
+        };
+
+        let code = match func {
+            Function::Sin
+            | Function::Cos
+            | Function::Tan
+            | Function::Asin
+            | Function::Acos
+            | Function::Atan
+            | Function::Log10
+            | Function::Log2
+            | Function::Cbrt => format!("{}({})", func.name(), x),
+            Function::Csc => format!("(1.0 / sin({}))", x),
+            Function::Sec => format!("(1.0 / cos({}))", x),
+            Function::Cot => format!("(1.0 / tan({}))", x),
+            Function::Acsc => format!("asin(1.0 / {})", x),
+            Function::Asec => format!("acos(1.0 / {})", x),
+            Function::Acot => format!("atan(1.0 / {})", x),
+            Function::Rad => format!("({} * {:?})", x, core::f64::consts::PI / 180.0),
+            Function::Deg => format!("({} * {:?})", x, 180.0 / core::f64::consts::PI),
+            _ => return None,
+        };
+
+        Some((code, CodeType::Float))
+    }
+
+    fn to_infix(&self) -> String {
+        match self.actor {
+            // Signs can only be applied to exponentiation or atomics.
+            UnaryAction::Neg => format!(
+                "-{}",
+                to_infix_operand(self.operand.as_ref(), BinaryAction::Pow.precedence())
+            ),
+            // Identity has no effect, so it is omitted.
+            UnaryAction::Iden => self.operand.to_infix(),
+            UnaryAction::Not => format!(
+                "!{}",
+                to_infix_operand(self.operand.as_ref(), NOT_PRECEDENCE)
+            ),
+            UnaryAction::Abs => {
+                // Bars next to each other would be read as `||`.
+                let operand = self.operand.to_infix();
+                if operand.starts_with('|') || operand.ends_with('|') {
+                    format!("|({})|", operand)
+                } else {
+                    format!("|{}|", operand)
+                }
+            }
+            UnaryAction::Func(func) => format!("{}({})", func.name(), self.operand.to_infix()),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self.actor {
+            UnaryAction::Neg => NEG_PRECEDENCE,
+            UnaryAction::Iden => self.operand.precedence(),
+            UnaryAction::Not => NOT_PRECEDENCE,
+            UnaryAction::Abs | UnaryAction::Func(_) => ATOMIC_PRECEDENCE,
+        }
+    }
+
+    fn depth(&self) -> usize {
+        1 + self.operand.depth()
+    }
+}
+
+impl Display for UnaryNode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.to_tree().join("\n"))
+    }
+}
+
+impl UnaryNode {
+    /// Creates a new [`UnaryNode`].
+    pub fn new(actor: UnaryAction, operand: NodeBox) -> UnaryNode {
+        Self {
+            actor,
+            operand,
+            span: None,
+        }
+    }
+
+    /// Returns the action performed by this [`UnaryNode`].
+    pub fn actor(&self) -> UnaryAction {
+        self.actor
+    }
+
+    /// Returns the sole operand of this [`UnaryNode`].
+    pub fn operand(&self) -> &dyn Node {
+        self.operand.as_ref()
+    }
+
+    /// Applies the actor of this [`UnaryNode`] to its evaluated operand.
+    fn apply(&self, operand: Number, vars: &dyn Variables) -> Number {
+        // Earlier results are recalled from the variables.
+        if self.actor == UnaryAction::Func(Function::Hist) {
+            return UnaryNode::recall(vars, &operand).unwrap_or(Number::Flt(f64::NAN));
+        }
+
+        self.actor.evaluate(operand)
+    }
+
+    /// Recalls the `n`th earlier result, where `n` must be a positive integer.
+    fn recall(vars: &dyn Variables, n: &Number) -> Option<Number> {
+        let n = n.to_f64();
+        if n < 1.0 || n != math::floor(n) {
+            return None;
+        }
+
+        vars.recall(n as usize)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// 6. impls for FunctionNode.
+// -----------------------------------------------------------------------------
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for FunctionNode {
+    fn evaluate(&self) -> Number {
+        self.evaluate_indexed(&[])
+    }
+
+    fn evaluate_with(&self, vars: &dyn Variables) -> Number {
+        // Evaluate all operands.
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.evaluate_with(vars))
+            .collect::<Vec<_>>();
+
+        // Then evaluate this node.
+        FunctionNode::evaluate_function(&self.func, &args)
+    }
+
+    fn try_evaluate_with(&self, vars: &dyn Variables) -> Result<Number, EvalError> {
+        vars.interrupt()?;
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.try_evaluate_with(vars))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Functions taking exactly one operand are evaluated as unary actions.
+        if let [operand] = args.as_slice() {
+            UnaryAction::Func(self.func).check_domain(operand)?;
+        }
+
+        #[cfg(feature = "vector")]
+        if let (Function::Dot, [a, b]) = (self.func, args.as_slice()) {
+            a.check_lengths(b)?;
+        }
+
+        Ok(FunctionNode::evaluate_function(&self.func, &args))
+    }
+
+    fn is_exact(&self) -> bool {
+        // Dot products stay exact, other functions are computed with
+        // floating-point numbers.
+        #[cfg(feature = "vector")]
+        if self.func == Function::Dot {
+            return self.args.iter().all(|arg| arg.is_exact());
+        }
+
+        false
+    }
+
+    fn hash_content(&self, state: &mut dyn Hasher) {
+        state.write_u8(2);
+        hash_bytes(state, self.func.name().as_bytes());
+        state.write_u64(self.args.len() as u64);
+        for arg in &self.args {
+            arg.hash_content(state);
+        }
+    }
+
+    fn structural_cmp(&self, other: &dyn Node) -> Ordering {
+        match other.as_any().downcast_ref::<FunctionNode>() {
+            Some(other) => self
+                .func
+                .cmp(&other.func)
+                .then_with(|| structural_cmp_all(&self.args, &other.args)),
+            None => node_rank(self).cmp(&node_rank(other.as_any())),
+        }
+    }
+
+    fn canonicalize(self: Box<Self>) -> NodeBox {
+        let FunctionNode { func, args, span } = *self;
+
+        Box::new(FunctionNode {
+            func,
+            args: args.into_iter().map(|arg| arg.canonicalize()).collect(),
+            span,
+        })
+    }
+
+    fn partial_eval(&self, vars: &dyn Variables) -> NodeBox {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.partial_eval(vars))
+            .collect::<Vec<_>>();
+
+        // Only evaluate this node if all operands are known.
+        match args
+            .iter()
+            .map(|arg| arg.as_number().cloned())
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(values) => Box::new(PlainNode::new(FunctionNode::evaluate_function(
+                &self.func, &values,
+            ))),
+            None => Box::new(FunctionNode::new(self.func, args)),
+        }
+    }
+
+    fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    fn set_span(&mut self, span: Span) {
+        self.span = Some(span);
+    }
+
+    fn to_tree(&self) -> Vec<String> {
+        self.to_tree_limited(usize::MAX)
+    }
+
+    fn to_tree_with(&self, operand_tree: &dyn Fn(&dyn Node) -> Vec<String>) -> Vec<String> {
+        // Get function.
+        let mut tree = vec![UnaryAction::Func(self.func).to_string()];
+
+        // Process all operands, the last one is connected differently.
+        for (i, arg) in self.args.iter().enumerate() {
+            let padding = if i + 1 < self.args.len() {
+                "|   "
+            } else {
+                "    "
+            };
+
+            let mut arg_tree = operand_tree(arg.as_ref());
+            arg_tree[0].insert_str(0, "`-- ");
+            for line in arg_tree.iter_mut().skip(1) {
+                line.insert_str(0, padding);
+            }
+
+            tree.extend(arg_tree);
+        }
+
+        tree
+    }
+
+    fn node_count(&self) -> usize {
+        1 + self.args.iter().map(|arg| arg.node_count()).sum::<usize>()
+    }
+
+    #[cfg(feature = "decimal")]
+    fn evaluate_with_context(&self, vars: &dyn Variables, ctx: &DecimalContext) -> Number {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.evaluate_with_context(vars, ctx))
+            .collect::<Vec<_>>();
+
+        ctx.apply(FunctionNode::evaluate_function(&self.func, &args))
+    }
+
+    fn trace(&self, vars: &dyn Variables, order: EvalOrder, steps: &mut Vec<String>) -> Number {
+        let mut args = Vec::with_capacity(self.args.len());
+        match order {
+            EvalOrder::LeftToRight => {
+                for arg in &self.args {
+                    args.push(arg.trace(vars, order, steps));
+                }
+            }
+            EvalOrder::RightToLeft => {
+                for arg in self.args.iter().rev() {
+                    args.push(arg.trace(vars, order, steps));
+                }
+                args.reverse();
+            }
+        }
+
+        let result = FunctionNode::evaluate_function(&self.func, &args);
+        steps.push(format!("{} = {}", self.to_infix(), result));
+        result
+    }
+
+    #[cfg(feature = "dimensions")]
+    fn dimension(&self, mismatches: &mut Vec<DimensionMismatch>) -> Option<DimensionTag> {
+        for arg in &self.args {
+            arg.dimension(mismatches);
+        }
+        None
+    }
+
+    fn to_rust_typed(&self) -> Option<(String, CodeType)> {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.to_rust_typed().map(rust_f64))
+            .collect::<Option<Vec<_>>>()?;
+
+        let code = match (self.func, args.as_slice()) {
+            (Function::Hypot, [x, y]) => format!("{}.hypot({})", x, y),
+            (Function::PctChange, [old, new]) => {
+                format!("(({1} - {0}) * 100.0 / {0})", old, new)
+            }
+            _ => return None,
+        };
+
+        Some((code, CodeType::Float))
+    }
+
+    fn to_c_typed(&self) -> Option<(String, CodeType)> {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.to_c_typed().map(|(code, _)| code))
+            .collect::<Option<Vec<_>>>()?;
+
+        let code = match (self.func, args.as_slice()) {
+            (Function::Hypot, [x, y]) => format!("hypot({}, {})", x, y),
+            (Function::PctChange, [old, new]) => {
+                format!("(({1} - {0}) * 100.0 / {0})", old, new)
+            }
+            _ => return None,
+        };
+
+        Some((code, CodeType::Float))
+    }
+
+    fn to_infix(&self) -> String {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.to_infix())
+            .collect::<Vec<_>>();
+
+        format!("{}({})", self.func.name(), args.join(", "))
+    }
+
+    fn depth(&self) -> usize {
+        1 + self.args.iter().map(|arg| arg.depth()).max().unwrap_or(0)
+    }
+}
+
+impl Display for FunctionNode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.to_tree().join("\n"))
+    }
+}
+
+impl FunctionNode {
+    /// Creates a new [`FunctionNode`].
+    pub fn new(func: Function, args: Vec<NodeBox>) -> FunctionNode {
+        Self {
+            func,
+            args,
+            span: None,
+        }
+    }
+
+    fn evaluate_function(func: &Function, args: &[Number]) -> Number {
+        match (func, args) {
+            (Function::Hypot, [a, b]) => Number::Flt(math::hypot(a.to_f64(), b.to_f64())),
+            #[cfg(feature = "vector")]
+            (Function::Dot, [a, b]) => a.dot(b),
+            (Function::RoundSig, [x, n]) => {
+                Number::Flt(FunctionNode::round_sig(x.to_f64(), n.to_f64()))
+            }
+            (Function::PctChange, [old, new]) => {
+                Number::Flt(FunctionNode::pct_change(old.to_f64(), new.to_f64()))
+            }
+            (Function::If, [cond, then, otherwise]) => {
+                IfNode::choose(cond, || then.clone(), || otherwise.clone())
+            }
+
+            // Functions taking exactly one operand.
+            (func, [operand]) => UnaryAction::Func(*func).evaluate(operand.clone()),
+
+            // Wrong number of operands.
+            _ => Number::Flt(f64::NAN),
+        }
+    }
+
+    /// Finds the change from `old` to `new` as a percentage of `old`. There is
+    /// no percentage of a zero base, so the result is then `NaN`.
+    fn pct_change(old: f64, new: f64) -> f64 {
+        if old == 0.0 {
+            return f64::NAN;
+        }
+
+        // Scaling before dividing keeps whole percentages exact.
+        (new - old) * 100.0 / old
+    }
+
+    /// Rounds `x` to `n` significant figures, where `n` is rounded to an
+    /// integer and must be positive.
+    fn round_sig(x: f64, n: f64) -> f64 {
+        let n = math::round(n);
+        if n < 1.0 || !n.is_finite() {
+            return f64::NAN;
+        }
+
+        // Zero and non-finite numbers have no significant figures to round.
+        if x == 0.0 || !x.is_finite() {
+            return x;
+        }
+
+        // Scale so that the wanted figures are in the integer part. Only powers
+        // of ten above one are used, as their inverses are not exact.
+        let exponent = n - 1.0 - math::floor(math::log10(x.abs()));
+        let scale = math::pow(10.0, exponent.abs());
+        if exponent >= 0.0 {
+            math::round(x * scale) / scale
+        } else {
+            math::round(x / scale) * scale
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// 7. impls for PlainNode.
+// -----------------------------------------------------------------------------
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for PlainNode {
+    fn evaluate(&self) -> Number {
+        self.0.clone()
+    }
+
+    fn is_exact(&self) -> bool {
+        self.0.kind() != NumberKind::Flt
+    }
+
+    fn hash_content(&self, state: &mut dyn Hasher) {
+        state.write_u8(3);
+        self.0.hash_content(state);
+    }
+
+    fn structural_cmp(&self, other: &dyn Node) -> Ordering {
+        match other.as_any().downcast_ref::<PlainNode>() {
+            Some(other) => self.0.total_cmp(&other.0),
+            None => node_rank(self).cmp(&node_rank(other.as_any())),
+        }
+    }
+
+    fn partial_eval(&self, _vars: &dyn Variables) -> NodeBox {
+        Box::new(self.clone())
+    }
+
+    fn as_number(&self) -> Option<&Number> {
+        Some(&self.0)
+    }
+
+    fn span(&self) -> Option<Span> {
+        self.1
+    }
+
+    fn set_span(&mut self, span: Span) {
+        self.1 = Some(span);
+    }
+
+    fn to_tree(&self) -> Vec<String> {
+        vec![self.0.to_string()]
+    }
+
+    #[cfg(feature = "dimensions")]
+    fn dimension(&self, _mismatches: &mut Vec<DimensionMismatch>) -> Option<DimensionTag> {
+        self.2
+    }
+
+    fn to_rust_typed(&self) -> Option<(String, CodeType)> {
+        self.0.to_rust_typed()
+    }
+
+    fn to_c_typed(&self) -> Option<(String, CodeType)> {
+        self.0.to_c_typed()
+    }
+
+    fn to_infix(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn precedence(&self) -> u8 {
+        // Negative numbers are written with a sign.
+        if self.0 < Number::zero() {
+            NEG_PRECEDENCE
+        } else {
+            ATOMIC_PRECEDENCE
+        }
+    }
+}
+
+impl Display for PlainNode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.to_tree().join("\n"))
+    }
+}
+
+impl PlainNode {
+    pub fn new(value: Number) -> PlainNode {
+        Self(
+            value,
+            None,
+            #[cfg(feature = "dimensions")]
+            None,
+        )
+    }
+
+    /// Returns the number stored in this [`PlainNode`].
+    pub fn value(&self) -> &Number {
+        &self.0
+    }
+
+    /// Marks the number stored as taken from a physical constant, see
+    /// [`Node::evaluate_dimensioned`].
+    #[cfg(feature = "dimensions")]
+    pub fn with_dimension(mut self, dimension: DimensionTag) -> PlainNode {
+        self.2 = Some(dimension);
+        self
+    }
+}
+
+impl PartialEq for PlainNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Into<Number>> From<T> for PlainNode {
+    fn from(value: T) -> Self {
+        Self::new(value.into())
+    }
+}
+
+/// Numbers can be used directly as [`NodeBox`]es, e.g. `let n: NodeBox = 5.into()`.
+impl<T: Into<Number>> From<T> for NodeBox {
+    fn from(value: T) -> Self {
+        Box::new(PlainNode::from(value))
+    }
+}
+
+// -----------------------------------------------------------------------------
+// 8. impls for VariableNode.
+// -----------------------------------------------------------------------------
+
+#[cfg_attr(feature = "serde", typetag::serde)]
+impl Node for VariableNode {
+    fn evaluate(&self) -> Number {
+        self.evaluate_indexed(&[])
+    }
+
+    fn evaluate_with(&self, vars: &dyn Variables) -> Number {
+        vars.get(&self.name, self.index)
+            .unwrap_or(Number::Flt(f64::NAN))
+    }
+
+    fn hash_content(&self, state: &mut dyn Hasher) {
+        state.write_u8(4);
+        hash_bytes(state, self.name.as_bytes());
+    }
+
+    fn structural_cmp(&self, other: &dyn Node) -> Ordering {
+        match other.as_any().downcast_ref::<VariableNode>() {
+            Some(other) => self.name.cmp(&other.name),
+            None => node_rank(self).cmp(&node_rank(other.as_any())),
+        }
+    }
 
-impl From<u32> for Number {
-    fn from(n: u32) -> Self {
-        Self::Int(n as i128)
+    fn partial_eval(&self, vars: &dyn Variables) -> NodeBox {
+        match vars.get(&self.name, self.index) {
+            Some(n) => Box::new(PlainNode::new(n)),
+            None => Box::new(VariableNode::new(self.name.clone(), self.index)),
+        }
     }
-}
 
-impl From<u64> for Number {
-    fn from(n: u64) -> Self {
-        Self::Int(n as i128)
+    fn span(&self) -> Option<Span> {
+        self.span
     }
-}
 
-impl From<i8> for Number {
-    fn from(n: i8) -> Self {
-        Self::Int(n as i128)
+    fn set_span(&mut self, span: Span) {
+        self.span = Some(span);
     }
-}
 
-impl From<i16> for Number {
-    fn from(n: i16) -> Self {
-        Self::Int(n as i128)
+    fn to_tree(&self) -> Vec<String> {
+        vec![format!("Var({})", self.name)]
     }
-}
 
-impl From<i32> for Number {
-    fn from(n: i32) -> Self {
-        Self::Int(n as i128)
+    fn to_rust_typed(&self) -> Option<(String, CodeType)> {
+        Some((self.name.clone(), CodeType::Float))
     }
-}
 
-impl From<i64> for Number {
-    fn from(n: i64) -> Self {
-        Self::Int(n as i128)
+    fn to_c_typed(&self) -> Option<(String, CodeType)> {
+        Some((self.name.clone(), CodeType::Float))
     }
-}
 
-impl From<i128> for Number {
-    fn from(n: i128) -> Self {
-        Self::Int(n)
+    fn to_infix(&self) -> String {
+        self.name.clone()
     }
 }
 
-impl From<f32> for Number {
-    fn from(n: f32) -> Self {
-        Self::Flt(n as f64)
+impl Display for VariableNode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.to_tree().join("\n"))
     }
 }
 
-impl From<f64> for Number {
-    fn from(n: f64) -> Self {
-        Self::Flt(n)
+impl PartialEq for VariableNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.index == other.index
     }
 }
 
-// -----------------------------------------------------------------------------
-// 2. impls for BinaryAction.
-// -----------------------------------------------------------------------------
-
-impl BinaryAction {
-    pub fn evaluate(&self, left: Number, right: Number) -> Number {
-        match self {
-            Self::Add => left + right,
-            Self::Sub => left - right,
-            Self::Mul => left * right,
-            Self::Div => left / right,
-            Self::Pow => {
-                // Integer base and exponent are kept as integer.
-                if let Number::Int(n) = left {
-                    if let Number::Int(m) = right {
-                        if m >= 0 {
-                            return Number::Int(n.pow(m as u32));
-                        } else {
-                            return Number::Flt((n as f64).powf(m as f64));
-                        }
-                    }
-                }
-
-                // Otherwise, both are converted to float.
-                let left = match left {
-                    Number::Int(n) => n as f64,
-                    Number::Flt(n) => n,
-                };
-                let right = match right {
-                    Number::Int(n) => n as f64,
-                    Number::Flt(n) => n,
-                };
-                Number::Flt(left.powf(right))
-            }
+impl VariableNode {
+    /// Creates a new [`VariableNode`].
+    pub fn new(name: String, index: usize) -> VariableNode {
+        Self {
+            name,
+            index,
+            span: None,
         }
     }
-}
 
-impl Display for BinaryAction {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Op(")?;
-        match self {
-            Self::Add => write!(f, "+"),
-            Self::Sub => write!(f, "-"),
-            Self::Mul => write!(f, "*"),
-            Self::Div => write!(f, "/"),
-            Self::Pow => write!(f, "^"),
-        }?;
-        write!(f, ")")
+    /// Returns the name of this variable.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the index of this variable.
+    pub fn index(&self) -> usize {
+        self.index
     }
 }
 
 // -----------------------------------------------------------------------------
-// 3. impls for BinaryNode.
+// 9. impls for IfNode.
 // -----------------------------------------------------------------------------
 
 #[cfg_attr(feature = "serde", typetag::serde)]
-impl Node for BinaryNode {
+impl Node for IfNode {
     fn evaluate(&self) -> Number {
-        // Evaluate both sub-nodes.
-        let left = self.left.evaluate();
-        let right = self.right.evaluate();
+        self.evaluate_indexed(&[])
+    }
 
-        // Then evalute this node.
-        self.actor.evaluate(left, right)
+    fn evaluate_with(&self, vars: &dyn Variables) -> Number {
+        IfNode::choose(
+            &self.cond.evaluate_with(vars),
+            || self.then.evaluate_with(vars),
+            || self.otherwise.evaluate_with(vars),
+        )
     }
 
-    fn to_tree(&self) -> Vec<String> {
-        // Get actor.
-        let actor = self.actor.to_string();
+    fn try_evaluate_with(&self, vars: &dyn Variables) -> Result<Number, EvalError> {
+        vars.interrupt()?;
 
-        // Process left side.
-        let mut left_tree = self.left.to_tree();
-        left_tree[0].insert_str(0, "`-- ");
-        for line in left_tree.iter_mut().skip(1) {
-            line.insert_str(0, "|   ");
+        // Only the chosen operand can fail.
+        match self.cond.try_evaluate_with(vars)?.to_bool() {
+            Some(true) => self.then.try_evaluate_with(vars),
+            Some(false) => self.otherwise.try_evaluate_with(vars),
+            None => Ok(Number::Flt(f64::NAN)),
         }
+    }
 
-        // Process right side.
-        let mut right_tree = self.right.to_tree();
-        right_tree[0].insert_str(0, "`-- ");
-        for line in right_tree.iter_mut().skip(1) {
-            line.insert_str(0, "    ");
+    fn is_exact(&self) -> bool {
+        self.cond.is_exact() && self.then.is_exact() && self.otherwise.is_exact()
+    }
+
+    fn hash_content(&self, state: &mut dyn Hasher) {
+        state.write_u8(5);
+        self.cond.hash_content(state);
+        self.then.hash_content(state);
+        self.otherwise.hash_content(state);
+    }
+
+    fn structural_cmp(&self, other: &dyn Node) -> Ordering {
+        match other.as_any().downcast_ref::<IfNode>() {
+            Some(other) => self
+                .cond
+                .structural_cmp(other.cond.as_ref())
+                .then_with(|| self.then.structural_cmp(other.then.as_ref()))
+                .then_with(|| self.otherwise.structural_cmp(other.otherwise.as_ref())),
+            None => node_rank(self).cmp(&node_rank(other.as_any())),
         }
+    }
 
-        // Combine all three.
-        let mut tree = vec![actor];
-        tree.extend(left_tree);
-        tree.extend(right_tree);
+    fn canonicalize(self: Box<Self>) -> NodeBox {
+        let IfNode {
+            cond,
+            then,
+            otherwise,
+            span,
+        } = *self;
+
+        Box::new(IfNode {
+            cond: cond.canonicalize(),
+            then: then.canonicalize(),
+            otherwise: otherwise.canonicalize(),
+            span,
+        })
+    }
+
+    fn partial_eval(&self, vars: &dyn Variables) -> NodeBox {
+        let cond = self.cond.partial_eval(vars);
+
+        // Only the chosen operand is kept if the condition is known.
+        match cond.as_number().map(Number::to_bool) {
+            Some(Some(true)) => self.then.partial_eval(vars),
+            Some(Some(false)) => self.otherwise.partial_eval(vars),
+            Some(None) => Box::new(PlainNode::new(Number::Flt(f64::NAN))),
+            None => Box::new(IfNode::new(
+                cond,
+                self.then.partial_eval(vars),
+                self.otherwise.partial_eval(vars),
+            )),
+        }
+    }
+
+    fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    fn set_span(&mut self, span: Span) {
+        self.span = Some(span);
+    }
+
+    fn to_tree(&self) -> Vec<String> {
+        self.to_tree_limited(usize::MAX)
+    }
+
+    fn to_tree_with(&self, operand_tree: &dyn Fn(&dyn Node) -> Vec<String>) -> Vec<String> {
+        // Get function.
+        let mut tree = vec![UnaryAction::Func(Function::If).to_string()];
+
+        // Process all operands, the last one is connected differently.
+        let operands = [&self.cond, &self.then, &self.otherwise];
+        for (i, operand) in operands.iter().enumerate() {
+            let padding = if i + 1 < operands.len() {
+                "|   "
+            } else {
+                "    "
+            };
+
+            let mut subtree = operand_tree(operand.as_ref());
+            subtree[0].insert_str(0, "`-- ");
+            for line in subtree.iter_mut().skip(1) {
+                line.insert_str(0, padding);
+            }
+
+            tree.extend(subtree);
+        }
 
         tree
     }
+
+    fn node_count(&self) -> usize {
+        1 + self.cond.node_count() + self.then.node_count() + self.otherwise.node_count()
+    }
+
+    #[cfg(feature = "decimal")]
+    fn evaluate_with_context(&self, vars: &dyn Variables, ctx: &DecimalContext) -> Number {
+        IfNode::choose(
+            &self.cond.evaluate_with_context(vars, ctx),
+            || self.then.evaluate_with_context(vars, ctx),
+            || self.otherwise.evaluate_with_context(vars, ctx),
+        )
+    }
+
+    fn trace(&self, vars: &dyn Variables, order: EvalOrder, steps: &mut Vec<String>) -> Number {
+        // The condition always comes first, as it chooses the operand.
+        let result = match self.cond.trace(vars, order, steps).to_bool() {
+            Some(true) => self.then.trace(vars, order, steps),
+            Some(false) => self.otherwise.trace(vars, order, steps),
+            None => Number::Flt(f64::NAN),
+        };
+        steps.push(format!("{} = {}", self.to_infix(), result));
+        result
+    }
+
+    #[cfg(feature = "dimensions")]
+    fn dimension(&self, mismatches: &mut Vec<DimensionMismatch>) -> Option<DimensionTag> {
+        self.cond.dimension(mismatches);
+        self.then.dimension(mismatches);
+        self.otherwise.dimension(mismatches);
+        None
+    }
+
+    fn to_rust_typed(&self) -> Option<(String, CodeType)> {
+        let cond = rust_bool(self.cond.to_rust_typed()?);
+        let (then, otherwise, ty) =
+            rust_unify(self.then.to_rust_typed()?, self.otherwise.to_rust_typed()?);
+
+        Some((
+            format!("(if {} {{ {} }} else {{ {} }})", cond, then, otherwise),
+            ty,
+        ))
+    }
+
+    fn to_c_typed(&self) -> Option<(String, CodeType)> {
+        let (cond, (then, a), (otherwise, b)) = (
+            self.cond.to_c_typed()?.0,
+            self.then.to_c_typed()?,
+            self.otherwise.to_c_typed()?,
+        );
+        let ty = match (a, b) {
+            (CodeType::Int, CodeType::Int) => CodeType::Int,
+            _ => CodeType::Float,
+        };
+
+        Some((format!("({} ? {} : {})", cond, then, otherwise), ty))
+    }
+
+    fn to_infix(&self) -> String {
+        format!(
+            "if({}, {}, {})",
+            self.cond.to_infix(),
+            self.then.to_infix(),
+            self.otherwise.to_infix()
+        )
+    }
+
+    fn depth(&self) -> usize {
+        1 + self
+            .cond
+            .depth()
+            .max(self.then.depth())
+            .max(self.otherwise.depth())
+    }
 }
 
-impl Display for BinaryNode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Display for IfNode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.to_tree().join("\n"))
     }
 }
 
-impl BinaryNode {
-    /// Creates a new [`BinaryNode`].
-    #[rustfmt::skip]
-    pub fn new(
-        left: NodeBox,
-        actor: BinaryAction,
-        right: NodeBox
-    ) -> BinaryNode {
-        Self { left, actor, right }
+impl IfNode {
+    /// Creates a new [`IfNode`].
+    pub fn new(cond: NodeBox, then: NodeBox, otherwise: NodeBox) -> IfNode {
+        Self {
+            cond,
+            then,
+            otherwise,
+            span: None,
+        }
+    }
+
+    /// Evaluates one of the operands based on the value of the condition.
+    /// A `NaN` condition is neither true nor false, resulting in `NaN`.
+    fn choose(
+        cond: &Number,
+        then: impl FnOnce() -> Number,
+        otherwise: impl FnOnce() -> Number,
+    ) -> Number {
+        match cond.to_bool() {
+            Some(true) => then(),
+            Some(false) => otherwise(),
+            None => Number::Flt(f64::NAN),
+        }
     }
 }
 
 // -----------------------------------------------------------------------------
-// 4. impls for UnaryAction.
+// 10. impls for Variables.
 // -----------------------------------------------------------------------------
 
-impl UnaryAction {
-    pub fn evaluate(&self, operand: Number) -> Number {
-        match self {
-            Self::Neg => -operand,
-            Self::Iden => operand,
-            Self::Func(f) => UnaryAction::evaluate_function(f, operand),
-        }
+/// Variables are looked up by their indices.
+impl Variables for &[Number] {
+    fn get(&self, _name: &str, index: usize) -> Option<Number> {
+        <[Number]>::get(self, index).cloned()
     }
+}
 
-    fn evaluate_function(func: &Function, operand: Number) -> Number {
-        match func {
-            Function::Sin => match operand {
-                Number::Int(n) => Number::Flt((n as f64).sin()),
-                Number::Flt(n) => Number::Flt(n.sin()),
-            },
-            Function::Cos => match operand {
-                Number::Int(n) => Number::Flt((n as f64).cos()),
-                Number::Flt(n) => Number::Flt(n.cos()),
-            },
-            Function::Tan => match operand {
-                Number::Int(n) => Number::Flt((n as f64).tan()),
-                Number::Flt(n) => Number::Flt(n.tan()),
-            },
-            Function::Sec => match operand {
-                Number::Int(n) => Number::Flt((n as f64).cos().recip()),
-                Number::Flt(n) => Number::Flt(n.cos().recip()),
-            },
-            Function::Csc => match operand {
-                Number::Int(n) => Number::Flt((n as f64).sin().recip()),
-                Number::Flt(n) => Number::Flt(n.sin().recip()),
-            },
+impl<'a> Sample<'a> {
+    /// Creates a new [`Sample`], binding `name` to `x`.
+    fn new(name: &'a str, x: f64) -> Self {
+        Self {
+            name,
+            value: Number::Flt(x),
+        }
+    }
+}
 
-            Function::Cot => match operand {
-                Number::Int(n) => Number::Flt((n as f64).tan().recip()),
-                Number::Flt(n) => Number::Flt(n.tan().recip()),
-            },
+impl Variables for Sample<'_> {
+    fn get(&self, name: &str, _index: usize) -> Option<Number> {
+        (name == self.name).then(|| self.value.clone())
+    }
+}
 
-            Function::Asin => match operand {
-                Number::Int(n) => Number::Flt((n as f64).asin()),
-                Number::Flt(n) => Number::Flt(n.asin()),
-            },
+/// Variables are looked up by their names.
+#[cfg(feature = "std")]
+impl Variables for HashMap<String, Number> {
+    fn get(&self, name: &str, _index: usize) -> Option<Number> {
+        HashMap::get(self, name).cloned()
+    }
+}
 
-            Function::Acos => match operand {
-                Number::Int(n) => Number::Flt((n as f64).acos()),
-                Number::Flt(n) => Number::Flt(n.acos()),
-            },
+#[cfg(feature = "std")]
+impl<'a> Deadline<'a> {
+    /// Creates a new [`Deadline`], forwarding look-ups to `vars`.
+    fn new(vars: &'a dyn Variables, deadline: Instant) -> Self {
+        Self {
+            vars,
+            deadline,
+            count: Cell::new(0),
+        }
+    }
+}
 
-            Function::Atan => match operand {
-                Number::Int(n) => Number::Flt((n as f64).atan()),
-                Number::Flt(n) => Number::Flt(n.atan()),
-            },
+/// Variables are looked up in the wrapped variables, while the clock is checked
+/// every [`DEADLINE_INTERVAL`] nodes, starting with the first.
+#[cfg(feature = "std")]
+impl Variables for Deadline<'_> {
+    fn get(&self, name: &str, index: usize) -> Option<Number> {
+        self.vars.get(name, index)
+    }
 
-            Function::Asec => match operand {
-                Number::Int(n) => Number::Flt((n as f64).recip().acos()),
-                Number::Flt(n) => Number::Flt(n.recip().acos()),
-            },
+    fn recall(&self, n: usize) -> Option<Number> {
+        self.vars.recall(n)
+    }
 
-            Function::Acsc => match operand {
-                Number::Int(n) => Number::Flt((n as f64).recip().asin()),
-                Number::Flt(n) => Number::Flt(n.recip().asin()),
-            },
+    // `is_multiple_of` is only stable since Rust 1.87.
+    #[allow(clippy::manual_is_multiple_of)]
+    fn interrupt(&self) -> Result<(), EvalError> {
+        let count = self.count.get();
+        self.count.set(count.wrapping_add(1));
 
-            Function::Acot => match operand {
-                Number::Int(n) => Number::Flt((n as f64).recip().atan()),
-                Number::Flt(n) => Number::Flt(n.recip().atan()),
-            },
+        if count % DEADLINE_INTERVAL == 0 && Instant::now() >= self.deadline {
+            return Err(EvalError::Timeout);
         }
-    }
-}
 
-impl Display for UnaryAction {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Neg => write!(f, "Op(-)"),
-            Self::Iden => write!(f, "Op(+)"),
-            Self::Func(func) => write!(f, "Func({})", func),
-        }
+        Ok(())
     }
 }
 
 // -----------------------------------------------------------------------------
-// 5. impls for UnaryNode.
+// 11. impls for VectorNode.
 // -----------------------------------------------------------------------------
 
+#[cfg(feature = "vector")]
 #[cfg_attr(feature = "serde", typetag::serde)]
-impl Node for UnaryNode {
+impl Node for VectorNode {
     fn evaluate(&self) -> Number {
-        // Evaluate the operand.
-        let operand = self.operand.evaluate();
+        self.evaluate_indexed(&[])
+    }
 
-        // Then evaluate this node.
-        self.actor.evaluate(operand)
+    fn evaluate_with(&self, vars: &dyn Variables) -> Number {
+        Number::Vec(
+            self.elements
+                .iter()
+                .map(|element| element.evaluate_with(vars))
+                .collect(),
+        )
+    }
+
+    fn try_evaluate_with(&self, vars: &dyn Variables) -> Result<Number, EvalError> {
+        vars.interrupt()?;
+        self.elements
+            .iter()
+            .map(|element| element.try_evaluate_with(vars))
+            .collect::<Result<_, _>>()
+            .map(Number::Vec)
+    }
+
+    fn is_exact(&self) -> bool {
+        self.elements.iter().all(|element| element.is_exact())
+    }
+
+    fn hash_content(&self, state: &mut dyn Hasher) {
+        state.write_u8(6);
+        state.write_u64(self.elements.len() as u64);
+        for element in &self.elements {
+            element.hash_content(state);
+        }
+    }
+
+    fn structural_cmp(&self, other: &dyn Node) -> Ordering {
+        match other.as_any().downcast_ref::<VectorNode>() {
+            Some(other) => structural_cmp_all(&self.elements, &other.elements),
+            None => node_rank(self).cmp(&node_rank(other.as_any())),
+        }
+    }
+
+    fn canonicalize(self: Box<Self>) -> NodeBox {
+        let VectorNode { elements, span } = *self;
+
+        Box::new(VectorNode {
+            elements: elements
+                .into_iter()
+                .map(|element| element.canonicalize())
+                .collect(),
+            span,
+        })
+    }
+
+    fn partial_eval(&self, vars: &dyn Variables) -> NodeBox {
+        let elements = self
+            .elements
+            .iter()
+            .map(|element| element.partial_eval(vars))
+            .collect::<Vec<_>>();
+
+        // Only evaluate this node if all elements are known.
+        match elements
+            .iter()
+            .map(|element| element.as_number().cloned())
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(values) => Box::new(PlainNode::new(Number::Vec(values))),
+            None => Box::new(VectorNode::new(elements)),
+        }
+    }
+
+    fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    fn set_span(&mut self, span: Span) {
+        self.span = Some(span);
     }
 
     fn to_tree(&self) -> Vec<String> {
-        // Get actor.
-        let actor = self.actor.to_string();
+        self.to_tree_limited(usize::MAX)
+    }
 
-        // Process left side.
-        let mut left_tree = self.operand.to_tree();
-        left_tree[0].insert_str(0, "`-- ");
-        for line in left_tree.iter_mut().skip(1) {
-            line.insert_str(0, "|   ");
+    fn to_tree_with(&self, operand_tree: &dyn Fn(&dyn Node) -> Vec<String>) -> Vec<String> {
+        let mut tree = vec![String::from("Vec")];
+
+        // Process all elements, the last one is connected differently.
+        for (i, element) in self.elements.iter().enumerate() {
+            let padding = if i + 1 < self.elements.len() {
+                "|   "
+            } else {
+                "    "
+            };
+
+            let mut element_tree = operand_tree(element.as_ref());
+            element_tree[0].insert_str(0, "`-- ");
+            for line in element_tree.iter_mut().skip(1) {
+                line.insert_str(0, padding);
+            }
+
+            tree.extend(element_tree);
         }
 
-        // Combine.
-        left_tree.insert(0, actor);
+        tree
+    }
 
-        left_tree
+    fn node_count(&self) -> usize {
+        1 + self
+            .elements
+            .iter()
+            .map(|element| element.node_count())
+            .sum::<usize>()
     }
-}
 
-impl Display for UnaryNode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_tree().join("\n"))
+    #[cfg(feature = "decimal")]
+    fn evaluate_with_context(&self, vars: &dyn Variables, ctx: &DecimalContext) -> Number {
+        Number::Vec(
+            self.elements
+                .iter()
+                .map(|element| element.evaluate_with_context(vars, ctx))
+                .collect(),
+        )
     }
-}
 
-impl UnaryNode {
-    /// Creates a new [`UnaryNode`].
-    pub fn new(actor: UnaryAction, operand: NodeBox) -> UnaryNode {
-        Self { actor, operand }
+    fn trace(&self, vars: &dyn Variables, order: EvalOrder, steps: &mut Vec<String>) -> Number {
+        let mut elements = Vec::with_capacity(self.elements.len());
+        match order {
+            EvalOrder::LeftToRight => {
+                for element in &self.elements {
+                    elements.push(element.trace(vars, order, steps));
+                }
+            }
+            EvalOrder::RightToLeft => {
+                for element in self.elements.iter().rev() {
+                    elements.push(element.trace(vars, order, steps));
+                }
+                elements.reverse();
+            }
+        }
+
+        let result = Number::Vec(elements);
+        steps.push(format!("{} = {}", self.to_infix(), result));
+        result
     }
-}
 
-// -----------------------------------------------------------------------------
-// 6. impls for PlainNode.
-// -----------------------------------------------------------------------------
+    #[cfg(feature = "dimensions")]
+    fn dimension(&self, mismatches: &mut Vec<DimensionMismatch>) -> Option<DimensionTag> {
+        for element in &self.elements {
+            element.dimension(mismatches);
+        }
+        None
+    }
 
-#[cfg_attr(feature = "serde", typetag::serde)]
-impl Node for PlainNode {
-    fn evaluate(&self) -> Number {
-        self.0
+    fn to_infix(&self) -> String {
+        let elements = self
+            .elements
+            .iter()
+            .map(|element| element.to_infix())
+            .collect::<Vec<_>>();
+
+        format!("[{}]", elements.join(", "))
     }
 
-    fn to_tree(&self) -> Vec<String> {
-        vec![self.0.to_string()]
+    fn depth(&self) -> usize {
+        1 + self
+            .elements
+            .iter()
+            .map(|element| element.depth())
+            .max()
+            .unwrap_or(0)
     }
 }
 
-impl Display for PlainNode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+#[cfg(feature = "vector")]
+impl Display for VectorNode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.to_tree().join("\n"))
     }
 }
 
-impl PlainNode {
-    pub fn new(value: Number) -> PlainNode {
-        Self(value)
+#[cfg(feature = "vector")]
+impl VectorNode {
+    /// Creates a new [`VectorNode`].
+    pub fn new(elements: Vec<NodeBox>) -> VectorNode {
+        Self {
+            elements,
+            span: None,
+        }
     }
 }
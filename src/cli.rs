@@ -65,6 +65,7 @@ impl CliParser {
         }
 
         let mut input = String::new();
+        let mut parser = Parser::from_lexer(Lexer::from_source_code(""));
         println!("Enter 'quit' to exit");
 
         loop {
@@ -76,8 +77,7 @@ impl CliParser {
                 break 0;
             }
 
-            let lexer = Lexer::from_source_code(&input);
-            let mut parser = Parser::from_lexer(lexer);
+            parser.feed(Lexer::from_source_code(&input));
             let result = parser.parse();
 
             match result {
@@ -1,6 +1,12 @@
 //! This module implements the error types for [`tilted`](crate).
-use crate::Token;
-use std::{error::Error, fmt::Display};
+use crate::{Function, Operator, Span, Token, TokenKind};
+use alloc::{boxed::Box, string::String};
+use core::{error::Error, fmt::Display};
+
+#[cfg(feature = "serde")]
+use alloc::string::ToString;
+#[cfg(feature = "serde")]
+use serde::{ser::SerializeStruct, Serialize, Serializer};
 
 /// Errors returned by [`tilted`](crate)
 #[derive(Debug)]
@@ -11,8 +17,12 @@ pub enum TilError {
     /// Errors returned by [`Parser`](crate::Parser).
     Parse(ParseError),
 
-    /// Errors from other sources.
-    Unknown(Box<dyn Error>),
+    /// Errors returned by [`Node::try_evaluate_with`](crate::ast::Node::try_evaluate_with).
+    Eval(EvalError),
+
+    /// Errors from other sources. These must be thread-safe, so that
+    /// [`TilError`] is too.
+    Unknown(Box<dyn Error + Send + Sync>),
 }
 
 /// Errors returned by [`Lexer`](crate::Lexer).
@@ -21,9 +31,16 @@ pub enum LexError {
     /// Character is not part of any [`Token`](crate::Token).
     UnrecognisedCharacter(char, usize),
 
-    /// Undefined function.
+    /// Name directly followed by a left parenthesis that is not a function,
+    /// e.g. `sn(0)`.
     UnrecognisedFunction(String, usize),
 
+    /// Decimal point without any digits around it.
+    MissingDigits(usize),
+
+    /// Number that cannot be converted to its value, e.g. `1.2.3` or `1e`.
+    InvalidNumber { text: String, span: Span },
+
     /// Errors caused by parsing valid but unexpected user input.
     InternalError(&'static str, usize),
 }
@@ -40,24 +57,112 @@ pub enum ParseError {
     /// Expected an operator, found something else.
     OperatorExpected(Token),
 
+    /// Expected a left parenthesis, found something else.
+    LeftParenExpected(Token),
+
     /// Expected a right parenthesis, found something else.
     RightParenExpected(Token),
 
-    /// Found an invalid unary operator.
+    /// Expected a right bracket, found something else.
+    RightBracketExpected(Token),
+
+    /// Expected a vertical bar closing an absolute value, found something else.
+    BarExpected(Token),
+
+    /// Expected a comma, found something else.
+    CommaExpected(Token),
+
+    /// Found an operator where an operand was expected, e.g. `*5`.
     InvalidUnaryOperator(Token),
 
+    /// Found a token that cannot start an operand where one was expected, e.g.
+    /// the comma in `1 + ,`.
+    OperandExpected(Token),
+
     /// Found a right parenthesis without a matching left parenthesis.
     MismatchRightParen(usize),
 
+    /// Found a right parenthesis without a matching left parenthesis at
+    /// `index`, followed by more on the same line for a total of `count` that
+    /// must be removed. A single one is reported as
+    /// [`MismatchRightParen`](ParseError::MismatchRightParen).
+    ExtraRightParens { index: usize, count: usize },
+
+    /// Found the end of the input with `open` parentheses left unclosed.
+    UnclosedParens { open: usize, span: Span },
+
+    /// Found a binary operator without a right-hand side operand.
+    MissingOperand { operator_span: Span },
+
+    /// Found a comma without an argument (or element) before it, e.g. `(1,,2)`.
+    MissingArgument { comma_span: Span },
+
+    /// Found tokens after a complete expression, starting at `span`.
+    TrailingTokens { span: Span },
+
+    /// Found a function call with the wrong number of arguments.
+    WrongArity {
+        func: Function,
+        expected: usize,
+        got: usize,
+    },
+
+    /// Found an operator from another language, such as `**`, which is most
+    /// likely a typo for `suggestion`.
+    DidYouMean {
+        found: &'static str,
+        suggestion: &'static str,
+        span: Span,
+    },
+
+    /// Found an expression that is nested too deeply to be evaluated safely.
+    TooDeep(usize),
+
+    /// Found a function or variable that is not allowed, see
+    /// [`Parser::with_allowed_identifiers`](crate::Parser::with_allowed_identifiers).
+    DisallowedIdentifier { name: String, span: Span },
+
     /// Errors caused by parsing valid but unexpected user input.
     InternalError(&'static str),
 }
 
+/// Errors returned by [`Node::try_evaluate_with`](crate::ast::Node::try_evaluate_with)
+/// and by running statements in a session.
+#[derive(Debug, Clone)]
+pub enum EvalError {
+    /// Function applied to a value outside its domain, e.g. `asin(2)`.
+    DomainError { func: Function, value: f64 },
+
+    /// Evaluation still running at its deadline, see
+    /// [`Node::evaluate_deadline`](crate::ast::Node::evaluate_deadline).
+    Timeout,
+
+    /// Vectors of different lengths combined elementwise or in a dot product,
+    /// e.g. `[1, 2] + [1, 2, 3]`.
+    LengthMismatch { left: usize, right: usize },
+
+    /// Earlier result recalled with `hist(n)` that does not exist, e.g.
+    /// `hist(0)` or `hist(n)` before the `n`th result.
+    HistoryOutOfRange(f64),
+
+    /// Found a variable definition that (indirectly) refers to itself.
+    CyclicDefinition(String),
+
+    /// Found a constant declaration, i.e. `let name = expr`, that refers to
+    /// variables that are not constants.
+    NonConstantDeclaration(String),
+
+    /// Found a definition of a variable that was already declared as a
+    /// constant, e.g. `r = 3` after `let r = 2`.
+    ConstantRedefined(String),
+}
+
 impl Display for TilError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::Lex(e) => write!(f, "{}", e),
             Self::Parse(e) => write!(f, "{}", e),
+            Self::Eval(e) => write!(f, "{}", e),
             Self::Unknown(e) => write!(f, "{}", e),
         }
     }
@@ -68,6 +173,7 @@ impl Error for TilError {
         match self {
             Self::Lex(e) => Some(e),
             Self::Parse(e) => Some(e),
+            Self::Eval(e) => Some(e),
             Self::Unknown(e) => Some(e.as_ref()),
         }
     }
@@ -85,8 +191,14 @@ impl From<ParseError> for TilError {
     }
 }
 
+impl From<EvalError> for TilError {
+    fn from(value: EvalError) -> Self {
+        Self::Eval(value)
+    }
+}
+
 impl Display for LexError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::UnrecognisedCharacter(c, i) => {
                 write!(f, "Unrecognised character '{}' at index {}", c, i)
@@ -94,6 +206,12 @@ impl Display for LexError {
             Self::UnrecognisedFunction(n, i) => {
                 write!(f, "Unrecognised function '{}' at index {}", n, i)
             }
+            Self::MissingDigits(i) => {
+                write!(f, "Expected digits around the decimal point at index {}", i)
+            }
+            Self::InvalidNumber { text, span } => {
+                write!(f, "Invalid number '{}' at index {}", text, span.start_index)
+            }
             Self::InternalError(e, i) => write!(f, "{} at index {}", e, i),
         }
     }
@@ -101,22 +219,258 @@ impl Display for LexError {
 
 impl Error for LexError {}
 
+impl LexError {
+    /// Returns the name of this error's variant, e.g. `"MissingDigits"`, for
+    /// tools that tell errors apart without parsing their messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnrecognisedCharacter(..) => "UnrecognisedCharacter",
+            Self::UnrecognisedFunction(..) => "UnrecognisedFunction",
+            Self::MissingDigits(..) => "MissingDigits",
+            Self::InvalidNumber { .. } => "InvalidNumber",
+            Self::InternalError(..) => "InternalError",
+        }
+    }
+
+    /// Returns the location of this error in the source code. Errors only
+    /// known by their index span a single character.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::UnrecognisedCharacter(_, i)
+            | Self::UnrecognisedFunction(_, i)
+            | Self::MissingDigits(i)
+            | Self::InternalError(_, i) => Some(point(*i)),
+            Self::InvalidNumber { span, .. } => Some(*span),
+        }
+    }
+}
+
 impl Display for ParseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::UnexpectedEOF => write!(f, "Unexpected end-of-file"),
             Self::NumberExpected(t) => write!(f, "Expected a number, found {}", t),
             Self::OperatorExpected(t) => write!(f, "Expected an operator, found {}", t),
+            Self::LeftParenExpected(t) => write!(f, "Expected a left parenthesis, found {}", t),
             Self::RightParenExpected(t) => write!(f, "Expected a right parenthesis, found {}", t),
-            Self::InvalidUnaryOperator(t) => write!(f, "Found an invalid unary operator {}", t),
+            Self::RightBracketExpected(t) => write!(f, "Expected a right bracket, found {}", t),
+            Self::BarExpected(t) => write!(f, "Expected a closing bar, found {}", t),
+            Self::CommaExpected(t) => write!(f, "Expected a comma, found {}", t),
+            Self::InvalidUnaryOperator(t) => match t.kind {
+                // Negations bind less tightly than arithmetic, e.g. `1 + !0`.
+                TokenKind::Op(Operator::Not) => write!(
+                    f,
+                    "'!' must be wrapped in parentheses here at index {}",
+                    t.span.start_index
+                ),
+                TokenKind::Op(op) => write!(
+                    f,
+                    "'{}' cannot start an expression at index {}",
+                    op.symbol(),
+                    t.span.start_index
+                ),
+                _ => write!(f, "Found an invalid unary operator {}", t),
+            },
+            Self::OperandExpected(t) => write!(f, "Expected an operand, found {}", t),
             Self::MismatchRightParen(i) => write!(
                 f,
-                "Found a right parenthesis without a matching left one at index {}",
+                "Found a right parenthesis without a matching left one at index {}, remove 1 closing parenthesis",
                 i
             ),
+            Self::ExtraRightParens { index, count } => write!(
+                f,
+                "Found a right parenthesis without a matching left one at index {}, remove {} closing {}",
+                index,
+                count,
+                parentheses(*count)
+            ),
+            Self::UnclosedParens { open, .. } => write!(
+                f,
+                "Unexpected end-of-file, add {} closing {}",
+                open,
+                parentheses(*open)
+            ),
+            Self::MissingOperand { operator_span } => write!(
+                f,
+                "Expected an operand after the operator at index {}",
+                operator_span.start_index
+            ),
+            Self::MissingArgument { comma_span } => write!(
+                f,
+                "Expected an argument before the comma at index {}",
+                comma_span.start_index
+            ),
+            Self::TrailingTokens { span } => write!(
+                f,
+                "Unexpected token after the end of the expression at index {}",
+                span.start_index
+            ),
+            Self::WrongArity {
+                func,
+                expected,
+                got,
+            } => write!(
+                f,
+                "{} expects {} argument{}, got {}",
+                func.name(),
+                expected,
+                if *expected == 1 { "" } else { "s" },
+                got
+            ),
+            Self::DidYouMean {
+                found,
+                suggestion,
+                span,
+            } => write!(
+                f,
+                "Found '{}' at index {}, did you mean '{}'?",
+                found, span.start_index, suggestion
+            ),
+            Self::TooDeep(i) => write!(f, "Expression is nested too deeply at index {}", i),
+            Self::DisallowedIdentifier { name, span } => {
+                write!(f, "'{}' is not allowed at index {}", name, span.start_index)
+            }
             Self::InternalError(s) => write!(f, "{}", s),
         }
     }
 }
 
 impl Error for ParseError {}
+
+impl ParseError {
+    /// Returns the name of this error's variant, e.g. `"TrailingTokens"`, for
+    /// tools that tell errors apart without parsing their messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnexpectedEOF => "UnexpectedEOF",
+            Self::NumberExpected(_) => "NumberExpected",
+            Self::OperatorExpected(_) => "OperatorExpected",
+            Self::LeftParenExpected(_) => "LeftParenExpected",
+            Self::RightParenExpected(_) => "RightParenExpected",
+            Self::RightBracketExpected(_) => "RightBracketExpected",
+            Self::BarExpected(_) => "BarExpected",
+            Self::CommaExpected(_) => "CommaExpected",
+            Self::InvalidUnaryOperator(_) => "InvalidUnaryOperator",
+            Self::OperandExpected(_) => "OperandExpected",
+            Self::MismatchRightParen(_) => "MismatchRightParen",
+            Self::ExtraRightParens { .. } => "ExtraRightParens",
+            Self::UnclosedParens { .. } => "UnclosedParens",
+            Self::MissingOperand { .. } => "MissingOperand",
+            Self::MissingArgument { .. } => "MissingArgument",
+            Self::TrailingTokens { .. } => "TrailingTokens",
+            Self::WrongArity { .. } => "WrongArity",
+            Self::DidYouMean { .. } => "DidYouMean",
+            Self::TooDeep(_) => "TooDeep",
+            Self::DisallowedIdentifier { .. } => "DisallowedIdentifier",
+            Self::InternalError(_) => "InternalError",
+        }
+    }
+
+    /// Returns the location of this error in the source code, if it is known.
+    /// Errors only known by their index span a single character.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::NumberExpected(t)
+            | Self::OperatorExpected(t)
+            | Self::LeftParenExpected(t)
+            | Self::RightParenExpected(t)
+            | Self::RightBracketExpected(t)
+            | Self::BarExpected(t)
+            | Self::CommaExpected(t)
+            | Self::InvalidUnaryOperator(t)
+            | Self::OperandExpected(t) => Some(t.span),
+            Self::MismatchRightParen(i)
+            | Self::ExtraRightParens { index: i, .. }
+            | Self::TooDeep(i) => Some(point(*i)),
+            Self::MissingOperand {
+                operator_span: span,
+            }
+            | Self::MissingArgument { comma_span: span }
+            | Self::TrailingTokens { span }
+            | Self::UnclosedParens { span, .. }
+            | Self::DidYouMean { span, .. }
+            | Self::DisallowedIdentifier { span, .. } => Some(*span),
+            Self::UnexpectedEOF | Self::WrongArity { .. } | Self::InternalError(_) => None,
+        }
+    }
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::DomainError { func, value } => {
+                write!(f, "{} is undefined for {}", func.name(), value)
+            }
+            Self::Timeout => write!(f, "Evaluation timed out"),
+            Self::LengthMismatch { left, right } => {
+                write!(
+                    f,
+                    "Vectors of lengths {} and {} cannot be combined",
+                    left, right
+                )
+            }
+            Self::HistoryOutOfRange(n) => write!(f, "There is no result {} to recall", n),
+            Self::CyclicDefinition(n) => {
+                write!(f, "Variable '{}' is defined in terms of itself", n)
+            }
+            Self::NonConstantDeclaration(n) => {
+                write!(f, "Constant '{}' must only refer to other constants", n)
+            }
+            Self::ConstantRedefined(n) => {
+                write!(f, "Constant '{}' cannot be redefined", n)
+            }
+        }
+    }
+}
+
+impl Error for EvalError {}
+
+/// Creates a [`Span`] covering the single character at `index`.
+fn point(index: usize) -> Span {
+    Span {
+        start_index: index,
+        end_index: index,
+    }
+}
+
+/// Writes "parenthesis" in the singular or plural depending on `count`.
+fn parentheses(count: usize) -> &'static str {
+    if count == 1 {
+        "parenthesis"
+    } else {
+        "parentheses"
+    }
+}
+
+/// Serialises an error as a diagnostic for tools such as editors, with its
+/// [`code`](LexError::code), message and [`span`](LexError::span).
+#[cfg(feature = "serde")]
+fn serialize_diagnostic<S: Serializer>(
+    serializer: S,
+    name: &'static str,
+    code: &'static str,
+    message: String,
+    span: Option<Span>,
+) -> Result<S::Ok, S::Error> {
+    let mut state = serializer.serialize_struct(name, 3)?;
+    state.serialize_field("code", code)?;
+    state.serialize_field("message", &message)?;
+    state.serialize_field("span", &span)?;
+    state.end()
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for LexError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let message = self.to_string();
+        serialize_diagnostic(serializer, "LexError", self.code(), message, self.span())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for ParseError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let message = self.to_string();
+        serialize_diagnostic(serializer, "ParseError", self.code(), message, self.span())
+    }
+}
@@ -0,0 +1,125 @@
+//! This module implements a builder for constructing ASTs in Rust code.
+//!
+//! Nodes are built from [`num`] (or [`var`]) and combined using the methods of
+//! [`ExprBuilder`], e.g. `(3 + 4) * 2` is built with
+//! `num(3).add(num(4)).mul(num(2))`. Numbers can also be passed to the methods
+//! directly, e.g. `num(3).add(4).mul(2)`.
+//!
+//! Nodes can also be parsed from source code as an [`Expr`], e.g.
+//! `"(3 + 4) * 2".parse::<Expr>()`.
+
+use alloc::{boxed::Box, string::ToString, vec::Vec};
+use core::{fmt::Display, ops::Deref, str::FromStr};
+
+use crate::{
+    ast::Node, BinaryAction, BinaryNode, Function, FunctionNode, IfNode, Lexer, NodeBox, Number,
+    Parser, PlainNode, TilError, UnaryAction, UnaryNode, VariableNode,
+};
+
+/// [`Expr`] wraps a [`NodeBox`] parsed from source code with [`FromStr`].
+#[derive(Debug)]
+pub struct Expr(pub NodeBox);
+
+/// Creates a node storing a number.
+pub fn num<T: Into<Number>>(n: T) -> NodeBox {
+    Box::new(PlainNode::from(n))
+}
+
+/// Creates a node looking up a variable by its name and index.
+pub fn var(name: &str, index: usize) -> NodeBox {
+    Box::new(VariableNode::new(name.to_string(), index))
+}
+
+/// Creates a node applying a function to its arguments, choosing the same
+/// node type as the [`Parser`](crate::Parser) would.
+pub fn call(func: Function, mut args: Vec<NodeBox>) -> NodeBox {
+    match (func, args.len()) {
+        (Function::If, 3) => {
+            let otherwise = args.remove(2);
+            let then = args.remove(1);
+            let cond = args.remove(0);
+            Box::new(IfNode::new(cond, then, otherwise))
+        }
+        (func, 1) => Box::new(UnaryNode::new(UnaryAction::Func(func), args.remove(0))),
+        (func, _) => Box::new(FunctionNode::new(func, args)),
+    }
+}
+
+/// [`ExprBuilder`] provides chainable methods to combine [`NodeBox`]es.
+pub trait ExprBuilder: Sized {
+    /// Combines this node with another one using a [`BinaryAction`].
+    fn binary<T: Into<NodeBox>>(self, actor: BinaryAction, rhs: T) -> NodeBox;
+
+    /// Applies a [`UnaryAction`] to this node.
+    fn unary(self, actor: UnaryAction) -> NodeBox;
+
+    fn add<T: Into<NodeBox>>(self, rhs: T) -> NodeBox {
+        self.binary(BinaryAction::Add, rhs)
+    }
+
+    fn sub<T: Into<NodeBox>>(self, rhs: T) -> NodeBox {
+        self.binary(BinaryAction::Sub, rhs)
+    }
+
+    fn mul<T: Into<NodeBox>>(self, rhs: T) -> NodeBox {
+        self.binary(BinaryAction::Mul, rhs)
+    }
+
+    fn div<T: Into<NodeBox>>(self, rhs: T) -> NodeBox {
+        self.binary(BinaryAction::Div, rhs)
+    }
+
+    fn pow<T: Into<NodeBox>>(self, rhs: T) -> NodeBox {
+        self.binary(BinaryAction::Pow, rhs)
+    }
+
+    fn neg(self) -> NodeBox {
+        self.unary(UnaryAction::Neg)
+    }
+
+    /// Applies a function taking one argument to this node.
+    fn apply(self, func: Function) -> NodeBox {
+        self.unary(UnaryAction::Func(func))
+    }
+}
+
+impl ExprBuilder for NodeBox {
+    fn binary<T: Into<NodeBox>>(self, actor: BinaryAction, rhs: T) -> NodeBox {
+        Box::new(BinaryNode::new(self, actor, rhs.into()))
+    }
+
+    fn unary(self, actor: UnaryAction) -> NodeBox {
+        Box::new(UnaryNode::new(actor, self))
+    }
+}
+
+impl FromStr for Expr {
+    type Err = TilError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lexer = Lexer::from_source_code(s);
+        let mut parser = Parser::from_lexer(lexer);
+
+        Ok(Self(parser.parse()?))
+    }
+}
+
+impl Deref for Expr {
+    type Target = dyn Node;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<Expr> for NodeBox {
+    fn from(expr: Expr) -> Self {
+        expr.0
+    }
+}
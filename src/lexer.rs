@@ -3,15 +3,20 @@
 //! A lexer's job is to generate a stream of [`Token`]s from user input, which
 //! is used by the [`Parser`] to generate an Abstract Syntax Tree.
 
-use std::{fmt::Display, ops::Index, slice::SliceIndex};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{fmt::Display, ops::Index, slice::SliceIndex};
 
+#[cfg(feature = "decimal")]
+use rust_decimal::Decimal;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "dimensions")]
+use crate::DimensionTag;
 use crate::{eof, token, LexError};
 
 /// Special [`Result`] type for the lexer.
-type Result<T> = std::result::Result<T, LexError>;
+type Result<T> = core::result::Result<T, LexError>;
 
 /// Lexer for [`tilted`](crate). It parses user input and return [`Token`]s.
 #[derive(Debug, Clone)]
@@ -21,7 +26,70 @@ pub struct Lexer {
     source_code: Box<str>,
 
     /// The index of the current character, i.e. the one that is parsed next.
+    /// This is a character index, not a byte offset.
     current_index: usize,
+
+    /// Byte offset of the current character, so that the rest of the source
+    /// code can be sliced without counting characters.
+    current_offset: usize,
+
+    /// Whether newlines outside parentheses are lexed as
+    /// [`TokenKind::Newline`] instead of being skipped.
+    newlines: bool,
+
+    /// Number of currently unmatched left parentheses and brackets.
+    paren_depth: usize,
+
+    /// Locale-dependent characters used by this [`Lexer`].
+    options: LexerOptions,
+
+    /// What to do with characters that are not part of any [`Token`].
+    on_unknown: UnknownPolicy,
+
+    /// Whether the names of physical constants are lexed as their values.
+    #[cfg(feature = "physics")]
+    physical_constants: bool,
+
+    /// Additional names of functions, set by the embedder.
+    aliases: Vec<(String, Function)>,
+
+    /// Unrecognised characters skipped with [`UnknownPolicy::Skip`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    warnings: Vec<LexError>,
+}
+
+/// Options of a [`Lexer`], for locales that write numbers differently.
+///
+/// The two separators must differ from each other, as well as from any other
+/// character in the language, e.g. `3,14` and `hypot(3,5; 4)` can be lexed with
+/// `,` as the decimal separator and `;` as the argument separator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LexerOptions {
+    /// Character separating the integer part of a number from its decimal
+    /// places, `.` by default.
+    pub decimal_separator: char,
+
+    /// Character separating the arguments of a function, `,` by default.
+    pub argument_separator: char,
+}
+
+/// Handling of unrecognised characters, e.g. `$`, by a [`Lexer`], see
+/// [`Lexer::with_unknown_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UnknownPolicy {
+    /// Return [`LexError::UnrecognisedCharacter`].
+    #[default]
+    Error,
+
+    /// Skip the character, recording the error as a warning, see
+    /// [`Lexer::warnings`].
+    Skip,
+
+    /// End the input before the character, leaving it and everything after it
+    /// unlexed, see [`Lexer::rest`].
+    StopAndKeepRest,
 }
 
 /// Part of the source code tokenised. Returned by a [`Lexer`].
@@ -50,21 +118,51 @@ pub enum TokenKind {
     /// Floating-point number, i.e. real numbers that are not integers.
     Flt(f64),
 
+    /// Exact decimal number. With the `decimal` feature, this is used instead
+    /// of [`TokenKind::Flt`] for numbers with decimal places.
+    #[cfg(feature = "decimal")]
+    Dec(Decimal),
+
     /// Operator.
     Op(Operator),
 
     /// Function.
     Func(Function),
 
+    /// Identifier, i.e. the name of a variable. The name itself can be looked
+    /// up by indexing the [`Lexer`] with the [`Span`] of the [`Token`].
+    Ident,
+
     /// Left parenthesis.
     LeftParen,
 
     /// Right parenthesis.
     RightParen,
+
+    /// Left bracket, starting a vector with the `vector` feature.
+    LeftBracket,
+
+    /// Right bracket, ending a vector with the `vector` feature.
+    RightBracket,
+
+    /// Comma, separating function arguments.
+    Comma,
+
+    /// Vertical bar, enclosing an absolute value, e.g. `|x|`. Two bars in a
+    /// row are lexed as [`Operator::Or`] instead.
+    Bar,
+
+    /// Newline outside parentheses, separating expressions in a program (see
+    /// [`Parser::parse_program`](crate::Parser::parse_program)).
+    Newline,
+
+    /// Equals sign, assigning a value to a variable in a
+    /// [`Session`](crate::Session).
+    Assign,
 }
 
 /// Functions.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Function {
     /// Sine.
@@ -102,6 +200,47 @@ pub enum Function {
 
     /// Inverse cotangent.
     Acot,
+
+    /// Base-10 logarithm.
+    Log10,
+
+    /// Base-2 logarithm.
+    Log2,
+
+    /// Cube root.
+    Cbrt,
+
+    /// Conversion from degrees to radians.
+    Rad,
+
+    /// Conversion from radians to degrees.
+    Deg,
+
+    /// Euclidean distance, i.e. the length of the hypotenuse.
+    Hypot,
+
+    /// Rounding to a number of significant figures, i.e. `round_sig(x, n)`.
+    RoundSig,
+
+    /// Percentage change from an old value to a new one, i.e.
+    /// `pct_change(old, new)`.
+    PctChange,
+
+    /// Dot product of two vectors of the same length.
+    #[cfg(feature = "vector")]
+    Dot,
+
+    /// Length, i.e. the Euclidean norm, of a vector.
+    #[cfg(feature = "vector")]
+    Len,
+
+    /// Conditional, i.e. `if(cond, then, else)`. Only the chosen branch is
+    /// evaluated.
+    If,
+
+    /// Earlier result, i.e. `hist(n)` is the `n`th result, as recalled by
+    /// [`Variables::recall`](crate::Variables::recall).
+    Hist,
 }
 
 /// Basic mathematical operators.
@@ -114,17 +253,60 @@ pub enum Operator {
     /// Operator `-`
     Minus,
 
-    /// Operator `*`.
+    /// Operator `*` (or `×`).
     Star,
 
-    /// Operator `/`.
+    /// Operator `/` (or `÷`).
     Slash,
 
+    /// Operator `//`.
+    DoubleSlash,
+
+    /// Operator `%` (or `mod`).
+    Percent,
+
     /// Operator `^`.
     Caret,
+
+    /// Operator `<`.
+    Less,
+
+    /// Operator `<=`.
+    LessEqual,
+
+    /// Operator `>`.
+    Greater,
+
+    /// Operator `>=`.
+    GreaterEqual,
+
+    /// Operator `==`.
+    Equal,
+
+    /// Operator `!=`.
+    NotEqual,
+
+    /// Operator `&&`.
+    And,
+
+    /// Operator `||`.
+    Or,
+
+    /// Operator `!` (or `not`).
+    Not,
+
+    /// Operator `xor`, which is always bitwise exclusive or, unlike `^`.
+    Xor,
+
+    /// Operator `fdiv`, which always promotes integers that do not divide
+    /// evenly to a float, unlike `/`.
+    FDiv,
 }
 
 /// Spatial information of a [`Token`].
+///
+/// Indices are measured in characters (i.e. [`char`]s), not bytes, so they
+/// remain meaningful for source code containing multibyte characters.
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Span {
@@ -135,19 +317,74 @@ pub struct Span {
     pub end_index: usize,
 }
 
+impl Span {
+    /// Creates the smallest [`Span`] covering both `a` and `b`, including
+    /// anything in between.
+    pub fn merge(a: Span, b: Span) -> Span {
+        Span {
+            start_index: a.start_index.min(b.start_index),
+            end_index: a.end_index.max(b.end_index),
+        }
+    }
+
+    /// Finds the line and column of the start of this [`Span`] in `source`,
+    /// the source code it was created from, both starting at 1. Columns are
+    /// counted in characters, like indices. A [`Span`] past the end of
+    /// `source`, e.g. that of EOF, is placed right after its last character.
+    pub fn line_col(&self, source: &str) -> (usize, usize) {
+        let mut position = (1, 1);
+        for c in source.chars().take(self.start_index) {
+            position = match c {
+                '\n' => (position.0 + 1, 1),
+                _ => (position.0, position.1 + 1),
+            };
+        }
+
+        position
+    }
+}
+
 impl Display for Token {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:?} at index {}", self.kind, self.span.start_index)
     }
 }
 
+impl Operator {
+    /// Returns the symbol of this [`Operator`], e.g. `+` for
+    /// [`Plus`](Operator::Plus).
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Plus => "+",
+            Self::Minus => "-",
+            Self::Star => "*",
+            Self::Slash => "/",
+            Self::DoubleSlash => "//",
+            Self::Percent => "%",
+            Self::Caret => "^",
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterEqual => ">=",
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::And => "&&",
+            Self::Or => "||",
+            Self::Not => "!",
+            Self::Xor => "xor",
+            Self::FDiv => "fdiv",
+        }
+    }
+}
+
 impl From<char> for Operator {
     fn from(value: char) -> Self {
         match value {
             '+' => Self::Plus,
             '-' => Self::Minus,
-            '*' => Self::Star,
-            '/' => Self::Slash,
+            '*' | '×' => Self::Star,
+            '/' | '÷' => Self::Slash,
+            '%' => Self::Percent,
             '^' => Self::Caret,
 
             // This also guards against attempts to add new operators
@@ -157,9 +394,54 @@ impl From<char> for Operator {
     }
 }
 
+impl Function {
+    /// Returns the number of arguments this [`Function`] takes.
+    pub fn arity(&self) -> usize {
+        match self {
+            Self::Hypot | Self::RoundSig | Self::PctChange => 2,
+            #[cfg(feature = "vector")]
+            Self::Dot => 2,
+            Self::If => 3,
+            _ => 1,
+        }
+    }
+
+    /// Returns the name of this [`Function`] as written in source code.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sin => "sin",
+            Self::Cos => "cos",
+            Self::Tan => "tan",
+            Self::Csc => "csc",
+            Self::Sec => "sec",
+            Self::Cot => "cot",
+            Self::Asin => "asin",
+            Self::Acos => "acos",
+            Self::Atan => "atan",
+            Self::Acsc => "acsc",
+            Self::Asec => "asec",
+            Self::Acot => "acot",
+            Self::Log10 => "log10",
+            Self::Log2 => "log2",
+            Self::Cbrt => "cbrt",
+            Self::Rad => "rad",
+            Self::Deg => "deg",
+            Self::Hypot => "hypot",
+            Self::RoundSig => "round_sig",
+            Self::PctChange => "pct_change",
+            #[cfg(feature = "vector")]
+            Self::Dot => "dot",
+            #[cfg(feature = "vector")]
+            Self::Len => "len",
+            Self::If => "if",
+            Self::Hist => "hist",
+        }
+    }
+}
+
 impl TryFrom<&str> for Function {
     type Error = ();
-    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: &str) -> core::result::Result<Self, Self::Error> {
         match value {
             "sin" => Ok(Self::Sin),
             "cos" => Ok(Self::Cos),
@@ -173,13 +455,27 @@ impl TryFrom<&str> for Function {
             "acsc" => Ok(Self::Acsc),
             "asec" => Ok(Self::Asec),
             "acot" => Ok(Self::Acot),
+            "log10" => Ok(Self::Log10),
+            "log2" => Ok(Self::Log2),
+            "cbrt" => Ok(Self::Cbrt),
+            "rad" => Ok(Self::Rad),
+            "deg" => Ok(Self::Deg),
+            "hypot" => Ok(Self::Hypot),
+            "round_sig" => Ok(Self::RoundSig),
+            "pct_change" => Ok(Self::PctChange),
+            #[cfg(feature = "vector")]
+            "dot" => Ok(Self::Dot),
+            #[cfg(feature = "vector")]
+            "len" => Ok(Self::Len),
+            "if" => Ok(Self::If),
+            "hist" => Ok(Self::Hist),
             _ => Err(()),
         }
     }
 }
 
 impl Display for Function {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Function::Sin => write!(f, "Sin"),
             Function::Cos => write!(f, "Cos"),
@@ -193,10 +489,105 @@ impl Display for Function {
             Function::Acsc => write!(f, "Acsc"),
             Function::Asec => write!(f, "Asec"),
             Function::Acot => write!(f, "Acot"),
+            Function::Log10 => write!(f, "Log10"),
+            Function::Log2 => write!(f, "Log2"),
+            Function::Cbrt => write!(f, "Cbrt"),
+            Function::Rad => write!(f, "Rad"),
+            Function::Deg => write!(f, "Deg"),
+            Function::Hypot => write!(f, "Hypot"),
+            Function::RoundSig => write!(f, "RoundSig"),
+            Function::PctChange => write!(f, "PctChange"),
+            #[cfg(feature = "vector")]
+            Function::Dot => write!(f, "Dot"),
+            #[cfg(feature = "vector")]
+            Function::Len => write!(f, "Len"),
+            Function::If => write!(f, "If"),
+            Function::Hist => write!(f, "Hist"),
         }
     }
 }
 
+impl Default for LexerOptions {
+    fn default() -> Self {
+        Self {
+            decimal_separator: '.',
+            argument_separator: ',',
+        }
+    }
+}
+
+/// Converts a name to the value of a built-in constant, if there is one.
+fn constant(name: &str) -> Option<f64> {
+    match name {
+        "inf" => Some(f64::INFINITY),
+        "nan" => Some(f64::NAN),
+        _ => None,
+    }
+}
+
+/// Converts a name to the value of a physical constant in SI units, if there
+/// is one, see [`Lexer::with_physical_constants`]:
+///
+/// - `c`: speed of light in vacuum, `299792458` m/s.
+/// - `h`: Planck constant, `6.62607015e-34` J s.
+/// - `NA`: Avogadro constant, `6.02214076e23` 1/mol.
+/// - `kB`: Boltzmann constant, `1.380649e-23` J/K.
+/// - `G`: gravitational constant, `6.6743e-11` m^3/(kg s^2).
+#[cfg(feature = "physics")]
+fn physical_constant(name: &str) -> Option<f64> {
+    match name {
+        "c" => Some(299_792_458.0),
+        "h" => Some(6.626_070_15e-34),
+        "NA" => Some(6.022_140_76e23),
+        "kB" => Some(1.380_649e-23),
+        "G" => Some(6.674_3e-11),
+        _ => None,
+    }
+}
+
+/// Converts the name of a physical constant to its dimension, if it is one.
+#[cfg(feature = "dimensions")]
+pub(crate) fn dimension(name: &str) -> Option<DimensionTag> {
+    match name {
+        "c" => Some(DimensionTag("c")),
+        "h" => Some(DimensionTag("h")),
+        "NA" => Some(DimensionTag("NA")),
+        "kB" => Some(DimensionTag("kB")),
+        "G" => Some(DimensionTag("G")),
+        _ => None,
+    }
+}
+
+/// Converts a common alternative name of a function to the [`Function`], if
+/// there is one.
+fn alias(name: &str) -> Option<Function> {
+    match name {
+        "arcsin" => Some(Function::Asin),
+        "arccos" => Some(Function::Acos),
+        "arctan" => Some(Function::Atan),
+        "arccsc" => Some(Function::Acsc),
+        "arcsec" => Some(Function::Asec),
+        "arccot" => Some(Function::Acot),
+        "tg" => Some(Function::Tan),
+        "ctg" => Some(Function::Cot),
+        "cosec" => Some(Function::Csc),
+        "lg" => Some(Function::Log10),
+        "lb" => Some(Function::Log2),
+        _ => None,
+    }
+}
+
+/// Converts a name to the radix of a radix literal, if there is one.
+fn radix(name: &str) -> Option<u32> {
+    match name {
+        "bin" => Some(2),
+        "oct" => Some(8),
+        "hex" => Some(16),
+        _ => None,
+    }
+}
+
+/// Indexes the source code by bytes, like [`str`] does.
 impl<Idx: SliceIndex<str>> Index<Idx> for Lexer {
     type Output = Idx::Output;
 
@@ -205,11 +596,23 @@ impl<Idx: SliceIndex<str>> Index<Idx> for Lexer {
     }
 }
 
+/// Indexes the source code by a [`Span`], whose indices are in characters.
 impl Index<Span> for Lexer {
     type Output = str;
 
     fn index(&self, index: Span) -> &Self::Output {
-        &self[index.start_index..(index.end_index - 1)]
+        // Convert character indices to byte offsets. Out-of-bound indices (such
+        // as those of EOF) are mapped to the end of the source code.
+        let byte_offset = |i: usize| {
+            self.source_code
+                .char_indices()
+                .nth(i)
+                .map_or(self.source_code.len(), |(offset, _)| offset)
+        };
+
+        let start = byte_offset(index.start_index);
+        let end = byte_offset(index.end_index + 1);
+        &self[start..end]
     }
 }
 
@@ -231,53 +634,171 @@ impl Lexer {
         Lexer {
             source_code: Box::from(source_code.as_ref()),
             current_index: 0,
+            current_offset: 0,
+            newlines: false,
+            paren_depth: 0,
+            options: LexerOptions::default(),
+            on_unknown: UnknownPolicy::default(),
+            #[cfg(feature = "physics")]
+            physical_constants: false,
+            aliases: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 
+    /// Sets the [`LexerOptions`] of this [`Lexer`].
+    pub fn with_options(mut self, options: LexerOptions) -> Lexer {
+        self.options = options;
+        self
+    }
+
+    /// Sets what to do with characters that are not part of any [`Token`],
+    /// [`UnknownPolicy::Error`] by default.
+    pub fn with_unknown_policy(mut self, on_unknown: UnknownPolicy) -> Lexer {
+        self.on_unknown = on_unknown;
+        self
+    }
+
+    /// Sets whether the names of physical constants, such as `c` (the speed
+    /// of light), are lexed as their values in SI units instead of as
+    /// variables. Disabled by default.
+    #[cfg(feature = "physics")]
+    pub fn with_physical_constants(mut self, physical_constants: bool) -> Lexer {
+        self.physical_constants = physical_constants;
+        self
+    }
+
+    /// Adds another name for a [`Function`], e.g. `sine` for
+    /// [`Function::Sin`]. Names of built-in functions cannot be redefined,
+    /// while built-in aliases such as `arcsin` can.
+    pub fn with_alias<T: Into<String>>(mut self, alias: T, func: Function) -> Lexer {
+        self.aliases.push((alias.into(), func));
+        self
+    }
+
+    /// Sets whether newlines outside parentheses are lexed as
+    /// [`TokenKind::Newline`] instead of being skipped.
+    pub fn set_newlines(&mut self, newlines: bool) {
+        self.newlines = newlines;
+    }
+
+    /// Unrecognised characters that were skipped, in order, if
+    /// the [`UnknownPolicy`] is [`UnknownPolicy::Skip`].
+    pub fn warnings(&self) -> &[LexError] {
+        &self.warnings
+    }
+
+    /// Returns the part of the source code that has not been lexed yet, e.g.
+    /// after stopping with [`UnknownPolicy::StopAndKeepRest`].
+    pub fn rest(&self) -> &str {
+        &self.source_code[self.current_offset..]
+    }
+
+    /// Moves past the next `count` characters.
+    fn advance(&mut self, count: usize) {
+        let bytes: usize = self.rest().chars().take(count).map(char::len_utf8).sum();
+        self.current_index += count;
+        self.current_offset += bytes;
+    }
+
     /// Gets the next [`Token`] from source.
     pub fn lex(&mut self) -> Result<Token> {
-        // Skip whitespaces.
-        for c in self.source_code.chars().skip(self.current_index) {
-            if c.is_whitespace() {
-                self.current_index += 1;
-            } else {
+        loop {
+            let (c, index) = match self.lex_token() {
+                Err(LexError::UnrecognisedCharacter(c, index)) => (c, index),
+                result => return result,
+            };
+
+            // Continue after the character, or stop right before it.
+            match self.on_unknown {
+                UnknownPolicy::Error => return Err(LexError::UnrecognisedCharacter(c, index)),
+                UnknownPolicy::Skip => {
+                    self.warnings
+                        .push(LexError::UnrecognisedCharacter(c, index));
+                    // Unrecognised characters are always the current one.
+                    self.advance(1);
+                }
+                UnknownPolicy::StopAndKeepRest => return Ok(eof!(index)),
+            }
+        }
+    }
+
+    /// Gets the next [`Token`] from source, failing on unrecognised
+    /// characters.
+    fn lex_token(&mut self) -> Result<Token> {
+        // Skip whitespaces, apart from significant newlines, and comments.
+        loop {
+            while let Some(c) = self.rest().chars().next() {
+                if c == '\n' && self.newlines && self.paren_depth == 0 {
+                    self.advance(1);
+                    return Ok(token!(TokenKind::Newline, self.current_index - 1, 1));
+                } else if c.is_whitespace() {
+                    self.advance(1);
+                } else {
+                    break;
+                }
+            }
+
+            if !self.skip_comment() {
                 break;
             }
         }
 
         // Check for EOF.
-        if self.current_index >= self.source_code.len() {
+        if self.current_offset >= self.source_code.len() {
             return Ok(eof!(self.current_index));
         }
 
         // Assign to handlers based on the next character.
-        match self
-            .source_code
-            .chars()
-            .nth(self.current_index)
-            .ok_or(LexError::InternalError(
-                "Unable to unwrap next character in source",
-                self.current_index,
-            ))? {
+        match self.rest().chars().next().ok_or(LexError::InternalError(
+            "Unable to unwrap next character in source",
+            self.current_index,
+        ))? {
             // Numbers (integers and reals)
             // Can start with a dot or number
-            '.' | '0'..='9' => self.handle_number(),
+            c if c.is_ascii_digit() || c == self.options.decimal_separator => self.handle_number(),
+
+            // Argument separator.
+            c if c == self.options.argument_separator => {
+                self.advance(1);
+                Ok(token!(TokenKind::Comma, self.current_index - 1, 1))
+            }
 
             // Operators.
-            '+' | '-' | '*' | '/' | '^' => self.handle_operator(),
+            '+' | '-' | '*' | '/' | '%' | '^' | '×' | '÷' => self.handle_operator(),
 
             // Parentheses.
             // These are short so they are handled in-place.
             '(' => {
-                self.current_index += 1;
+                self.advance(1);
+                self.paren_depth += 1;
                 Ok(token!(TokenKind::LeftParen, self.current_index - 1, 1))
             }
             ')' => {
-                self.current_index += 1;
+                self.advance(1);
+                self.paren_depth = self.paren_depth.saturating_sub(1);
                 Ok(token!(TokenKind::RightParen, self.current_index - 1, 1))
             }
 
-            // Functions.
+            // Brackets, which also allow newlines inside them.
+            '[' => {
+                self.advance(1);
+                self.paren_depth += 1;
+                Ok(token!(TokenKind::LeftBracket, self.current_index - 1, 1))
+            }
+            ']' => {
+                self.advance(1);
+                self.paren_depth = self.paren_depth.saturating_sub(1);
+                Ok(token!(TokenKind::RightBracket, self.current_index - 1, 1))
+            }
+
+            // Comparisons, negation and assignment.
+            '<' | '>' | '=' | '!' => self.handle_comparison(),
+
+            // Logical operators.
+            '&' | '|' => self.handle_logic(),
+
+            // Functions, constants and identifiers.
             c if c.is_ascii_alphabetic() => self.handle_function(),
 
             // Any other characters.
@@ -289,73 +810,151 @@ impl Lexer {
         // Keep track of the original index for later.
         let original_index = self.current_index;
 
-        // Tracker for decimal place.
+        // Trackers for decimal place and exponent.
         let mut seen_dot = false;
+        let mut seen_exp = false;
+        let mut valid = true;
 
         // Reserve enough space for a 100-char string.
         // Most numbers (hopefully) are within this limit. However, we still
         // need to cover the potential cases of more than 100 digits.
         let mut result = String::with_capacity(100);
-        for c in self.source_code[self.current_index..].chars() {
+        let mut chars = self.rest().chars().peekable();
+        while let Some(&c) = chars.peek() {
             match c {
-                '.' => {
-                    if !seen_dot {
-                        // Dot (if not seen)
-                        self.current_index += 1;
-                        seen_dot = true;
-                        result.push('.');
-                    } else {
-                        // Dot (if already seen)
-                        return Err(LexError::UnrecognisedCharacter(c, self.current_index));
-                    }
-                }
-
                 // Digit
-                c if c.is_ascii_digit() => {
-                    result.push(c);
-                    self.current_index += 1;
+                c if c.is_ascii_digit() => (),
+
+                // Dot
+                // A trailing dot (`5.`) ends the number, but further dots (as
+                // in `5..` or `1.2.3`) make the whole number invalid rather
+                // than being split off.
+                c if c == self.options.decimal_separator => {
+                    valid &= !seen_dot;
+                    seen_dot = true;
                 }
 
                 // Anything else
                 _ => break,
             }
+
+            result.push(c);
+            chars.next();
+        }
+
+        // Exponent, e.g. `1.5e-3`. An `e` followed by a letter is not part of
+        // the number, e.g. `2ex` is `2` followed by the identifier `ex`.
+        if matches!(chars.peek(), Some('e' | 'E')) {
+            let mut lookahead = chars.clone();
+            lookahead.next();
+            if !lookahead.next().is_some_and(|c| c.is_ascii_alphabetic()) {
+                seen_exp = true;
+
+                // The `e`, an optional sign, then digits.
+                result.extend(chars.next());
+                result.extend(chars.next_if(|c| matches!(c, '+' | '-')));
+                while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                    result.push(c);
+                }
+            }
+        }
+
+        // Update current index. All characters of a number are ASCII, apart
+        // from the decimal separator.
+        let length = result.chars().count();
+        self.advance(length);
+
+        // Numbers are converted with a dot as the decimal separator.
+        let text = result.replace(self.options.decimal_separator, ".");
+
+        // A number can omit its integer part (`.5`) but not all of its digits.
+        if text == "." {
+            return Err(LexError::MissingDigits(original_index));
+        }
+
+        let invalid = || LexError::InvalidNumber {
+            text: result.clone(),
+            span: Span {
+                start_index: original_index,
+                end_index: original_index + length - 1,
+            },
+        };
+
+        if !valid {
+            return Err(invalid());
         }
 
-        // Convert string to integer or float based on seen_dot.
-        if seen_dot {
+        // Convert string to integer or decimal based on seen_dot and seen_exp.
+        #[cfg(feature = "decimal")]
+        if seen_dot || seen_exp {
+            let num = if seen_exp {
+                Decimal::from_scientific(&text)
+            } else {
+                text.parse::<Decimal>()
+            }
+            .map_err(|_| invalid())?;
+
+            return Ok(token!(TokenKind::Dec(num), original_index, length));
+        }
+
+        // Convert string to integer or float based on seen_dot and seen_exp.
+        if seen_dot || seen_exp {
             // Float
-            let num = result
-                .parse::<f64>()
-                .map_err(|_| LexError::InternalError("Parse float failed", self.current_index))?;
+            let num = text.parse::<f64>().map_err(|_| invalid())?;
 
-            Ok(token!(TokenKind::Flt(num), original_index, result.len()))
+            Ok(token!(TokenKind::Flt(num), original_index, length))
         } else {
             // Integer
-            let num = result
-                .parse::<u64>()
-                .map_err(|_| LexError::InternalError("Parse integer failed", self.current_index))?;
+            let num = text.parse::<u64>().map_err(|_| invalid())?;
+
+            Ok(token!(TokenKind::Int(num), original_index, length))
+        }
+    }
 
-            Ok(token!(TokenKind::Int(num), original_index, result.len()))
+    /// Skips a comment, which starts with `--` followed by a whitespace (or the
+    /// end of the source code) and runs until the end of the line, e.g.
+    /// `5 -- five`. Otherwise, `--` is two minus signs, e.g. `5 --3` is
+    /// `5 - (-3)`. Returns whether a comment was skipped.
+    fn skip_comment(&mut self) -> bool {
+        let mut chars = self.rest().chars();
+        if chars.next() != Some('-') || chars.next() != Some('-') {
+            return false;
         }
+
+        let mut chars = chars.peekable();
+        if !chars.peek().is_none_or(|c| c.is_whitespace()) {
+            return false;
+        }
+
+        // The newline ending the comment is kept, as it may be significant.
+        let length = 2 + chars.take_while(|c| *c != '\n').count();
+        self.advance(length);
+        true
     }
 
     pub fn handle_operator(&mut self) -> Result<Token> {
-        // Operator has only one char so it should be trivial.
-        let op =
-            self.source_code
-                .chars()
-                .nth(self.current_index)
-                .ok_or(LexError::InternalError(
-                    "Unable to unwrap operator",
-                    self.current_index,
-                ))?;
+        // Operator has only one char so it should be trivial, except for `//`.
+        let mut chars = self.rest().chars();
+        if chars.next() == Some('/') && chars.next() == Some('/') {
+            self.advance(2);
+            return Ok(token!(
+                TokenKind::Op(Operator::DoubleSlash),
+                self.current_index - 2,
+                2
+            ));
+        }
+
+        let op = self.rest().chars().next().ok_or(LexError::InternalError(
+            "Unable to unwrap operator",
+            self.current_index,
+        ))?;
 
         // The parent match operator should have narrowed down the valid ones,
         // but I think it is still important to check here, just in case I mess
         // up somewhere else. Resources are cheap anyway :)
         match op {
-            '+' | '-' | '*' | '/' | '^' => {
-                self.current_index += 1;
+            '+' | '-' | '*' | '/' | '%' | '^' | '×' | '÷' => {
+                self.advance(1);
                 Ok(token!(TokenKind::Op(op.into()), self.current_index - 1, 1))
             }
             _ => Err(LexError::InternalError(
@@ -365,26 +964,171 @@ impl Lexer {
         }
     }
 
+    pub fn handle_comparison(&mut self) -> Result<Token> {
+        // Keep track of the original index for later.
+        let original_index = self.current_index;
+
+        // Comparisons have one or two chars, the second one being `=`.
+        let mut chars = self.rest().chars();
+        let first = chars.next().ok_or(LexError::InternalError(
+            "Unable to unwrap comparison",
+            self.current_index,
+        ))?;
+        let equals = chars.next() == Some('=');
+
+        let kind = match (first, equals) {
+            ('<', false) => TokenKind::Op(Operator::Less),
+            ('<', true) => TokenKind::Op(Operator::LessEqual),
+            ('>', false) => TokenKind::Op(Operator::Greater),
+            ('>', true) => TokenKind::Op(Operator::GreaterEqual),
+            ('=', true) => TokenKind::Op(Operator::Equal),
+            ('!', true) => TokenKind::Op(Operator::NotEqual),
+            ('!', false) => TokenKind::Op(Operator::Not),
+
+            // A single `=` is an assignment.
+            ('=', false) => TokenKind::Assign,
+
+            (c, _) => return Err(LexError::UnrecognisedCharacter(c, self.current_index)),
+        };
+
+        // Update current index.
+        let length = if equals { 2 } else { 1 };
+        self.advance(length);
+
+        Ok(token!(kind, original_index, length))
+    }
+
+    pub fn handle_logic(&mut self) -> Result<Token> {
+        // Logical operators consist of the same char twice.
+        let mut chars = self.rest().chars();
+        let first = chars.next().ok_or(LexError::InternalError(
+            "Unable to unwrap logical operator",
+            self.current_index,
+        ))?;
+
+        let kind = match (first, chars.next()) {
+            ('&', Some('&')) => TokenKind::Op(Operator::And),
+            ('|', Some('|')) => TokenKind::Op(Operator::Or),
+
+            // A single bar encloses an absolute value.
+            ('|', _) => {
+                self.advance(1);
+                return Ok(token!(TokenKind::Bar, self.current_index - 1, 1));
+            }
+
+            (c, _) => return Err(LexError::UnrecognisedCharacter(c, self.current_index)),
+        };
+
+        // Update current index.
+        self.advance(2);
+
+        Ok(token!(kind, self.current_index - 2, 2))
+    }
+
     pub fn handle_function(&mut self) -> Result<Token> {
         // Keep track of the original index for later.
         let original_index = self.current_index;
 
-        // Trigos only contain letters.
-        let name = self.source_code[self.current_index..]
+        // Names start with a letter (guaranteed by the caller) and may contain
+        // digits and underscores afterwards, e.g. `log10` or `round_sig`.
+        let name = self
+            .rest()
             .chars()
-            .take_while(|c| c.is_ascii_alphabetic())
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
             .collect::<String>();
 
-        // Convert string to trigonometric function.
-        let trigo = name
-            .as_str()
-            .try_into()
-            .map_err(|_| LexError::UnrecognisedFunction(name.clone(), self.current_index))?;
+        // Constants are lexed as floating-point numbers.
+        #[cfg(feature = "physics")]
+        let value = constant(&name)
+            .or_else(|| physical_constant(&name).filter(|_| self.physical_constants));
+        #[cfg(not(feature = "physics"))]
+        let value = constant(&name);
+        if let Some(value) = value {
+            self.advance(name.len());
+            return Ok(token!(TokenKind::Flt(value), original_index, name.len()));
+        }
+
+        // Radix literals are lexed as integers.
+        if let Some(radix) = radix(&name) {
+            if let Some(token) = self.handle_radix(name.len(), radix)? {
+                return Ok(token);
+            }
+        }
+
+        // Convert string to keyword or function (possibly by an alias, where
+        // those of the embedder come first), anything else is an identifier.
+        let func = Function::try_from(name.as_str()).ok().or_else(|| {
+            self.aliases
+                .iter()
+                .find_map(|(alias, func)| (*alias == name).then_some(*func))
+                .or_else(|| alias(&name))
+        });
+        let kind = match func {
+            _ if name == "not" => TokenKind::Op(Operator::Not),
+            _ if name == "mod" => TokenKind::Op(Operator::Percent),
+            _ if name == "xor" => TokenKind::Op(Operator::Xor),
+            _ if name == "fdiv" => TokenKind::Op(Operator::FDiv),
+            Some(func) => TokenKind::Func(func),
+
+            // A name called like a function must be one, so that typos such as
+            // `sn(0)` are not taken for implicit multiplication.
+            None if self.rest()[name.len()..].starts_with('(') => {
+                return Err(LexError::UnrecognisedFunction(name, original_index));
+            }
+            None => TokenKind::Ident,
+        };
+
+        // Update current index.
+        self.advance(name.len());
+
+        Ok(token!(kind, original_index, name.len()))
+    }
+
+    /// Handles radix literals, i.e. `bin(...)`, `oct(...)` and `hex(...)`, where
+    /// the parentheses contain digits in the corresponding base, e.g. `hex(FF)`
+    /// is `255`. The whole literal is a single integer token; this is not a
+    /// function call, so the digits cannot be an expression.
+    ///
+    /// Returns [`None`] if the name is not followed by a left parenthesis, in
+    /// which case it is lexed as an identifier instead.
+    fn handle_radix(&mut self, name_length: usize, radix: u32) -> Result<Option<Token>> {
+        // Keep track of the original index for later.
+        let original_index = self.current_index;
+
+        // Check for a left parenthesis right after the name.
+        let mut chars = self.rest().chars().skip(name_length);
+        if chars.next() != Some('(') {
+            return Ok(None);
+        }
+
+        // Take everything up to the right parenthesis.
+        let mut digits = String::new();
+        let mut closed = false;
+        for c in chars {
+            if c == ')' {
+                closed = true;
+                break;
+            }
+            digits.push(c);
+        }
+
+        // Name, parentheses and digits.
+        let length = name_length + digits.chars().count() + 1 + closed as usize;
+        let num = u64::from_str_radix(digits.trim(), radix)
+            .ok()
+            .filter(|_| closed)
+            .ok_or_else(|| LexError::InvalidNumber {
+                text: self.rest().chars().take(length).collect(),
+                span: Span {
+                    start_index: original_index,
+                    end_index: original_index + length - 1,
+                },
+            })?;
 
         // Update current index.
-        self.current_index += name.len();
+        self.advance(length);
 
-        Ok(token!(TokenKind::Func(trigo), original_index, name.len()))
+        Ok(Some(token!(TokenKind::Int(num), original_index, length)))
     }
 
     /// Reverts this [`Lexer`] to its original state.
@@ -392,5 +1136,8 @@ impl Lexer {
     pub fn reset(&mut self) {
         // Simply set the index to 0 to reset.
         self.current_index = 0;
+        self.current_offset = 0;
+        self.paren_depth = 0;
+        self.warnings.clear();
     }
 }
@@ -1,17 +1,71 @@
 #![deny(clippy::all)]
 #![warn(rustdoc::all)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::string::String;
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("either the `std` or the `libm` feature must be enabled");
 
 pub mod ast;
 #[cfg(feature = "cli")]
 pub mod cli;
 pub mod error;
+pub mod expr;
 pub mod lexer;
 pub mod macros;
+mod math;
 pub mod parser;
+#[cfg(feature = "std")]
+pub mod session;
 
-pub use ast::{BinaryAction, BinaryNode, NodeBox, Number, PlainNode, UnaryAction, UnaryNode};
+#[cfg(feature = "vector")]
+pub use ast::VectorNode;
+pub use ast::{
+    numerically_equivalent, BinaryAction, BinaryNode, CodeType, EvalOrder, EvalResult,
+    FormatOptions, FunctionNode, IfNode, NodeBox, Number, NumberKind, NumberLayout, PlainNode,
+    TotalNumber, UnaryAction, UnaryNode, VariableNode, Variables,
+};
+#[cfg(feature = "decimal")]
+pub use ast::{DecimalContext, DecimalRounding};
+#[cfg(feature = "dimensions")]
+pub use ast::{DimensionMismatch, DimensionTag};
 #[cfg(feature = "cli")]
 pub use cli::CliParser;
-pub use error::{LexError, ParseError, TilError};
-pub use lexer::{Function, Lexer, Operator, Span, Token, TokenKind};
-pub use parser::Parser;
+pub use error::{EvalError, LexError, ParseError, TilError};
+pub use lexer::{Function, Lexer, LexerOptions, Operator, Span, Token, TokenKind, UnknownPolicy};
+pub use parser::{CaretMeaning, GrammarRule, IntDivMode, Parser, Statements};
+#[cfg(feature = "std")]
+pub use session::Session;
+
+/// Parses and evaluates source code in one go.
+///
+/// This never panics, whatever the input: invalid input, as well as input that
+/// is nested or chained too deeply to be evaluated safely, results in an error
+/// instead.
+/// Numbers that overflow fall back to floating-point numbers, while functions
+/// applied outside their domain (e.g. `asin(2)`) result in an error.
+pub fn try_evaluate<T: AsRef<str>>(source_code: T) -> Result<Number, TilError> {
+    let lexer = Lexer::from_source_code(source_code);
+    let mut parser = Parser::from_lexer(lexer);
+
+    Ok(parser.parse()?.try_evaluate()?)
+}
+
+/// Parses and evaluates source code in one go like [`try_evaluate`], then
+/// formats the result with [`Number::to_string_with_layout`].
+pub fn eval_formatted<T: AsRef<str>>(
+    source_code: T,
+    opts: &FormatOptions,
+    layout: &NumberLayout,
+) -> Result<String, TilError> {
+    Ok(try_evaluate(source_code)?.to_string_with_layout(opts, layout))
+}
+
+/// Parses source code one line at a time, as with [`Parser::parse_program`],
+/// without building all ASTs up front.
+pub fn parse_statements<T: AsRef<str>>(source_code: T) -> Statements {
+    Parser::from_lexer(Lexer::from_source_code(source_code)).into_statements()
+}
@@ -0,0 +1,67 @@
+//! This module provides the floating-point functions used during evaluation.
+//!
+//! They come from the standard library if the `std` feature is enabled, and
+//! from [`libm`](https://docs.rs/libm) otherwise, so that evaluation works
+//! without `std`.
+
+#[cfg(feature = "std")]
+mod imp {
+    pub fn sin(x: f64) -> f64 {
+        x.sin()
+    }
+
+    pub fn cos(x: f64) -> f64 {
+        x.cos()
+    }
+
+    pub fn tan(x: f64) -> f64 {
+        x.tan()
+    }
+
+    pub fn asin(x: f64) -> f64 {
+        x.asin()
+    }
+
+    pub fn acos(x: f64) -> f64 {
+        x.acos()
+    }
+
+    pub fn atan(x: f64) -> f64 {
+        x.atan()
+    }
+
+    pub fn log10(x: f64) -> f64 {
+        x.log10()
+    }
+
+    pub fn log2(x: f64) -> f64 {
+        x.log2()
+    }
+
+    pub fn cbrt(x: f64) -> f64 {
+        x.cbrt()
+    }
+
+    pub fn hypot(x: f64, y: f64) -> f64 {
+        x.hypot(y)
+    }
+
+    pub fn pow(x: f64, y: f64) -> f64 {
+        x.powf(y)
+    }
+
+    pub fn floor(x: f64) -> f64 {
+        x.floor()
+    }
+
+    pub fn round(x: f64) -> f64 {
+        x.round()
+    }
+}
+
+#[cfg(not(feature = "std"))]
+mod imp {
+    pub use libm::{acos, asin, atan, cbrt, cos, floor, hypot, log10, log2, pow, round, sin, tan};
+}
+
+pub use imp::*;
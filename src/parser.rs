@@ -3,12 +3,94 @@
 //! A parser's job is to take in a stream of [`Token`] and produce an Abstract
 //! Syntax Tree. The AST can be used to generate code or evaluate in the future.
 
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+#[cfg(feature = "dimensions")]
+use crate::lexer;
+#[cfg(feature = "vector")]
+use crate::VectorNode;
 use crate::{
-    eof, BinaryAction, BinaryNode, Lexer, NodeBox, Number, Operator, ParseError, PlainNode,
-    TilError, Token, TokenKind, UnaryAction, UnaryNode,
+    eof, BinaryAction, BinaryNode, Function, FunctionNode, IfNode, LexError, Lexer, NodeBox,
+    Number, Operator, ParseError, PlainNode, Span, TilError, Token, TokenKind, UnaryAction,
+    UnaryNode, VariableNode,
 };
 
-pub type Result<T> = std::result::Result<T, TilError>;
+pub type Result<T> = core::result::Result<T, TilError>;
+
+/// Maximum depth of an AST, which bounds recursion during evaluation, e.g. of
+/// long chains of operators such as `1 + 1 + ...`.
+const MAX_DEPTH: usize = 1000;
+
+/// Maximum nesting of parentheses and arguments, which bounds recursion during
+/// parsing.
+const MAX_NESTING: usize = 64;
+
+/// Nonterminals of the grammar, used to start parsing at a specific rule with
+/// [`Parser::parse_rule`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GrammarRule {
+    /// `expr = and (|| and)*`
+    Expr,
+
+    /// `and = not (&& not)*`
+    And,
+
+    /// `not = (! | not)* cmp`
+    Not,
+
+    /// `cmp = sum ((< | <= | > | >= | == | !=) sum)?`
+    Cmp,
+
+    /// `sum = term ([+-] term)*`
+    Sum,
+
+    /// `term = factor (([*/%] | // | fdiv)? factor)*`
+    Term,
+
+    /// `factor = [+-]* pow`
+    Factor,
+
+    /// `pow = atomic ((^ | xor) atomic)?`, where `^` is exponentiation or
+    /// bitwise exclusive or depending on [`CaretMeaning`].
+    Pow,
+
+    /// `atomic = Int | Flt | Dec | Ident | paren_expr | abs | Func args`
+    Atomic,
+
+    /// `paren_expr = LeftParen expr RightParen`
+    ParenExpr,
+}
+
+/// Meaning of the `^` operator, set with [`Parser::with_caret_meaning`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CaretMeaning {
+    /// Exponentiation, i.e. [`BinaryAction::Pow`].
+    #[default]
+    Power,
+
+    /// Bitwise exclusive or, i.e. [`BinaryAction::Xor`], as in C. It binds as
+    /// tightly as exponentiation would.
+    Xor,
+}
+
+/// Meaning of `/` between two integers, set with
+/// [`Parser::with_int_div_mode`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum IntDivMode {
+    /// Truncating integer division, i.e. [`BinaryAction::Div`], so `7/2` is
+    /// `3`.
+    #[default]
+    Truncate,
+
+    /// Division resulting in a float unless the integers divide evenly, i.e.
+    /// [`BinaryAction::FloatDiv`], so `7/2` is `3.5`.
+    Float,
+}
 
 #[derive(Debug)]
 pub struct Parser {
@@ -17,6 +99,66 @@ pub struct Parser {
 
     /// The current token, allowing look-ahead once.
     current_token: Token,
+
+    /// Whether the first token has been retrieved from the [`Lexer`].
+    started: bool,
+
+    /// Whether unary operators that cancel out (e.g. `+5` or `--5`) are
+    /// dropped instead of producing a [`UnaryNode`] with [`UnaryAction::Iden`].
+    fold_identity: bool,
+
+    /// Whether a number directly following an operand (e.g. `7 6`) is
+    /// multiplied with it instead of ending the expression.
+    implicit_numbers: bool,
+
+    /// Whether a function taking one argument may be applied to the following
+    /// atomic without parentheses, e.g. `sin 0`.
+    bare_functions: bool,
+
+    /// Meaning of the `^` operator.
+    caret: CaretMeaning,
+
+    /// Meaning of `/` between two integers.
+    int_div: IntDivMode,
+
+    /// Names of all variables found so far, indexed by [`VariableNode`]s.
+    variables: Vec<String>,
+
+    /// Names of the only functions and variables allowed, if restricted.
+    allowed: Option<Vec<String>>,
+
+    /// Current nesting of parentheses and arguments.
+    nesting: usize,
+
+    /// Number of currently unclosed left parentheses, including those of
+    /// arguments, for [`ParseError::UnclosedParens`].
+    open_parens: usize,
+
+    /// Span of the last consumed token, i.e. the end of the latest node.
+    previous_span: Span,
+}
+
+/// Iterator over the ASTs of each line of a program, created by
+/// [`Parser::into_statements`]. Nothing is parsed past the first error, which
+/// ends the iteration.
+#[derive(Debug)]
+pub struct Statements {
+    parser: Parser,
+    done: bool,
+}
+
+impl Iterator for Statements {
+    type Item = Result<NodeBox>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.parser.parse_statement().transpose();
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
 }
 
 impl Parser {
@@ -25,22 +167,329 @@ impl Parser {
         Self {
             lexer,
             current_token: eof!(0),
+            started: false,
+            fold_identity: true,
+            implicit_numbers: false,
+            bare_functions: false,
+            caret: CaretMeaning::default(),
+            int_div: IntDivMode::default(),
+            variables: Vec::new(),
+            allowed: None,
+            nesting: 0,
+            open_parens: 0,
+            previous_span: eof!(0).span,
         }
     }
 
-    /// Generates an AST.
+    /// Replaces the [`Lexer`] of this [`Parser`] to parse new input, e.g. the
+    /// next line in a REPL. The state of the previous input is cleared,
+    /// including its [`variables`](Parser::variables), while settings such as
+    /// [`with_identity_folding`](Parser::with_identity_folding) are kept.
+    pub fn feed(&mut self, lexer: Lexer) {
+        self.lexer = lexer;
+        self.current_token = eof!(0);
+        self.started = false;
+        self.variables.clear();
+        self.nesting = 0;
+        self.open_parens = 0;
+        self.previous_span = eof!(0).span;
+    }
+
+    /// Sets whether unary operators that cancel out (e.g. `+5` or `--5`) are
+    /// dropped from the AST. Enabled by default, disable it to keep them as a
+    /// [`UnaryNode`] with [`UnaryAction::Iden`].
+    pub fn with_identity_folding(mut self, fold_identity: bool) -> Parser {
+        self.fold_identity = fold_identity;
+        self
+    }
+
+    /// Sets whether a number directly following an operand is multiplied with
+    /// it, e.g. `7 6` is `42`. Disabled by default, so that a missing operator
+    /// is reported as [`ParseError::TrailingTokens`] by [`Parser::parse`].
+    ///
+    /// Other implicit multiplication, such as `2x` or `2(3)`, is always
+    /// enabled.
+    pub fn with_implicit_numbers(mut self, implicit_numbers: bool) -> Parser {
+        self.implicit_numbers = implicit_numbers;
+        self
+    }
+
+    /// Sets whether a function taking one argument may be applied without
+    /// parentheses, as in `sin 0`. Disabled by default.
+    ///
+    /// The argument is only the following atomic, i.e. a number, a variable,
+    /// a parenthesised expression, an absolute value or another function, so
+    /// the function binds more tightly than any operator: `sin 2 * 3` is
+    /// `sin(2) * 3`, `sin x^2` is `sin(x)^2` and `sin 2x` is `sin(2) * x`. A
+    /// signed argument needs parentheses, e.g. `sin (-1)`.
+    pub fn with_bare_functions(mut self, bare_functions: bool) -> Parser {
+        self.bare_functions = bare_functions;
+        self
+    }
+
+    /// Sets the meaning of the `^` operator, exponentiation by default.
+    pub fn with_caret_meaning(mut self, caret: CaretMeaning) -> Parser {
+        self.caret = caret;
+        self
+    }
+
+    /// Sets whether `/` truncates the quotient of two integers, which it does
+    /// by default, or results in a float, e.g. `7/2` is `3.5`.
+    pub fn with_int_div_mode(mut self, int_div: IntDivMode) -> Parser {
+        self.int_div = int_div;
+        self
+    }
+
+    /// Restricts the functions and variables that may be used to `names`, so
+    /// that anything else is rejected with [`ParseError::DisallowedIdentifier`]
+    /// before evaluation. Functions are matched by their
+    /// [`name`](Function::name), so aliases such as `arcsin` need `asin`.
+    pub fn with_allowed_identifiers<I, T>(mut self, names: I) -> Parser
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.allowed = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Generates an AST, requiring the whole input to be a single expression.
+    ///
+    /// See [`Parser::parse_partial`] to allow trailing tokens.
     pub fn parse(&mut self) -> Result<NodeBox> {
-        self.lex_and_store()?;
+        let node = self.parse_partial()?;
+
+        match self.current_token.kind {
+            TokenKind::Eof => Ok(node),
+
+            // Right parenthesis without a matching left parenthesis.
+            TokenKind::RightParen => Err(self.mismatch_right_paren()),
 
-        self.parse_expr()
+            _ => Err(ParseError::TrailingTokens {
+                span: self.current_token.span,
+            }
+            .into()),
+        }
+    }
+
+    /// Generates one AST per line, where expressions are separated by newlines.
+    /// Newlines inside parentheses do not separate expressions, so a
+    /// parenthesised expression can span multiple lines. Empty lines are
+    /// skipped.
+    pub fn parse_program(&mut self) -> Result<Vec<NodeBox>> {
+        let mut program = Vec::new();
+        while let Some(node) = self.parse_statement()? {
+            program.push(node);
+        }
+
+        Ok(program)
+    }
+
+    /// Turns this [`Parser`] into an iterator of the ASTs of each line, as
+    /// with [`Parser::parse_program`], but parsing one line at a time on
+    /// demand.
+    pub fn into_statements(self) -> Statements {
+        Statements {
+            parser: self,
+            done: false,
+        }
+    }
+
+    /// Generates an AST from the next line of a program, or [`None`] once the
+    /// input is exhausted.
+    fn parse_statement(&mut self) -> Result<Option<NodeBox>> {
+        // Newlines must be significant from the very first token.
+        if !self.started {
+            self.lexer.set_newlines(true);
+        }
+
+        self.parse_rule_start()?;
+
+        // Skip empty lines.
+        while self.current_token.kind == TokenKind::Newline {
+            self.lex_and_store()?;
+        }
+
+        if self.current_token.kind == TokenKind::Eof {
+            return Ok(None);
+        }
+
+        let node = self.parse_expr()?;
+
+        // Expect the end of the line.
+        match self.current_token.kind {
+            TokenKind::Newline | TokenKind::Eof => Ok(Some(node)),
+            TokenKind::RightParen => Err(self.mismatch_right_paren()),
+            _ => Err(ParseError::TrailingTokens {
+                span: self.current_token.span,
+            }
+            .into()),
+        }
+    }
+
+    /// Generates an AST from the longest expression at the start of the
+    /// remaining input. Parsing stops at the first token that cannot extend the
+    /// expression, which is left unconsumed and can be retrieved with
+    /// [`Parser::peek`].
+    pub fn parse_partial(&mut self) -> Result<NodeBox> {
+        self.parse_rule(GrammarRule::Expr)
+    }
+
+    /// Generates an AST from the longest match of a specific [`GrammarRule`]
+    /// at the start of the remaining input. As with [`Parser::parse_partial`],
+    /// the rest of the input is left unconsumed.
+    pub fn parse_rule(&mut self, rule: GrammarRule) -> Result<NodeBox> {
+        self.parse_rule_start()?;
+
+        match rule {
+            GrammarRule::Expr => self.parse_expr(),
+            GrammarRule::And => self.parse_and(),
+            GrammarRule::Not => self.parse_not(),
+            GrammarRule::Cmp => self.parse_cmp(),
+            GrammarRule::Sum => self.parse_sum(),
+            GrammarRule::Term => self.parse_term(),
+            GrammarRule::Factor => self.parse_factor(),
+            GrammarRule::Pow => self.parse_pow(),
+            GrammarRule::Atomic => self.parse_atomic(),
+            GrammarRule::ParenExpr => {
+                // Unlike other rules, this cannot start with any token.
+                if self.current_token.kind != TokenKind::LeftParen {
+                    return Err(ParseError::LeftParenExpected(self.current_token).into());
+                }
+
+                self.parse_paren_expr()
+            }
+        }
+    }
+
+    /// Returns the current token, i.e. the first token that has not been
+    /// consumed by the parser.
+    pub fn peek(&self) -> Token {
+        self.current_token
+    }
+
+    /// Returns the names of all variables found so far, in the order of their
+    /// indices. Values passed to [`Node::evaluate_indexed`](crate::ast::Node::evaluate_indexed)
+    /// must follow the same order.
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    /// Returns the unrecognised characters skipped by the [`Lexer`] so far,
+    /// see [`UnknownPolicy::Skip`](crate::UnknownPolicy::Skip).
+    pub fn warnings(&self) -> &[LexError] {
+        self.lexer.warnings()
     }
 
     /// Production:
     /// ```text
-    /// expr = term ([+-] term)*
+    /// expr = and (|| and)*
     /// ```
     fn parse_expr(&mut self) -> Result<NodeBox> {
+        self.parse_logic(Operator::Or, BinaryAction::Or, Self::parse_and)
+    }
+
+    /// Production:
+    /// ```text
+    /// and = not (&& not)*
+    /// ```
+    fn parse_and(&mut self) -> Result<NodeBox> {
+        self.parse_logic(Operator::And, BinaryAction::And, Self::parse_not)
+    }
+
+    /// Parses a chain of operands separated by the same logical operator.
+    fn parse_logic(
+        &mut self,
+        operator: Operator,
+        actor: BinaryAction,
+        parse_operand: fn(&mut Self) -> Result<NodeBox>,
+    ) -> Result<NodeBox> {
+        // Get the first operand.
+        let first = self.current_token.span;
+        let mut operand = parse_operand(self)?;
+
+        // Loop to get all operands.
+        while self.current_token.kind == TokenKind::Op(operator) {
+            // Next.
+            let operator = self.current_token;
+            self.lex_and_store()?;
+            self.expect_operand(operator)?;
+
+            // Get the next operand.
+            let next_operand = parse_operand(self)?;
+
+            // Create a new node.
+            let node = BinaryNode::new(operand, actor, next_operand);
+            operand = self.check_depth(self.spanned(Box::new(node), first))?;
+        }
+
+        Ok(operand)
+    }
+
+    /// Production:
+    /// ```text
+    /// not = (! | not)* cmp
+    /// ```
+    fn parse_not(&mut self) -> Result<NodeBox> {
+        // Collect negations, without recursion.
+        let mut negations = Vec::new();
+        while self.current_token.kind == TokenKind::Op(Operator::Not) {
+            let operator = self.current_token;
+            self.lex_and_store()?;
+            self.expect_operand(operator)?;
+            negations.push(operator.span);
+        }
+
+        // Parse comparison, then apply all negations from the innermost.
+        let mut operand = self.parse_cmp()?;
+        for first in negations.into_iter().rev() {
+            let node = UnaryNode::new(UnaryAction::Not, operand);
+            operand = self.check_depth(self.spanned(Box::new(node), first))?;
+        }
+
+        Ok(operand)
+    }
+
+    /// Production:
+    /// ```text
+    /// cmp = sum ((< | <= | > | >= | == | !=) sum)?
+    /// ```
+    fn parse_cmp(&mut self) -> Result<NodeBox> {
+        // Get the first sum.
+        let first = self.current_token.span;
+        let sum = self.parse_sum()?;
+
+        // Get the comparison, if any.
+        let actor = match self.current_token.kind {
+            TokenKind::Op(Operator::Less) => BinaryAction::Less,
+            TokenKind::Op(Operator::LessEqual) => BinaryAction::LessEqual,
+            TokenKind::Op(Operator::Greater) => BinaryAction::Greater,
+            TokenKind::Op(Operator::GreaterEqual) => BinaryAction::GreaterEqual,
+            TokenKind::Op(Operator::Equal) => BinaryAction::Equal,
+            TokenKind::Op(Operator::NotEqual) => BinaryAction::NotEqual,
+            _ => return Ok(sum),
+        };
+
+        // Next.
+        let operator = self.current_token;
+        self.lex_and_store()?;
+        self.expect_operand(operator)?;
+
+        // Get the second sum, comparisons cannot be chained.
+        let next_sum = self.parse_sum()?;
+
+        // Create a new node.
+        let node = BinaryNode::new(sum, actor, next_sum);
+        self.check_depth(self.spanned(Box::new(node), first))
+    }
+
+    /// Production:
+    /// ```text
+    /// sum = term ([+-] term)*
+    /// ```
+    fn parse_sum(&mut self) -> Result<NodeBox> {
         // Get the first term.
+        let first = self.current_token.span;
         let mut term = self.parse_term()?;
 
         // Loop to get all terms.
@@ -59,22 +508,26 @@ impl Parser {
             };
 
             // Next.
+            let operator = self.current_token;
             self.lex_and_store()?;
+            self.expect_operand(operator)?;
 
             // Get the next term.
             let next_term = self.parse_term()?;
 
             // Create a new node.
-            term = Box::new(BinaryNode::new(term, actor, next_term));
+            let node = BinaryNode::new(term, actor, next_term);
+            term = self.check_depth(self.spanned(Box::new(node), first))?;
         }
     }
 
     /// Production:
     /// ```text
-    /// term = factor ([*/] factor)*
+    /// term = factor (([*/%] | // | fdiv)? factor)*
     /// ```
     fn parse_term(&mut self) -> Result<NodeBox> {
         // Get the first factor.
+        let first = self.current_token.span;
         let mut factor = self.parse_factor()?;
 
         // Loop to get all factors.
@@ -84,12 +537,21 @@ impl Parser {
                 TokenKind::Op(op) => {
                     let a = match op {
                         Operator::Star => BinaryAction::Mul,
-                        Operator::Slash => BinaryAction::Div,
+                        Operator::Slash => match self.int_div {
+                            IntDivMode::Truncate => BinaryAction::Div,
+                            IntDivMode::Float => BinaryAction::FloatDiv,
+                        },
+                        Operator::FDiv => BinaryAction::FloatDiv,
+                        Operator::DoubleSlash => BinaryAction::FloorDiv,
+                        Operator::Percent => BinaryAction::Mod,
                         _ => return Ok(factor),
                     };
 
                     // Consume operator.
+                    let operator = self.current_token;
                     self.lex_and_store()?;
+                    self.expect_operand(operator)?;
+                    self.check_doubled(operator)?;
 
                     a
                 }
@@ -99,6 +561,12 @@ impl Parser {
                 TokenKind::LeftParen => BinaryAction::Mul,
                 // 2. Function.
                 TokenKind::Func(_) => BinaryAction::Mul,
+                // 3. Variable.
+                TokenKind::Ident => BinaryAction::Mul,
+                // 4. Number, if enabled.
+                TokenKind::Int(_) | TokenKind::Flt(_) if self.implicit_numbers => BinaryAction::Mul,
+                #[cfg(feature = "decimal")]
+                TokenKind::Dec(_) if self.implicit_numbers => BinaryAction::Mul,
 
                 _ => return Ok(factor),
             };
@@ -107,7 +575,8 @@ impl Parser {
             let next_factor = self.parse_factor()?;
 
             // Create a new node.
-            factor = Box::new(BinaryNode::new(factor, actor, next_factor));
+            let node = BinaryNode::new(factor, actor, next_factor);
+            factor = self.check_depth(self.spanned(Box::new(node), first))?;
         }
     }
 
@@ -117,7 +586,9 @@ impl Parser {
     /// ```
     fn parse_factor(&mut self) -> Result<NodeBox> {
         // Check for unary operator(s).
+        let first = self.current_token.span;
         let mut actor = UnaryAction::Iden;
+        let mut signed = false;
         loop {
             match self.current_token.kind {
                 TokenKind::Op(c) => match c {
@@ -141,52 +612,90 @@ impl Parser {
             };
 
             // Consume operator.
+            signed = true;
             self.lex_and_store()?;
         }
 
         // Parse atomic.
         let operand = self.parse_pow()?;
 
-        if actor == UnaryAction::Iden {
+        if !signed || (actor == UnaryAction::Iden && self.fold_identity) {
             Ok(operand)
         } else {
-            Ok(Box::new(UnaryNode::new(actor, operand)))
+            let node = UnaryNode::new(actor, operand);
+            self.check_depth(self.spanned(Box::new(node), first))
         }
     }
 
     /// Production:
     /// ```text
-    /// pow = atomic (^ atomic)?
+    /// pow = atomic ((^ | xor) atomic)?
     /// ```
     fn parse_pow(&mut self) -> Result<NodeBox> {
         // Parse base.
+        let first = self.current_token.span;
         let base = self.parse_atomic()?;
 
-        // Check for exponentiation.
-        if self.current_token.kind != TokenKind::Op(Operator::Caret) {
-            return Ok(base);
-        }
+        // Check for exponentiation or exclusive or.
+        let actor = match (self.current_token.kind, self.caret) {
+            (TokenKind::Op(Operator::Caret), CaretMeaning::Power) => BinaryAction::Pow,
+            (TokenKind::Op(Operator::Caret), CaretMeaning::Xor) => BinaryAction::Xor,
+            (TokenKind::Op(Operator::Xor), _) => BinaryAction::Xor,
+            _ => return Ok(base),
+        };
 
         // Consume operator.
+        let operator = self.current_token;
         self.lex_and_store()?;
+        self.expect_operand(operator)?;
 
         // Parse exponent.
         let exponent = self.parse_atomic()?;
 
         // Create a new node.
-        Ok(Box::new(BinaryNode::new(base, BinaryAction::Pow, exponent)))
+        let node = BinaryNode::new(base, actor, exponent);
+        self.check_depth(self.spanned(Box::new(node), first))
     }
 
     /// Production:
     /// ```text
-    /// atomic = Int | Flt | paren_expr | Func paren_expr
+    /// atomic = Int | Flt | Dec | Ident | paren_expr | abs | Func args
     /// ```
     fn parse_atomic(&mut self) -> Result<NodeBox> {
         // Match the next token.
+        let first = self.current_token.span;
         let node: NodeBox = match self.current_token.kind {
             // Numbers (parse_numbers is merged here).
-            TokenKind::Flt(f) => Box::new(PlainNode::new(Number::Flt(f))),
+            TokenKind::Flt(f) => {
+                let node = PlainNode::new(Number::Flt(f));
+
+                // Physical constants keep their dimensions.
+                #[cfg(feature = "dimensions")]
+                let node = match lexer::dimension(&self.lexer[self.current_token.span]) {
+                    Some(dimension) => node.with_dimension(dimension),
+                    None => node,
+                };
+
+                Box::new(node)
+            }
             TokenKind::Int(i) => Box::new(PlainNode::new(Number::Int(i as i128))),
+            #[cfg(feature = "decimal")]
+            TokenKind::Dec(d) => Box::new(PlainNode::new(Number::Dec(d))),
+
+            // Variables, the same name always gets the same index.
+            TokenKind::Ident => {
+                let name = &self.lexer[self.current_token.span];
+                self.check_allowed(name)?;
+                let index = match self.variables.iter().position(|v| v == name) {
+                    Some(index) => index,
+                    None => {
+                        self.variables.push(name.to_string());
+                        self.variables.len() - 1
+                    }
+                };
+
+                Box::new(VariableNode::new(name.to_string(), index))
+            }
 
             // Parenthesised expressions.
             // Return immediately to avoid consumption of current token.
@@ -194,16 +703,56 @@ impl Parser {
 
             // Functions.
             TokenKind::Func(func) => {
+                self.check_allowed(func.name())?;
+
                 // Consume function.
                 self.lex_and_store()?;
 
-                // Parse parenthesised expression.
-                let expr = self.parse_paren_expr()?;
+                // Parse arguments, or a bare argument if enabled.
+                let mut args = match self.current_token.kind {
+                    TokenKind::LeftParen => self.parse_args()?,
+                    _ if self.bare_functions && func.arity() == 1 => {
+                        vec![self.parse_bare_argument()?]
+                    }
+                    _ => self.parse_args()?,
+                };
+                if args.len() != func.arity() {
+                    return Err(ParseError::WrongArity {
+                        func,
+                        expected: func.arity(),
+                        got: args.len(),
+                    }
+                    .into());
+                }
+
+                // Conditionals only evaluate one operand, so they have their
+                // own node.
+                if func == Function::If {
+                    let [cond, then, otherwise] = <[NodeBox; 3]>::try_from(args)
+                        .map_err(|_| ParseError::InternalError("Unexpected number of arguments"))?;
+                    let node = IfNode::new(cond, then, otherwise);
+                    return self.check_depth(self.spanned(Box::new(node), first));
+                }
 
-                // Create a new node.
-                Box::new(UnaryNode::new(UnaryAction::Func(func), expr))
+                // Create a new node, functions taking one operand are unary.
+                // Return immediately as the right parenthesis was consumed.
+                if args.len() == 1 {
+                    let node = UnaryNode::new(UnaryAction::Func(func), args.remove(0));
+                    return self.check_depth(self.spanned(Box::new(node), first));
+                }
+
+                let node = FunctionNode::new(func, args);
+                return self.check_depth(self.spanned(Box::new(node), first));
             }
 
+            // Absolute values, returned immediately as the closing bar is
+            // consumed.
+            TokenKind::Bar => return self.parse_abs(),
+
+            // Vectors, returned immediately as the right bracket is consumed.
+            #[cfg(feature = "vector")]
+            TokenKind::LeftBracket => return self.parse_vector(),
+
             // Invalid unary operators, valid ones were handled up top.
             TokenKind::Op(_) => {
                 return Err(ParseError::InvalidUnaryOperator(self.current_token).into())
@@ -212,19 +761,19 @@ impl Parser {
             // Catch all EOF.
             TokenKind::Eof => return Err(ParseError::UnexpectedEOF.into()),
 
-            // Invalid:
-            // RightParen: Unmatched left parenthesis.
-            _ => {
-                return Err(
-                    ParseError::MismatchRightParen(self.current_token.span.start_index).into(),
-                )
+            // Right parentheses without a matching left one.
+            TokenKind::RightParen if self.open_parens == 0 => {
+                return Err(self.mismatch_right_paren())
             }
+
+            // Any other token cannot start an operand.
+            _ => return Err(ParseError::OperandExpected(self.current_token).into()),
         };
 
         // Consume token.
         self.lex_and_store()?;
 
-        Ok(node)
+        Ok(self.spanned(node, first))
     }
 
     /// Production:
@@ -238,25 +787,285 @@ impl Parser {
         }
 
         // Consume left parenthesis.
+        let first = self.current_token.span;
         self.lex_and_store()?;
 
         // Parse expression.
         // Errors need to be return immediately as the lexer might be in an
         // unusable state.
-        let expr = self.parse_expr()?;
+        self.open_parens += 1;
+        let expr = self.parse_nested_expr()?;
 
         // Expect a right parenthesis.
-        if self.current_token.kind != TokenKind::RightParen {
-            return Err(ParseError::RightParenExpected(self.current_token).into());
+        self.expect_right_paren()?;
+
+        // Consume right parenthesis.
+        self.lex_and_store()?;
+
+        // The parentheses are part of the expression.
+        Ok(self.spanned(expr, first))
+    }
+
+    /// Production:
+    /// ```text
+    /// abs = Bar expr Bar
+    /// ```
+    ///
+    /// Bars do not nest directly: the first bar after the opening one closes
+    /// the absolute value, so `|a| + |b|` is the sum of two absolute values.
+    /// Nested absolute values need another opening bar inside an operand, e.g.
+    /// `|a - |b| |`, or parentheses, e.g. `|(|a|)|`, as `||` is always the
+    /// logical or.
+    fn parse_abs(&mut self) -> Result<NodeBox> {
+        // Consume opening bar, which was checked by the caller.
+        let first = self.current_token.span;
+        self.lex_and_store()?;
+
+        // Parse expression, which stops at the closing bar.
+        let expr = self.parse_nested_expr()?;
+
+        // Expect a closing bar.
+        if self.current_token.kind != TokenKind::Bar {
+            return Err(ParseError::BarExpected(self.current_token).into());
         };
 
+        // Consume closing bar.
+        self.lex_and_store()?;
+
+        let node = UnaryNode::new(UnaryAction::Abs, expr);
+        self.check_depth(self.spanned(Box::new(node), first))
+    }
+
+    /// Production:
+    /// ```text
+    /// args = LeftParen (expr (Comma expr)* Comma?)? RightParen
+    /// ```
+    fn parse_args(&mut self) -> Result<Vec<NodeBox>> {
+        // Expect a left parenthesis.
+        if self.current_token.kind != TokenKind::LeftParen {
+            return Err(ParseError::LeftParenExpected(self.current_token).into());
+        }
+
+        // Consume left parenthesis.
+        self.lex_and_store()?;
+
+        // Parse arguments (if any).
+        self.open_parens += 1;
+        let args = self.parse_list(TokenKind::RightParen)?;
+
+        // Expect a right parenthesis.
+        self.expect_right_paren()?;
+
         // Consume right parenthesis.
         self.lex_and_store()?;
 
-        Ok(expr)
+        Ok(args)
+    }
+
+    /// Production:
+    /// ```text
+    /// vector = LeftBracket (expr (Comma expr)* Comma?)? RightBracket
+    /// ```
+    #[cfg(feature = "vector")]
+    fn parse_vector(&mut self) -> Result<NodeBox> {
+        // Consume left bracket, which was checked by the caller.
+        let first = self.current_token.span;
+        self.lex_and_store()?;
+
+        // Parse elements (if any).
+        let elements = self.parse_list(TokenKind::RightBracket)?;
+
+        // Expect a right bracket.
+        if self.current_token.kind != TokenKind::RightBracket {
+            return Err(ParseError::RightBracketExpected(self.current_token).into());
+        };
+
+        // Consume right bracket.
+        self.lex_and_store()?;
+
+        let node = VectorNode::new(elements);
+        self.check_depth(self.spanned(Box::new(node), first))
+    }
+
+    /// Checks that the function or variable `name` at the current token is
+    /// allowed by [`with_allowed_identifiers`](Parser::with_allowed_identifiers).
+    fn check_allowed(&self, name: &str) -> Result<()> {
+        match &self.allowed {
+            Some(allowed) if !allowed.iter().any(|a| a == name) => {
+                Err(ParseError::DisallowedIdentifier {
+                    name: name.to_string(),
+                    span: self.current_token.span,
+                }
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Parses expressions separated by commas, up to (but excluding) `end`.
+    /// A trailing comma is allowed, but empty expressions are not.
+    fn parse_list(&mut self, end: TokenKind) -> Result<Vec<NodeBox>> {
+        let mut list = Vec::new();
+        if self.current_token.kind == end {
+            return Ok(list);
+        }
+
+        loop {
+            // Empty expressions, as in `(,)` or `(1,,2)`.
+            if self.current_token.kind == TokenKind::Comma {
+                return Err(ParseError::MissingArgument {
+                    comma_span: self.current_token.span,
+                }
+                .into());
+            }
+
+            list.push(self.parse_nested_expr()?);
+
+            // Elements are separated by commas, as in `hypot(3, 4)` rather than
+            // `hypot(3 4)`. An unclosed list is reported by the caller.
+            match self.current_token.kind {
+                TokenKind::Comma => (),
+                kind if kind == end || kind == TokenKind::Eof => return Ok(list),
+                _ => return Err(ParseError::CommaExpected(self.current_token).into()),
+            }
+
+            // Consume comma, which may be the last one.
+            self.lex_and_store()?;
+            if self.current_token.kind == end {
+                return Ok(list);
+            }
+        }
+    }
+
+    /// Retrieves the first token, if that has not been done yet.
+    fn parse_rule_start(&mut self) -> Result<()> {
+        if !self.started {
+            self.started = true;
+            self.lex_and_store()?;
+        }
+
+        Ok(())
+    }
+
+    /// Parses an expression inside parentheses, keeping track of the nesting.
+    fn parse_nested_expr(&mut self) -> Result<NodeBox> {
+        if self.nesting >= MAX_NESTING {
+            return Err(ParseError::TooDeep(self.current_token.span.start_index).into());
+        }
+
+        self.nesting += 1;
+        let expr = self.parse_expr();
+        self.nesting -= 1;
+
+        expr
+    }
+
+    /// Checks that the current token closes the innermost open parenthesis.
+    /// The end of the input reports how many parentheses are left unclosed.
+    fn expect_right_paren(&mut self) -> Result<()> {
+        match self.current_token.kind {
+            TokenKind::RightParen => {
+                self.open_parens -= 1;
+                Ok(())
+            }
+            TokenKind::Eof => Err(ParseError::UnclosedParens {
+                open: self.open_parens,
+                span: self.current_token.span,
+            }
+            .into()),
+            _ => Err(ParseError::RightParenExpected(self.current_token).into()),
+        }
+    }
+
+    /// Creates a [`ParseError::MismatchRightParen`] for the current token, or
+    /// a [`ParseError::ExtraRightParens`] if right parentheses on the rest of
+    /// the line are unmatched too.
+    fn mismatch_right_paren(&self) -> TilError {
+        let mut lexer = self.lexer.clone();
+        let (mut extra, mut depth) = (1, 0);
+        while let Ok(token) = lexer.lex() {
+            match token.kind {
+                TokenKind::LeftParen => depth += 1,
+                TokenKind::RightParen if depth == 0 => extra += 1,
+                TokenKind::RightParen => depth -= 1,
+                TokenKind::Newline | TokenKind::Eof => break,
+                _ => (),
+            }
+        }
+
+        let index = self.current_token.span.start_index;
+        match extra {
+            1 => ParseError::MismatchRightParen(index).into(),
+            count => ParseError::ExtraRightParens { index, count }.into(),
+        }
+    }
+
+    /// Parses the atomic argument of a function applied without parentheses,
+    /// which counts as nesting like a parenthesised argument.
+    fn parse_bare_argument(&mut self) -> Result<NodeBox> {
+        if self.nesting >= MAX_NESTING {
+            return Err(ParseError::TooDeep(self.current_token.span.start_index).into());
+        }
+
+        self.nesting += 1;
+        let argument = self.parse_atomic();
+        self.nesting -= 1;
+
+        argument
+    }
+
+    /// Checks that a newly created node is not too deep to be evaluated.
+    fn check_depth(&self, node: NodeBox) -> Result<NodeBox> {
+        if node.depth() > MAX_DEPTH {
+            return Err(ParseError::TooDeep(self.current_token.span.start_index).into());
+        }
+
+        Ok(node)
+    }
+
+    /// Sets the span of a newly created node, from the first token at `first`
+    /// up to the last consumed token.
+    fn spanned(&self, mut node: NodeBox, first: Span) -> NodeBox {
+        node.set_span(Span::merge(first, self.previous_span));
+        node
+    }
+
+    /// Checks that a `*`, which has just been consumed, is not directly
+    /// followed by another one, as in `2 ** 3`.
+    fn check_doubled(&self, operator: Token) -> Result<()> {
+        if operator.kind != TokenKind::Op(Operator::Star)
+            || self.current_token.kind != operator.kind
+            || self.current_token.span.start_index != operator.span.end_index + 1
+        {
+            return Ok(());
+        }
+
+        Err(ParseError::DidYouMean {
+            found: "**",
+            suggestion: "^",
+            span: Span {
+                start_index: operator.span.start_index,
+                end_index: self.current_token.span.end_index,
+            },
+        }
+        .into())
+    }
+
+    /// Checks that a binary operator, which has just been consumed, is followed
+    /// by something other than the end of the input or line.
+    fn expect_operand(&self, operator: Token) -> Result<()> {
+        if matches!(self.current_token.kind, TokenKind::Eof | TokenKind::Newline) {
+            return Err(ParseError::MissingOperand {
+                operator_span: operator.span,
+            }
+            .into());
+        }
+
+        Ok(())
     }
 
     fn lex_and_store(&mut self) -> Result<Token> {
+        self.previous_span = self.current_token.span;
         let token = self.lexer.lex()?;
         self.current_token = token;
         Ok(token)
@@ -0,0 +1,185 @@
+//! This module implements sessions for [`tilted`](crate).
+//!
+//! A session runs statements one after another, remembering the variables
+//! defined by earlier statements, e.g. `a = 2` followed by `a + 1`, as well
+//! as their results, e.g. `hist(1)` for the result of the first statement.
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::Node, EvalError, Lexer, NodeBox, Number, Parser, PlainNode, TilError, TokenKind, Variables,
+};
+
+pub type Result<T> = std::result::Result<T, TilError>;
+
+/// [`Session`] keeps track of variable definitions and results across
+/// statements.
+#[derive(Debug, Default)]
+pub struct Session {
+    /// Definitions of all variables, by name.
+    definitions: HashMap<String, Definition>,
+
+    /// Results of all statements run so far, in order.
+    history: Vec<Number>,
+
+    /// Whether definitions that only refer to constants are replaced by their
+    /// values when they are defined.
+    fold_constants: bool,
+}
+
+/// A variable definition stored in a [`Session`].
+#[derive(Debug)]
+struct Definition {
+    /// The defining expression.
+    node: NodeBox,
+
+    /// Names of the variables the defining expression refers to.
+    variables: Vec<String>,
+
+    /// Whether the defining expression only (indirectly) refers to constants.
+    constant: bool,
+
+    /// Whether the variable was declared with `let`, and so cannot be
+    /// redefined.
+    declared: bool,
+}
+
+impl Session {
+    /// Creates a new [`Session`] without any variables.
+    pub fn new() -> Session {
+        Self::default()
+    }
+
+    /// Sets whether definitions that only refer to constants are stored as
+    /// their values, e.g. `b = a + 3` is stored as `b = 5` if `a = 2`.
+    /// Definitions that refer to unbound variables always stay symbolic.
+    pub fn with_constant_folding(mut self, fold_constants: bool) -> Session {
+        self.fold_constants = fold_constants;
+        self
+    }
+
+    /// Runs a statement, which is either an expression, a definition of the
+    /// form `name = expr` or a constant declaration of the form
+    /// `let name = expr`, and returns its value. Functions applied outside
+    /// their domain result in an error, see [`Node::try_evaluate_with`].
+    ///
+    /// Definitions are evaluated whenever the variable is used, so a
+    /// definition may refer to variables that are defined later. Constant
+    /// declarations may only refer to constants, and are replaced by their
+    /// values immediately, e.g. `let r = 2` followed by `3.14159 * r^2`.
+    /// Declared constants cannot be redefined by later statements, while
+    /// other variables may be redefined in terms of their previous constant
+    /// values, e.g. `x = x + 1`.
+    pub fn run(&mut self, source: &str) -> Result<Number> {
+        let mut lexer = Lexer::from_source_code(source);
+
+        // Check for a declaration or a definition, otherwise start over.
+        let mut first = lexer.lex()?;
+        let mut declared = false;
+        let name = if first.kind == TokenKind::Ident {
+            let mut second = lexer.lex()?;
+            if &lexer[first.span] == "let" && second.kind == TokenKind::Ident {
+                (first, second, declared) = (second, lexer.lex()?, true);
+            }
+
+            (second.kind == TokenKind::Assign).then(|| lexer[first.span].to_string())
+        } else {
+            None
+        };
+        if name.is_none() {
+            lexer.reset();
+            declared = false;
+        }
+
+        if let Some(name) = &name {
+            if self.definitions.get(name).is_some_and(|d| d.declared) {
+                return Err(EvalError::ConstantRedefined(name.clone()).into());
+            }
+        }
+
+        // Parse the (defining) expression.
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse()?;
+        let variables = parser.variables().to_vec();
+
+        let constant = variables
+            .iter()
+            .all(|v| self.definitions.get(v).is_some_and(|d| d.constant));
+        if let (Some(name), true, false) = (&name, declared, constant) {
+            return Err(EvalError::NonConstantDeclaration(name.clone()).into());
+        }
+
+        let value = node.try_evaluate_with(self)?;
+        let name = match name {
+            Some(name) => name,
+            None => {
+                self.history.push(value.clone());
+                return Ok(value);
+            }
+        };
+
+        // Constant definitions referring to the variable itself, e.g. `x = x + 1`,
+        // refer to its previous value, so they are always replaced by their values.
+        let definition =
+            if constant && (declared || self.fold_constants || self.refers_to(&variables, &name)) {
+                Definition {
+                    node: Box::new(PlainNode::new(value.clone())),
+                    variables: Vec::new(),
+                    constant,
+                    declared,
+                }
+            } else {
+                // Symbolic definitions must not lead back to themselves.
+                if self.refers_to(&variables, &name) {
+                    return Err(EvalError::CyclicDefinition(name).into());
+                }
+
+                Definition {
+                    node,
+                    variables,
+                    constant,
+                    declared,
+                }
+            };
+
+        self.definitions.insert(name, definition);
+        self.history.push(value.clone());
+
+        Ok(value)
+    }
+
+    /// Returns the stored definition of a variable, if there is one.
+    pub fn definition(&self, name: &str) -> Option<&dyn Node> {
+        self.definitions.get(name).map(|d| d.node.as_ref())
+    }
+
+    /// Returns the results of all successful statements, in the order they
+    /// were run. `hist(n)` refers to `history()[n - 1]`.
+    pub fn history(&self) -> &[Number] {
+        &self.history
+    }
+
+    /// Checks if any of the variables is, or is defined in terms of, `name`.
+    fn refers_to(&self, variables: &[String], name: &str) -> bool {
+        variables.iter().any(|v| {
+            v == name
+                || self
+                    .definitions
+                    .get(v)
+                    .is_some_and(|d| self.refers_to(&d.variables, name))
+        })
+    }
+}
+
+/// Variables are looked up by their names and evaluated on demand.
+impl Variables for Session {
+    fn get(&self, name: &str, _index: usize) -> Option<Number> {
+        self.definitions
+            .get(name)
+            .map(|d| d.node.evaluate_with(self))
+    }
+
+    fn recall(&self, n: usize) -> Option<Number> {
+        self.history.get(n.checked_sub(1)?).cloned()
+    }
+}
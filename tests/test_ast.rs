@@ -0,0 +1,273 @@
+use std::cmp::Ordering;
+
+use tilted::{
+    ast::Node,
+    expr::{self, Expr, ExprBuilder},
+    BinaryAction, BinaryNode, Function, Lexer, NodeBox, Number, ParseError, Parser, PlainNode,
+    TilError, UnaryAction, UnaryNode,
+};
+
+#[test]
+fn test_ast_plain_node_from() {
+    let int = PlainNode::from(5);
+    let flt = PlainNode::from(2.5);
+
+    assert_eq!(int, PlainNode::new(Number::Int(5)));
+    assert_eq!(flt, PlainNode::new(Number::Flt(2.5)));
+    assert_eq!(
+        PlainNode::from(Number::from(7)),
+        PlainNode::new(Number::Int(7))
+    );
+}
+
+#[test]
+fn test_ast_plain_node_default() {
+    let node = PlainNode::default();
+
+    assert_eq!(node, PlainNode::new(Number::zero()));
+    assert!(matches!(node.evaluate(), Number::Int(0)));
+    assert!(node.span().is_none());
+}
+
+#[test]
+fn test_ast_number_ops() {
+    let a = Number::from(6);
+    let b = Number::from(3);
+
+    assert_eq!(&a + &b, Number::from(9));
+    assert_eq!(&a - &b, Number::from(3));
+    assert_eq!(&a * &b, Number::from(18));
+    assert_eq!(&a / &b, Number::from(2));
+    assert_eq!(a, Number::from(6));
+}
+
+#[test]
+fn test_ast_number_assign_ops() {
+    let mut sum = Number::from(0);
+    for i in 1..=4 {
+        sum += Number::from(i);
+    }
+    assert_eq!(sum, Number::from(10));
+
+    let mut n = Number::from(10);
+    n -= &Number::from(4);
+    n *= Number::from(2.5);
+    n /= Number::from(3);
+    assert_eq!(n, Number::from(5.0));
+
+    // Integers that overflow still fall back to float.
+    let mut big = Number::from(i64::MAX);
+    big *= Number::from(i64::MAX);
+    big *= Number::from(4);
+    assert!(big > Number::from(i64::MAX));
+}
+
+#[test]
+fn test_ast_node_box_from() {
+    let int: NodeBox = 5.into();
+    let flt: NodeBox = 2.5.into();
+
+    assert_eq!(int.evaluate(), Number::from(5));
+    assert_eq!(flt.evaluate(), Number::from(2.5));
+
+    let node = BinaryNode::new(int, BinaryAction::Mul, flt);
+    assert_eq!(node.to_infix(), "5*2.5");
+}
+
+#[test]
+fn test_ast_builder() {
+    let node = expr::num(3).add(expr::num(4)).mul(expr::num(2));
+    assert_eq!(node.evaluate(), Number::from(14));
+    assert_eq!(node.to_infix(), "(3 + 4)*2");
+
+    let node = expr::num(3).add(4).mul(2.5).neg();
+    assert_eq!(node.evaluate(), Number::from(-17.5));
+    assert_eq!(node.to_infix(), "-((3 + 4)*2.5)");
+}
+
+#[test]
+fn test_ast_builder_functions() {
+    let node = expr::num(8).apply(Function::Cbrt).pow(3).sub(1).div(7);
+    assert_eq!(node.evaluate(), Number::from(1.0));
+    assert_eq!(node.to_infix(), "(cbrt(8)^3 - 1)/7");
+
+    let node = expr::call(Function::Hypot, vec![expr::num(3), expr::num(4)]);
+    assert_eq!(node.evaluate(), Number::from(5.0));
+
+    let node = expr::call(
+        Function::If,
+        vec![
+            expr::var("x", 0).binary(BinaryAction::Less, 0),
+            expr::var("x", 0).neg(),
+            expr::var("x", 0),
+        ],
+    );
+    assert_eq!(node.evaluate_indexed(&[Number::from(-2)]), Number::from(2));
+    assert_eq!(node.to_infix(), "if(x < 0, -x, x)");
+}
+
+#[test]
+fn test_ast_expr_from_str() {
+    let expr: Expr = "(3 + 4) * 2".parse().unwrap();
+    assert_eq!(expr.evaluate(), Number::from(14));
+    assert_eq!(expr.to_infix(), "(3 + 4)*2");
+
+    let node: NodeBox = expr.into();
+    assert_eq!(node.evaluate(), Number::from(14));
+}
+
+#[test]
+fn test_ast_expr_from_str_invalid() {
+    assert!(matches!(
+        "1 +".parse::<Expr>(),
+        Err(TilError::Parse(ParseError::MissingOperand { .. }))
+    ));
+    assert!(matches!("1 $ 2".parse::<Expr>(), Err(TilError::Lex(_))));
+}
+
+#[test]
+fn test_ast_accessors() {
+    let lexer = Lexer::from_source_code("1+2");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+
+    let binary = node.as_any().downcast_ref::<BinaryNode>().unwrap();
+    assert_eq!(binary.actor(), BinaryAction::Add);
+    assert_eq!(binary.left().as_number(), Some(&Number::from(1)));
+    assert_eq!(binary.right().as_number(), Some(&Number::from(2)));
+    assert!(node.as_any().downcast_ref::<UnaryNode>().is_none());
+
+    let lexer = Lexer::from_source_code("-x");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+
+    let unary = node.as_any().downcast_ref::<UnaryNode>().unwrap();
+    assert_eq!(unary.actor(), UnaryAction::Neg);
+    assert_eq!(unary.operand().to_infix(), "x");
+    assert!(unary
+        .operand()
+        .as_any()
+        .downcast_ref::<BinaryNode>()
+        .is_none());
+
+    assert_eq!(PlainNode::from(5).value(), &Number::from(5));
+}
+
+#[test]
+fn test_ast_downcast() {
+    let lexer = Lexer::from_source_code("1+2");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+
+    let binary = node.as_any().downcast_ref::<BinaryNode>().unwrap();
+    assert_eq!(binary.actor(), BinaryAction::Add);
+    assert!(node.as_any().downcast_ref::<PlainNode>().is_none());
+
+    let left = binary.left().as_any().downcast_ref::<PlainNode>().unwrap();
+    assert_eq!(left.value(), &Number::from(1));
+}
+
+#[test]
+fn test_ast_canonicalize() {
+    let parse = |source| {
+        let lexer = Lexer::from_source_code(source);
+        Parser::from_lexer(lexer).parse().unwrap().canonicalize()
+    };
+
+    for (a, b) in [
+        ("a + b", "b + a"),
+        ("x*2", "2x"),
+        ("sin(b*a) + 1", "1 + sin(a*b)"),
+        ("(c + d)*(a + b)", "(b + a)*(d + c)"),
+    ] {
+        let (a, b) = (parse(a), parse(b));
+        assert_eq!(a.structural_cmp(b.as_ref()), Ordering::Equal);
+        assert_eq!(a.to_infix(), b.to_infix());
+    }
+
+    // Other actions keep their order.
+    assert_ne!(
+        parse("a - b").structural_cmp(parse("b - a").as_ref()),
+        Ordering::Equal
+    );
+    assert_eq!(parse("b / a").to_infix(), "b/a");
+}
+
+#[test]
+fn test_ast_structural_cmp() {
+    let parse = |source| {
+        let lexer = Lexer::from_source_code(source);
+        Parser::from_lexer(lexer).parse().unwrap()
+    };
+    let binary = |actor| -> NodeBox { Box::new(BinaryNode::new(2.into(), actor, 3.into())) };
+    let (pow, xor) = (binary(BinaryAction::Pow), binary(BinaryAction::Xor));
+    assert_eq!(pow.structural_cmp(xor.as_ref()), Ordering::Less);
+    assert_eq!(xor.structural_cmp(pow.as_ref()), Ordering::Greater);
+
+    // Numbers are compared by value and kind, not as displayed.
+    let (int, flt): (NodeBox, NodeBox) = (1.into(), 1.0.into());
+    assert_eq!(int.structural_cmp(flt.as_ref()), Ordering::Less);
+    let (small, large): (NodeBox, NodeBox) = (9.into(), 10.into());
+    assert_eq!(small.structural_cmp(large.as_ref()), Ordering::Less);
+
+    // Nodes of different types are ordered by type.
+    let (a, b) = (parse("x + 1"), parse("sin(x)"));
+    assert_eq!(a.structural_cmp(b.as_ref()), Ordering::Less);
+    assert_eq!(b.structural_cmp(a.as_ref()), Ordering::Greater);
+    assert_eq!(a.structural_cmp(parse("x + 1").as_ref()), Ordering::Equal);
+}
+
+#[test]
+fn test_ast_content_hash() {
+    let parse = |source| {
+        let lexer = Lexer::from_source_code(source);
+        Parser::from_lexer(lexer).parse().unwrap()
+    };
+
+    // Spans and whitespace are not hashed.
+    for (a, b) in [
+        ("1 + 2*x", "1+2 * x"),
+        ("sin(a) - b", "(sin(a)) - b"),
+        ("if(x, 1.5, -y)", "if(x,1.5,-y)"),
+    ] {
+        assert_eq!(parse(a).content_hash(), parse(b).content_hash(), "{:?}", a);
+    }
+
+    for (a, b) in [
+        ("a + b", "b + a"),
+        ("a + b", "a - b"),
+        ("1", "1.0"),
+        ("x", "y"),
+        ("sin(x)", "cos(x)"),
+        ("-x", "x"),
+    ] {
+        assert_ne!(parse(a).content_hash(), parse(b).content_hash(), "{:?}", a);
+    }
+
+    assert_eq!(
+        parse("a + b").canonicalize().content_hash(),
+        parse("b + a").canonicalize().content_hash()
+    );
+
+    // Hashes do not depend on the platform or the features enabled.
+    assert_eq!(parse("hypot(3, 4) - a").content_hash(), 0xf5636843e89dd3ec);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_ast_threads() {
+    use std::{sync::Arc, thread};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<NodeBox>();
+
+    let lexer = Lexer::from_source_code("2^10 + x");
+    let node: Arc<dyn Node> = Arc::from(Parser::from_lexer(lexer).parse().unwrap());
+
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let node = Arc::clone(&node);
+            thread::spawn(move || node.evaluate_indexed(&[Number::from(i)]))
+        })
+        .collect();
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(handle.join().unwrap(), Number::from(1024 + i as i128));
+    }
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "bigint")]
+
+use tilted::{Lexer, Number, Parser};
+
+macro_rules! make_bigint_test {
+    ($name: ident, $source: literal, $expected: literal) => {
+        #[test]
+        fn $name() {
+            let lexer = Lexer::from_source_code($source);
+            let mut parser = Parser::from_lexer(lexer);
+            let node = parser.parse().unwrap();
+
+            assert_eq!(node.evaluate().to_string(), $expected);
+        }
+    };
+}
+
+make_bigint_test!(
+    test_bigint_pow_128,
+    "2^128",
+    "340282366920938463463374607431768211456"
+);
+make_bigint_test!(
+    test_bigint_pow_200,
+    "2^200",
+    "1606938044258990275541962092341162602522202993782792835301376"
+);
+make_bigint_test!(
+    test_bigint_mul_overflow,
+    "2^126 * 4",
+    "340282366920938463463374607431768211456"
+);
+make_bigint_test!(
+    test_bigint_add_overflow,
+    "(2^127 - 1) + 1",
+    "170141183460469231731687303715884105728"
+);
+make_bigint_test!(test_bigint_div, "2^200 / 2^190", "1024");
+make_bigint_test!(test_bigint_floor_div, "-(2^200 + 1) // 2^190", "-1025");
+
+#[test]
+fn test_bigint_pow_exact() {
+    let lexer = Lexer::from_source_code("2^128 - 2^128 + 1");
+    let mut parser = Parser::from_lexer(lexer);
+    let node = parser.parse().unwrap();
+
+    assert!(matches!(node.evaluate(), Number::Int(1)));
+}
+
+#[test]
+fn test_bigint_flt() {
+    let lexer = Lexer::from_source_code("2^200 * 0.5");
+    let mut parser = Parser::from_lexer(lexer);
+    let node = parser.parse().unwrap();
+
+    assert!(matches!(node.evaluate(), Number::Flt(_)));
+    assert_eq!(node.evaluate(), Number::from(2f64.powi(199)));
+}
+
+#[test]
+fn test_bigint_factorial() {
+    let factorial = (1..=50).fold(Number::from(1), |acc, n| acc * Number::from(n));
+
+    assert!(matches!(factorial, Number::Big(_)));
+    assert_eq!(
+        factorial.to_string(),
+        "30414093201713378043612608166064768844377641568960512000000000000"
+    );
+    assert!(factorial > Number::from(i128::MAX));
+}
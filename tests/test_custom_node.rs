@@ -0,0 +1,99 @@
+// Nodes defined outside the crate must be registered with `typetag` when
+// serialisation is enabled, so these tests only run without it.
+#![cfg(not(feature = "serde"))]
+
+use core::cmp::Ordering;
+use core::fmt::{self, Display};
+
+use tilted::ast::Node;
+use tilted::{BinaryAction, BinaryNode, NodeBox, Number, PlainNode};
+
+/// Node implementing only the required methods of [`Node`].
+#[derive(Debug, Clone)]
+struct Answer;
+
+impl Node for Answer {
+    fn evaluate(&self) -> Number {
+        Number::Int(42)
+    }
+
+    fn to_tree(&self) -> Vec<String> {
+        vec![String::from("Answer")]
+    }
+}
+
+impl Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_tree().join("\n"))
+    }
+}
+
+#[test]
+fn test_custom_node_evaluate_with() {
+    let vars: &[Number] = &[Number::from(1)];
+    assert_eq!(Answer.evaluate_with(&vars), Number::Int(42));
+    assert_eq!(Answer.evaluate_indexed(vars), Number::Int(42));
+}
+
+#[test]
+fn test_custom_node_to_infix() {
+    let node = BinaryNode::new(
+        Box::new(Answer),
+        BinaryAction::Mul,
+        Box::new(PlainNode::from(2)),
+    );
+    assert_eq!(node.to_infix(), "42*2");
+    assert_eq!(node.summary(), "42*2 = 84");
+}
+
+#[test]
+fn test_custom_node_partial_eval() {
+    let vars: &[Number] = &[];
+    let node = Answer.partial_eval(&vars);
+    assert_eq!(node.as_number(), Some(&Number::Int(42)));
+
+    let node = BinaryNode::new(
+        Box::new(Answer),
+        BinaryAction::Add,
+        Box::new(PlainNode::from(1)),
+    );
+    let node = node.partial_eval(&vars);
+    assert_eq!(node.as_number(), Some(&Number::Int(43)));
+}
+
+#[test]
+fn test_custom_node_canonicalize() {
+    let node: NodeBox = Box::new(BinaryNode::new(
+        Box::new(Answer),
+        BinaryAction::Add,
+        Box::new(PlainNode::from(1)),
+    ));
+    let node = node.canonicalize();
+    assert_eq!(node.to_tree(), ["Op(+)", "`-- 1", "`-- Answer"]);
+
+    // Nodes defined outside the crate come after those defined in it.
+    let answer: NodeBox = Box::new(Answer);
+    assert_eq!(
+        answer.structural_cmp(&PlainNode::from(1)),
+        Ordering::Greater
+    );
+    assert_eq!(answer.structural_cmp(&Answer), Ordering::Equal);
+}
+
+#[test]
+fn test_custom_node_content_hash() {
+    let answer: NodeBox = Box::new(Answer);
+    assert_eq!(answer.content_hash(), Answer.content_hash());
+    assert_ne!(answer.content_hash(), PlainNode::from(42).content_hash());
+}
+
+#[test]
+fn test_custom_node_to_code() {
+    let node = BinaryNode::new(
+        Box::new(Answer),
+        BinaryAction::Add,
+        Box::new(PlainNode::from(1)),
+    );
+    assert_eq!(node.to_rust(), None);
+    assert_eq!(node.to_c(), None);
+}
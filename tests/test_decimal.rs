@@ -0,0 +1,98 @@
+#![cfg(feature = "decimal")]
+
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use tilted::{DecimalContext, DecimalRounding, Lexer, Number, Parser};
+
+macro_rules! make_decimal_test {
+    ($name: ident, $source: literal, $expected: literal) => {
+        #[test]
+        fn $name() {
+            let lexer = Lexer::from_source_code($source);
+            let mut parser = Parser::from_lexer(lexer);
+            let node = parser.parse().unwrap();
+
+            match node.evaluate() {
+                Number::Dec(n) => assert_eq!(n, Decimal::from_str($expected).unwrap()),
+                n => panic!("Expected a decimal, found {:?}", n),
+            }
+        }
+    };
+}
+
+make_decimal_test!(test_decimal_one, "0.1", "0.1");
+make_decimal_test!(test_decimal_add, "0.1 + 0.2", "0.3");
+make_decimal_test!(test_decimal_sub, "0.3 - 0.1", "0.2");
+make_decimal_test!(test_decimal_mul_int, "1.1 * 3", "3.3");
+make_decimal_test!(test_decimal_div, "1.0 / 4", "0.25");
+make_decimal_test!(test_decimal_floor_div, "-7.5 // 2", "-4");
+make_decimal_test!(
+    test_decimal_div_repeating,
+    "10.0 / 3",
+    "3.3333333333333333333333333333"
+);
+make_decimal_test!(test_decimal_pow, "1.1^2", "1.21");
+make_decimal_test!(test_decimal_neg, "-(0.1 + 0.2)", "-0.3");
+
+#[test]
+fn test_decimal_exact_eq() {
+    let lexer = Lexer::from_source_code("0.1 + 0.2");
+    let mut parser = Parser::from_lexer(lexer);
+    let node = parser.parse().unwrap();
+
+    assert_eq!(
+        node.evaluate(),
+        Number::Dec(Decimal::from_str("0.3").unwrap())
+    );
+    assert_ne!(
+        node.evaluate(),
+        Number::Dec(Decimal::from_str("0.30000000000000001").unwrap())
+    );
+}
+
+#[test]
+fn test_decimal_with_float() {
+    let lexer = Lexer::from_source_code("0.5 + sin(0)");
+    let mut parser = Parser::from_lexer(lexer);
+    let node = parser.parse().unwrap();
+
+    assert!(matches!(node.evaluate(), Number::Flt(_)));
+    assert_eq!(node.evaluate(), Number::Flt(0.5));
+}
+
+#[test]
+fn test_decimal_context() {
+    let eval = |source, scale, rounding| {
+        let lexer = Lexer::from_source_code(source);
+        let node = Parser::from_lexer(lexer).parse().unwrap();
+        let vars: &[Number] = &[];
+        node.evaluate_with_context(&vars, &DecimalContext { scale, rounding })
+    };
+    let dec = |s| Number::Dec(Decimal::from_str(s).unwrap());
+
+    // Results are kept to the configured scale.
+    assert_eq!(eval("1.0/3", 4, DecimalRounding::Truncate), dec("0.3333"));
+    assert_eq!(
+        eval("1.0/3", 4, DecimalRounding::Truncate).to_string(),
+        "0.3333"
+    );
+
+    // The rounding mode decides the last digit.
+    assert_eq!(eval("2.0/3", 4, DecimalRounding::HalfUp), dec("0.6667"));
+    assert_eq!(eval("2.0/3", 4, DecimalRounding::Truncate), dec("0.6666"));
+    assert_eq!(eval("0.125", 2, DecimalRounding::HalfUp), dec("0.13"));
+    assert_eq!(eval("0.125", 2, DecimalRounding::HalfEven), dec("0.12"));
+    assert_eq!(eval("-2.0/3", 2, DecimalRounding::Floor), dec("-0.67"));
+    assert_eq!(eval("-2.0/3", 2, DecimalRounding::Ceiling), dec("-0.66"));
+
+    // Rounding happens after each action, not only at the end.
+    assert_eq!(eval("1.0/3 * 3", 4, DecimalRounding::HalfUp), dec("0.9999"));
+
+    // Decimal literals are rounded too, but other numbers are not.
+    assert_eq!(eval("7/2", 0, DecimalRounding::HalfUp), Number::from(3));
+    assert_eq!(
+        eval("sin(1)", 0, DecimalRounding::Truncate),
+        Number::Flt(1f64.sin())
+    );
+}
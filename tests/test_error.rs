@@ -0,0 +1,73 @@
+use std::error::Error;
+
+use tilted::{try_evaluate, EvalError, Function, LexError, ParseError, TilError};
+
+#[test]
+fn test_error_boxed() {
+    let errors: [Box<dyn Error + Send + Sync>; 4] = [
+        Box::new(LexError::MissingDigits(0)),
+        Box::new(ParseError::UnexpectedEOF),
+        Box::new(EvalError::DomainError {
+            func: Function::Asin,
+            value: 2.0,
+        }),
+        Box::new(TilError::from(ParseError::UnexpectedEOF)),
+    ];
+
+    for error in errors {
+        assert!(!error.to_string().is_empty());
+    }
+}
+
+#[test]
+fn test_error_source() {
+    for source in ["1 + $", "1 +", "asin(2)"] {
+        let error = try_evaluate(source).unwrap_err();
+        let inner = error.source().expect("errors have a source");
+
+        // The source is the error from the stage that failed.
+        assert_eq!(inner.to_string(), error.to_string(), "{:?}", source);
+        assert!(inner.source().is_none());
+    }
+
+    let error = try_evaluate("1 + $").unwrap_err();
+    let inner = error.source().unwrap();
+    assert!(inner.downcast_ref::<LexError>().is_some());
+}
+
+#[test]
+fn test_error_question_mark() {
+    fn run() -> Result<(), Box<dyn Error>> {
+        try_evaluate("1 +")?;
+        Ok(())
+    }
+
+    assert_eq!(
+        run().unwrap_err().to_string(),
+        "Expected an operand after the operator at index 2"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_error_serialize() {
+    let error = match try_evaluate("1 + *5").unwrap_err() {
+        TilError::Parse(e) => e,
+        e => panic!("Unexpected error {:?}", e),
+    };
+    let json = serde_json::to_value(&error).unwrap();
+
+    assert_eq!(json["code"], "InvalidUnaryOperator");
+    assert_eq!(json["message"], error.to_string());
+    assert_eq!(json["span"]["start_index"], 4);
+    assert_eq!(json["span"]["end_index"], 4);
+
+    // Errors without a location have a null span.
+    let json = serde_json::to_value(ParseError::UnexpectedEOF).unwrap();
+    assert_eq!(json["code"], "UnexpectedEOF");
+    assert!(json["span"].is_null());
+
+    let json = serde_json::to_value(LexError::MissingDigits(3)).unwrap();
+    assert_eq!(json["code"], "MissingDigits");
+    assert_eq!(json["span"]["start_index"], 3);
+}
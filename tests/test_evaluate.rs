@@ -0,0 +1,329 @@
+use tilted::{
+    eval_formatted, numerically_equivalent, try_evaluate, EvalError, EvalOrder, EvalResult,
+    FormatOptions, Function, LexError, Lexer, Number, NumberLayout, ParseError, Parser, TilError,
+};
+
+#[test]
+fn test_evaluate_ok() {
+    assert_eq!(try_evaluate("7 + 6 * 2").unwrap(), Number::from(19));
+    assert_eq!(
+        try_evaluate(String::from("2^10")).unwrap(),
+        Number::from(1024)
+    );
+}
+
+#[test]
+fn test_evaluate_formatted() {
+    let opts = FormatOptions::default();
+    let layout = NumberLayout::default()
+        .with_decimals(2)
+        .with_group_separator(',')
+        .with_scientific_threshold(6);
+
+    assert_eq!(eval_formatted("10^3", &opts, &layout).unwrap(), "1,000.00");
+    assert_eq!(
+        eval_formatted("1.5 / 10^7", &opts, &layout).unwrap(),
+        "1.50e-07"
+    );
+    assert!(eval_formatted("asin(2)", &opts, &layout).is_err());
+}
+
+#[test]
+fn test_evaluate_invalid_input() {
+    for source in ["", "1 +", "(1", "1)", "$", ".", "sin(1, 2)", "1 2"] {
+        assert!(try_evaluate(source).is_err(), "{:?}", source);
+    }
+}
+
+#[test]
+fn test_evaluate_error_kinds() {
+    assert!(matches!(
+        try_evaluate("1 $ 2"),
+        Err(TilError::Lex(LexError::UnrecognisedCharacter('$', 2)))
+    ));
+    assert!(matches!(
+        try_evaluate("1 +"),
+        Err(TilError::Parse(ParseError::MissingOperand { .. }))
+    ));
+    assert!(matches!(
+        try_evaluate("log2(0)"),
+        Err(TilError::Eval(EvalError::DomainError {
+            func: Function::Log2,
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn test_evaluate_domain_errors() {
+    for (source, function, value) in [
+        ("asin(2)", Function::Asin, 2.0),
+        ("acos(-1.5)", Function::Acos, -1.5),
+        ("asec(0.5)", Function::Asec, 0.5),
+        ("acsc(0)", Function::Acsc, 0.0),
+        ("log10(0)", Function::Log10, 0.0),
+        ("log2(-4)", Function::Log2, -4.0),
+        ("1 + 2 * log10(1 - 3)", Function::Log10, -2.0),
+        ("if(1, asin(3), 0)", Function::Asin, 3.0),
+    ] {
+        match try_evaluate(source) {
+            Err(TilError::Eval(EvalError::DomainError { func, value: v })) => {
+                assert_eq!((func, v), (function, value), "{:?}", source)
+            }
+            r => panic!("Unexpected result {:?} for {:?}", r, source),
+        }
+    }
+
+    let error = try_evaluate("asin(2)").unwrap_err();
+    assert_eq!(error.to_string(), "asin is undefined for 2");
+}
+
+#[test]
+fn test_evaluate_history_out_of_range() {
+    // There are no earlier results outside a session.
+    match try_evaluate("1 + hist(1)") {
+        Err(TilError::Eval(EvalError::HistoryOutOfRange(n))) => assert_eq!(n, 1.0),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    let lexer = Lexer::from_source_code("hist(1)");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+    assert_eq!(node.evaluate().to_string(), "NaN");
+}
+
+#[test]
+fn test_evaluate_domain_ok() {
+    for source in [
+        "asin(1)",
+        "acos(-1)",
+        "asec(1)",
+        "log2(8)",
+        "if(0, asin(3), 0)",
+    ] {
+        assert!(try_evaluate(source).is_ok(), "{:?}", source);
+    }
+
+    // Infallible evaluation keeps resulting in NaN.
+    let lexer = Lexer::from_source_code("asin(2) + log10(0)");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+    assert_eq!(node.evaluate().to_string(), "NaN");
+    assert!(node.try_evaluate().is_err());
+}
+
+#[test]
+fn test_evaluate_huge_numbers() {
+    assert!(try_evaluate("9".repeat(100)).is_err());
+
+    // Integers that overflow do not panic.
+    for source in [
+        "2^126 * 4",
+        "2^127 + 2^127",
+        "-(2^126) - 2^127",
+        "(0 - 2^126 * 2) / (0 - 1)",
+        "-(0 - 2^126 * 2)",
+        "10^40",
+        "10.0^400",
+    ] {
+        assert!(try_evaluate(source).is_ok(), "{:?}", source);
+    }
+}
+
+#[test]
+fn test_evaluate_deep_parens() {
+    let source = format!("{}1{}", "(".repeat(1000), ")".repeat(1000));
+    match try_evaluate(source) {
+        Err(TilError::Parse(ParseError::TooDeep(_))) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    let source = format!("{}1{}", "sin(".repeat(1000), ")".repeat(1000));
+    assert!(try_evaluate(source).is_err());
+
+    let source = format!("{}1", "(".repeat(1000));
+    assert!(try_evaluate(source).is_err());
+}
+
+#[test]
+fn test_evaluate_long_chains() {
+    let source = vec!["1"; 2000].join(" + ");
+    match try_evaluate(source) {
+        Err(TilError::Parse(ParseError::TooDeep(_))) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    // Chains far too long to evaluate recursively are rejected, not crashing.
+    for op in [" + ", " * ", " && ", "^"] {
+        let source = vec!["1"; 100_000].join(op);
+        assert!(try_evaluate(&source).is_err(), "{:?}", op);
+    }
+
+    let source = vec!["1"; 500].join(" + ");
+    assert_eq!(try_evaluate(source).unwrap(), Number::from(500));
+
+    let source = format!("{}1", "-".repeat(2000));
+    assert_eq!(try_evaluate(source).unwrap(), Number::from(1));
+}
+
+#[test]
+fn test_evaluate_typed() {
+    for (source, exact) in [
+        ("1 + 2", true),
+        ("2^10 // 3", true),
+        ("if(1 < 2, 3, 4)", true),
+        ("sin(1)", false),
+        // Float literals are exact decimals if enabled.
+        ("1.5 * 2", cfg!(feature = "decimal")),
+        ("2^(-1)", false),
+        ("sin(1) > 0", false),
+        ("x + 1", false),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        match node.evaluate_typed() {
+            EvalResult::Exact(n) => {
+                assert!(exact, "{:?}", source);
+                assert_eq!(n, node.evaluate());
+            }
+            EvalResult::Approx(_) => assert!(!exact, "{:?}", source),
+        }
+    }
+
+    let mut parser = Parser::from_lexer(Lexer::from_source_code("1 + 2"));
+    assert_eq!(
+        parser.parse().unwrap().evaluate_typed(),
+        EvalResult::Exact(Number::from(3))
+    );
+}
+
+#[test]
+fn test_evaluate_clean() {
+    for (source, expected) in [
+        ("sin(3.141592653589793 / 2)", 1),
+        ("2cos(rad(60))", 1),
+        ("tan(rad(45)) * 3", 3),
+        ("cbrt(-8)", -2),
+        ("sin(3.141592653589793)", 0),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let node = Parser::from_lexer(lexer).parse().unwrap();
+
+        match node.evaluate_clean(1e-9) {
+            Number::Int(n) => assert_eq!(n, expected, "{:?}", source),
+            n => panic!("Unexpected result {:?} for {:?}", n, source),
+        }
+
+        // Normal evaluation is unchanged.
+        assert!(matches!(node.evaluate(), Number::Flt(_)), "{:?}", source);
+    }
+
+    for source in ["sin(1)", "log10(2)", "1 / 0.0", "0 / 0.0"] {
+        let lexer = Lexer::from_source_code(source);
+        let node = Parser::from_lexer(lexer).parse().unwrap();
+
+        assert!(
+            matches!(node.evaluate_clean(1e-9), Number::Flt(_)),
+            "{:?}",
+            source
+        );
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_evaluate_deadline() {
+    use std::time::{Duration, Instant};
+
+    let lexer = Lexer::from_source_code("sin(1)^2 + cos(1)^2");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+    let deadline = Instant::now() + Duration::from_secs(60);
+    assert_eq!(node.evaluate_deadline(deadline).unwrap(), Number::from(1));
+
+    // Domain errors are still reported.
+    let lexer = Lexer::from_source_code("asin(2)");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+    assert!(matches!(
+        node.evaluate_deadline(deadline),
+        Err(EvalError::DomainError { .. })
+    ));
+
+    // A long expression cannot finish once its deadline has passed.
+    let source = vec!["sin(1)"; 500].join(" + ");
+    let lexer = Lexer::from_source_code(&source);
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+    assert!(matches!(
+        node.evaluate_deadline(Instant::now()),
+        Err(EvalError::Timeout)
+    ));
+}
+
+#[test]
+fn test_evaluate_trace() {
+    let lexer = Lexer::from_source_code("(1.0/3) + (2.0/3)");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+
+    let (value, ltr) = node.evaluate_trace(EvalOrder::LeftToRight);
+    assert_eq!(value, node.evaluate());
+    assert_eq!(ltr.len(), 3);
+    assert!(ltr[0].starts_with('1') && ltr[0].contains("/3 = 0.333"));
+    assert!(ltr[1].starts_with('2') && ltr[1].contains("/3 = 0.666"));
+    assert!(ltr[2].contains("/3 + 2"));
+
+    // The same steps are taken, with the operands swapped.
+    let (value, rtl) = node.evaluate_trace(EvalOrder::RightToLeft);
+    assert_eq!(value, node.evaluate());
+    assert_eq!(rtl, [ltr[1].as_str(), &ltr[0], &ltr[2]]);
+}
+
+#[test]
+fn test_evaluate_trace_short_circuit() {
+    let lexer = Lexer::from_source_code("0 && 1/0");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+
+    for order in [EvalOrder::LeftToRight, EvalOrder::RightToLeft] {
+        assert_eq!(node.evaluate_trace(order).1, ["0 && 1/0 = 0"]);
+    }
+}
+
+#[test]
+fn test_evaluate_numerically_equivalent() {
+    let parse = |source| {
+        let lexer = Lexer::from_source_code(source);
+        Parser::from_lexer(lexer).parse().unwrap()
+    };
+
+    for (a, b, equivalent) in [
+        ("sin(x)^2 + cos(x)^2", "1", true),
+        ("(x + 1)^2", "x^2 + 2x + 1", true),
+        ("log10(x^2)", "2log10(|x|)", true),
+        ("sin(x)", "x", false),
+        ("x^2", "|x|*x", false),
+        ("x + y", "x + y", false),
+        ("log10(x)", "log10(-x)", false),
+    ] {
+        let (a, b) = (parse(a), parse(b));
+        assert_eq!(
+            numerically_equivalent(a.as_ref(), b.as_ref(), "x", 50, 1e-9),
+            equivalent,
+            "{} {}",
+            a.to_infix(),
+            b.to_infix()
+        );
+    }
+}
+
+#[test]
+fn test_evaluate_sample() {
+    let lexer = Lexer::from_source_code("2x");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+    assert_eq!(
+        node.sample("x", &[-1.0, 0.5, 3.0]),
+        [Number::from(-2), Number::from(1), Number::from(6)]
+    );
+
+    // Other variables are unbound.
+    let lexer = Lexer::from_source_code("x^2 + y");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+    assert!(node.sample("x", &[1.0])[0].is_nan());
+}
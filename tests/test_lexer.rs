@@ -1,4 +1,21 @@
-use tilted::{Function::*, Lexer, Operator::*, TokenKind::*};
+use tilted::{
+    Function::*, LexError, Lexer, LexerOptions, Operator::*, TokenKind::*, UnknownPolicy,
+};
+
+/// Number literals with decimal places are lexed as decimals if enabled.
+#[cfg(not(feature = "decimal"))]
+macro_rules! flt {
+    ($value: literal) => {
+        Flt($value)
+    };
+}
+
+#[cfg(feature = "decimal")]
+macro_rules! flt {
+    ($value: literal) => {
+        Dec(stringify!($value).parse().unwrap())
+    };
+}
 
 macro_rules! make_lexer_test {
     ($name: ident, $source: literal, [$($token_kind: expr,)*]) => {
@@ -24,12 +41,12 @@ make_lexer_test!(test_lexer_empty, "", [Eof,]);
 
 make_lexer_test!(test_lexer_one_int, "8", [Int(8), Eof,]);
 
-make_lexer_test!(test_lexer_one_flt, "9.0", [Flt(9.0), Eof,]);
+make_lexer_test!(test_lexer_one_flt, "9.0", [flt!(9.0), Eof,]);
 
 make_lexer_test!(
     test_lexer_one_int_and_flt,
     "8 9.0",
-    [Int(8), Flt(9.0), Eof,]
+    [Int(8), flt!(9.0), Eof,]
 );
 
 make_lexer_test!(
@@ -38,12 +55,46 @@ make_lexer_test!(
     [Op(Plus), Op(Minus), Op(Star), Op(Slash), Op(Caret), Eof,]
 );
 
+make_lexer_test!(
+    test_lexer_mod,
+    "7 mod 3 % x modx",
+    [Int(7), Op(Percent), Int(3), Op(Percent), Ident, Ident, Eof,]
+);
+
+make_lexer_test!(
+    test_lexer_fdiv,
+    "7 fdiv 2 / fdivx",
+    [Int(7), Op(FDiv), Int(2), Op(Slash), Ident, Eof,]
+);
+
+make_lexer_test!(
+    test_lexer_double_slash,
+    "7 // 2 / / 1",
+    [
+        Int(7),
+        Op(DoubleSlash),
+        Int(2),
+        Op(Slash),
+        Op(Slash),
+        Int(1),
+        Eof,
+    ]
+);
+
+make_lexer_test!(test_lexer_pow, "2^3", [Int(2), Op(Caret), Int(3), Eof,]);
+
+make_lexer_test!(
+    test_lexer_xor,
+    "6 xor 3 ^ xors",
+    [Int(6), Op(Xor), Int(3), Op(Caret), Ident, Eof,]
+);
+
 make_lexer_test!(test_lexer_parens, "( )", [LeftParen, RightParen, Eof,]);
 
 make_lexer_test!(
     test_lexer_simple_expr,
     "8 + 9.0 * 2",
-    [Int(8), Op(Plus), Flt(9.0), Op(Star), Int(2), Eof,]
+    [Int(8), Op(Plus), flt!(9.0), Op(Star), Int(2), Eof,]
 );
 
 make_lexer_test!(
@@ -53,7 +104,7 @@ make_lexer_test!(
         LeftParen,
         Int(8),
         Op(Plus),
-        Flt(9.0),
+        flt!(9.0),
         RightParen,
         Op(Star),
         Int(2),
@@ -69,7 +120,7 @@ make_lexer_test!(
         LeftParen,
         Int(8),
         Op(Plus),
-        Flt(9.0),
+        flt!(9.0),
         RightParen,
         Op(Star),
         Int(2),
@@ -86,8 +137,355 @@ make_lexer_test!(
 make_lexer_test!(
     test_lexer_trigo_with_expr,
     "sin(3.14)",
-    [Func(Sin), LeftParen, Flt(3.14), RightParen, Eof,]
+    [Func(Sin), LeftParen, flt!(3.14), RightParen, Eof,]
 );
 
 make_lexer_test!(E: test_lexer_too_many_decimals, "9.0.0");
-make_lexer_test!(E: test_lexer_invalid_char, "a");
+make_lexer_test!(E: test_lexer_invalid_char, "$");
+
+make_lexer_test!(
+    test_lexer_ident,
+    "2x + sinx - y2",
+    [Int(2), Ident, Op(Plus), Ident, Op(Minus), Ident, Eof,]
+);
+
+make_lexer_test!(
+    test_lexer_underscore,
+    "round_sig x_1",
+    [Func(RoundSig), Ident, Eof,]
+);
+
+make_lexer_test!(
+    test_lexer_builtin_aliases,
+    "arcsin tg ctg lg arcsinx",
+    [Func(Asin), Func(Tan), Func(Cot), Func(Log10), Ident, Eof,]
+);
+
+make_lexer_test!(test_lexer_leading_dot, ".5", [flt!(0.5), Eof,]);
+
+make_lexer_test!(
+    test_lexer_leading_dot_expr,
+    ".25 + .25",
+    [flt!(0.25), Op(Plus), flt!(0.25), Eof,]
+);
+
+make_lexer_test!(test_lexer_trailing_dot, "5.", [flt!(5.0), Eof,]);
+
+make_lexer_test!(
+    test_lexer_trailing_dot_expr,
+    "5. * 2",
+    [flt!(5.0), Op(Star), Int(2), Eof,]
+);
+
+make_lexer_test!(E: test_lexer_double_trailing_dot, "5..");
+
+make_lexer_test!(
+    test_lexer_scientific,
+    "1.5e3 2E-2 1e+2",
+    [flt!(1500.0), flt!(0.02), flt!(100.0), Eof,]
+);
+
+make_lexer_test!(test_lexer_exponent_like_ident, "2ex", [Int(2), Ident, Eof,]);
+
+make_lexer_test!(
+    test_lexer_radix,
+    "hex(FF) bin(1010) oct( 17 ) hex(ff)",
+    [Int(255), Int(10), Int(15), Int(255), Eof,]
+);
+
+make_lexer_test!(
+    test_lexer_radix_as_ident,
+    "hex + 1",
+    [Ident, Op(Plus), Int(1), Eof,]
+);
+
+#[test]
+fn test_lexer_invalid_radix() {
+    for source in ["bin(102)", "hex(FG)", "hex()", "oct(-7)", "hex(FF"] {
+        let mut lexer = Lexer::from_source_code(source);
+
+        match lexer.lex() {
+            Err(LexError::InvalidNumber { text, .. }) => assert_eq!(text, source),
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+}
+
+#[test]
+fn test_lexer_invalid_number() {
+    for (source, expected) in [
+        ("1.2.3", "1.2.3"),
+        ("1e", "1e"),
+        ("2.5e+ 1", "2.5e+"),
+        ("99999999999999999999999", "99999999999999999999999"),
+    ] {
+        let mut lexer = Lexer::from_source_code(source);
+
+        match lexer.lex() {
+            Err(LexError::InvalidNumber { text, span }) => {
+                assert_eq!(text, expected);
+                assert_eq!(span.start_index, 0);
+                assert_eq!(span.end_index, expected.len() - 1);
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+}
+
+#[test]
+fn test_lexer_lone_dot() {
+    let mut lexer = Lexer::from_source_code("1 + . 2");
+    lexer.lex().unwrap();
+    lexer.lex().unwrap();
+
+    match lexer.lex() {
+        Err(LexError::MissingDigits(4)) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+make_lexer_test!(
+    test_lexer_inf,
+    "inf -inf",
+    [Flt(f64::INFINITY), Op(Minus), Flt(f64::INFINITY), Eof,]
+);
+
+make_lexer_test!(
+    test_lexer_unicode_ops,
+    "2 × 3 ÷ 4.5",
+    [Int(2), Op(Star), Int(3), Op(Slash), flt!(4.5), Eof,]
+);
+
+#[test]
+fn test_lexer_multibyte_error_index() {
+    let mut lexer = Lexer::from_source_code("2 × $");
+    lexer.lex().unwrap();
+    lexer.lex().unwrap();
+
+    match lexer.lex() {
+        Err(LexError::UnrecognisedCharacter('$', 4)) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_lexer_multibyte_span() {
+    let mut lexer = Lexer::from_source_code("2 × 3.5");
+    let tokens = [lexer.lex(), lexer.lex(), lexer.lex(), lexer.lex()].map(Result::unwrap);
+
+    assert_eq!(tokens[1].span.start_index, 2);
+    assert_eq!(tokens[2].span.start_index, 4);
+    assert_eq!(&lexer[tokens[1].span], "×");
+    assert_eq!(&lexer[tokens[2].span], "3.5");
+    assert_eq!(&lexer[tokens[3].span], "");
+}
+
+make_lexer_test!(
+    test_lexer_log,
+    "log10(1000) log2",
+    [
+        Func(Log10),
+        LeftParen,
+        Int(1000),
+        RightParen,
+        Func(Log2),
+        Eof,
+    ]
+);
+
+make_lexer_test!(
+    test_lexer_args,
+    "hypot(3, 4)",
+    [
+        Func(Hypot),
+        LeftParen,
+        Int(3),
+        Comma,
+        Int(4),
+        RightParen,
+        Eof,
+    ]
+);
+
+#[test]
+fn test_lexer_from_string_types() {
+    let owned = String::from("1 + 2");
+    let borrowed: &str = "1 + 2";
+    let cow = std::borrow::Cow::from(borrowed);
+
+    for mut lexer in [
+        Lexer::from_source_code(owned.clone()),
+        Lexer::from_source_code(&owned),
+        Lexer::from_source_code(borrowed),
+        Lexer::from_source_code(cow),
+    ] {
+        assert_eq!(lexer.lex().unwrap().kind, Int(1));
+        assert_eq!(lexer.lex().unwrap().kind, Op(Plus));
+        assert_eq!(lexer.lex().unwrap().kind, Int(2));
+        assert_eq!(lexer.lex().unwrap().kind, Eof);
+    }
+}
+
+make_lexer_test!(
+    test_lexer_comparisons,
+    "< <= > >= == != =",
+    [
+        Op(Less),
+        Op(LessEqual),
+        Op(Greater),
+        Op(GreaterEqual),
+        Op(Equal),
+        Op(NotEqual),
+        Assign,
+        Eof,
+    ]
+);
+
+make_lexer_test!(
+    test_lexer_logic,
+    "&& || ! not",
+    [Op(And), Op(Or), Op(Not), Op(Not), Eof,]
+);
+
+make_lexer_test!(E: test_lexer_single_ampersand, "&");
+make_lexer_test!(test_lexer_single_bar, "| 1", [Bar, Int(1), Eof,]);
+make_lexer_test!(
+    test_lexer_bars,
+    "|x| || |-1|",
+    [Bar, Ident, Bar, Op(Or), Bar, Op(Minus), Int(1), Bar, Eof,]
+);
+make_lexer_test!(test_lexer_comment, "5 -- comment", [Int(5), Eof,]);
+make_lexer_test!(test_lexer_comment_only, "--", [Eof,]);
+make_lexer_test!(
+    test_lexer_comment_line,
+    "-- first\n1 -- second\n 2",
+    [Int(1), Int(2), Eof,]
+);
+make_lexer_test!(
+    test_lexer_double_minus,
+    "5 - -3 --3",
+    [
+        Int(5),
+        Op(Minus),
+        Op(Minus),
+        Int(3),
+        Op(Minus),
+        Op(Minus),
+        Int(3),
+        Eof,
+    ]
+);
+
+#[test]
+#[allow(clippy::approx_constant)]
+fn test_lexer_locale_options() {
+    let options = LexerOptions {
+        decimal_separator: ',',
+        argument_separator: ';',
+    };
+    let mut lexer = Lexer::from_source_code("3,14 hypot(3,5; 4) ,5").with_options(options);
+
+    for kind in [
+        flt!(3.14),
+        Func(Hypot),
+        LeftParen,
+        flt!(3.5),
+        Comma,
+        Int(4),
+        RightParen,
+        flt!(0.5),
+        Eof,
+    ] {
+        assert_eq!(lexer.lex().unwrap().kind, kind);
+    }
+}
+
+#[test]
+fn test_lexer_locale_options_errors() {
+    let options = LexerOptions {
+        decimal_separator: ',',
+        argument_separator: ';',
+    };
+
+    // The default decimal separator is no longer recognised.
+    let mut lexer = Lexer::from_source_code("3.14").with_options(options);
+    lexer.lex().unwrap();
+    match lexer.lex() {
+        Err(LexError::UnrecognisedCharacter('.', 1)) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    // Invalid numbers are reported as written.
+    let mut lexer = Lexer::from_source_code("1,2,3").with_options(options);
+    match lexer.lex() {
+        Err(LexError::InvalidNumber { text, span }) => {
+            assert_eq!(text, "1,2,3");
+            assert_eq!(span.end_index, 4);
+        }
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_lexer_unknown_policy() {
+    // Unknown characters are errors by default.
+    let mut lexer = Lexer::from_source_code("1 $ 2");
+    lexer.lex().unwrap();
+    match lexer.lex() {
+        Err(LexError::UnrecognisedCharacter('$', 2)) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    // Skipped characters are collected as warnings.
+    let mut lexer = Lexer::from_source_code("1 $ 2 €€").with_unknown_policy(UnknownPolicy::Skip);
+    for kind in [Int(1), Int(2), Eof] {
+        assert_eq!(lexer.lex().unwrap().kind, kind);
+    }
+    assert_eq!(lexer.warnings().len(), 3);
+    for (warning, expected) in lexer.warnings().iter().zip([('$', 2), ('€', 6), ('€', 7)]) {
+        match warning {
+            LexError::UnrecognisedCharacter(c, index) if (*c, *index) == expected => (),
+            w => panic!("Unexpected warning {:?}", w),
+        }
+    }
+    lexer.reset();
+    assert!(lexer.warnings().is_empty());
+
+    // Lexing may stop at the character instead, keeping the rest.
+    let mut lexer =
+        Lexer::from_source_code("1 € 2").with_unknown_policy(UnknownPolicy::StopAndKeepRest);
+    for kind in [Int(1), Eof, Eof] {
+        assert_eq!(lexer.lex().unwrap().kind, kind);
+    }
+    assert_eq!(lexer.rest(), "€ 2");
+    assert!(lexer.warnings().is_empty());
+}
+
+#[test]
+fn test_lexer_unrecognised_function() {
+    let mut lexer = Lexer::from_source_code("2 sn(0)");
+    lexer.lex().unwrap();
+    match lexer.lex() {
+        Err(LexError::UnrecognisedFunction(name, 2)) => assert_eq!(name, "sn"),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    // Separated names are variables.
+    let mut lexer = Lexer::from_source_code("sn (0)");
+    for kind in [Ident, LeftParen, Int(0), RightParen, Eof] {
+        assert_eq!(lexer.lex().unwrap().kind, kind);
+    }
+}
+
+#[test]
+fn test_lexer_custom_aliases() {
+    let mut lexer = Lexer::from_source_code("sine arcsin sin x")
+        .with_alias("sine", Sin)
+        .with_alias("arcsin", Acos)
+        .with_alias("sin", Cos);
+
+    // Built-in names cannot be redefined, built-in aliases can.
+    for kind in [Func(Sin), Func(Acos), Func(Sin), Ident, Eof] {
+        assert_eq!(lexer.lex().unwrap().kind, kind);
+    }
+}
@@ -0,0 +1,382 @@
+use tilted::{FormatOptions, Number, NumberKind, NumberLayout, TotalNumber};
+
+macro_rules! make_number_test {
+    ($name: ident, $left: expr, == $right: expr) => {
+        #[test]
+        fn $name() {
+            assert_eq!(Number::from($left), Number::from($right));
+        }
+    };
+    ($name: ident, $left: expr, != $right: expr) => {
+        #[test]
+        fn $name() {
+            assert_ne!(Number::from($left), Number::from($right));
+        }
+    };
+    ($name: ident, $left: expr, < $right: expr) => {
+        #[test]
+        fn $name() {
+            assert!(Number::from($left) < Number::from($right));
+        }
+    };
+}
+
+make_number_test!(test_number_small_flt_eq, 0.1 + 0.2, == 0.3);
+make_number_test!(test_number_small_flt_ne, 0.1, != 0.2);
+make_number_test!(test_number_large_flt_eq, 1e18, == 1e18 + 512.0);
+make_number_test!(test_number_large_flt_ne, 1e18, != 1.001e18);
+make_number_test!(test_number_large_int_flt_eq, 1_000_000_000_000_000_000i64, == 1e18);
+make_number_test!(test_number_large_int_ne, 1_000_000_000_000_000_000i64, != 1_000_000_000_000_000_001i64);
+make_number_test!(test_number_huge_flt_eq, 1e300, == 1e300 * (1.0 + 1e-15));
+make_number_test!(test_number_large_flt_lt, 1e18, < 1.001e18);
+make_number_test!(test_number_large_flt_not_lt, 1e18, == 1e18 - 512.0);
+
+make_number_test!(test_number_pos_neg_zero_eq, 0.0, == -0.0);
+make_number_test!(test_number_int_neg_zero_eq, 0, == -0.0);
+make_number_test!(test_number_nan_ne_nan, f64::NAN, != f64::NAN);
+make_number_test!(test_number_nan_ne_int, f64::NAN, != 0);
+
+#[test]
+fn test_number_nan_ordering() {
+    let nan = Number::from(f64::NAN);
+    assert_eq!(nan.partial_cmp(&nan), None);
+    assert_eq!(nan.partial_cmp(&Number::from(1)), None);
+    assert_eq!(Number::from(1.0).partial_cmp(&nan), None);
+}
+
+#[test]
+fn test_number_zero_ordering() {
+    let pos = Number::from(0.0);
+    let neg = Number::from(-0.0);
+    assert_eq!(pos.partial_cmp(&neg), Some(std::cmp::Ordering::Equal));
+    assert_eq!(
+        neg.partial_cmp(&Number::from(0)),
+        Some(std::cmp::Ordering::Equal)
+    );
+}
+
+#[test]
+fn test_number_to_percent_string() {
+    assert_eq!(Number::from(0.25).to_percent_string(1), "25.0%");
+    assert_eq!(Number::from(1.0).to_percent_string(1), "100.0%");
+    assert_eq!(Number::from(0.333).to_percent_string(1), "33.3%");
+    assert_eq!(Number::from(2).to_percent_string(0), "200%");
+}
+
+#[test]
+fn test_number_kind() {
+    assert_eq!(Number::from(2).kind(), NumberKind::Int);
+    assert_eq!(Number::from(2.5).kind(), NumberKind::Flt);
+    assert_eq!(Number::from(f64::NAN).kind(), NumberKind::Flt);
+    assert_eq!((Number::from(1) / Number::from(0)).kind(), NumberKind::Flt);
+}
+
+#[test]
+fn test_number_predicates() {
+    for (n, nan, finite, infinite) in [
+        (Number::from(i128::MAX), false, true, false),
+        (Number::from(-2.5), false, true, false),
+        (Number::from(1) / Number::from(0), false, false, true),
+        (Number::from(f64::NEG_INFINITY), false, false, true),
+        (Number::from(0) / Number::from(0), true, false, false),
+    ] {
+        assert_eq!(n.is_nan(), nan, "{:?}", n);
+        assert_eq!(n.is_finite(), finite, "{:?}", n);
+        assert_eq!(n.is_infinite(), infinite, "{:?}", n);
+    }
+}
+
+#[test]
+fn test_number_promote_to_float() {
+    assert!(matches!(
+        Number::from(2).promote_to_float(),
+        Number::Flt(n) if n == 2.0
+    ));
+    assert!(matches!(
+        Number::from(-7).promote_to_float(),
+        Number::Flt(n) if n == -7.0
+    ));
+    assert!(matches!(
+        Number::from(2.5).promote_to_float(),
+        Number::Flt(n) if n == 2.5
+    ));
+}
+
+#[test]
+fn test_number_checked_ops() {
+    let max = Number::from(i128::MAX);
+    let min = Number::from(i128::MIN);
+
+    // Normal cases.
+    assert!(matches!(
+        Number::from(2).checked_add(Number::from(3)),
+        Some(Number::Int(5))
+    ));
+    assert!(matches!(
+        Number::from(2).checked_sub(Number::from(3)),
+        Some(Number::Int(-1))
+    ));
+    assert!(matches!(
+        Number::from(-4).checked_mul(Number::from(3)),
+        Some(Number::Int(-12))
+    ));
+    assert_eq!(
+        Number::from(1.5).checked_add(Number::from(2)),
+        Some(Number::from(3.5))
+    );
+
+    // Integer overflow.
+    assert!(max.clone().checked_add(Number::from(1)).is_none());
+    assert!(min.checked_sub(Number::from(1)).is_none());
+    assert!(max.clone().checked_mul(Number::from(2)).is_none());
+
+    // Floats become infinite instead.
+    assert_eq!(
+        Number::from(f64::MAX).checked_mul(Number::from(2.0)),
+        Some(Number::from(f64::INFINITY))
+    );
+    assert_eq!(
+        max.checked_add(Number::from(1.0)),
+        Some(Number::from(i128::MAX as f64))
+    );
+}
+
+#[test]
+fn test_number_saturating() {
+    // In range, with floats rounded.
+    assert_eq!(Number::from(42).to_i32_saturating(), 42);
+    assert_eq!(Number::from(-2.5).to_i32_saturating(), -3);
+    assert_eq!(Number::from(254.6).to_u8_saturating(), 255);
+
+    // Out of range values are clamped.
+    assert_eq!(Number::from(300).to_u8_saturating(), u8::MAX);
+    assert_eq!(Number::from(1e30).to_i64_saturating(), i64::MAX);
+    assert_eq!(Number::from(-1e30).to_i16_saturating(), i16::MIN);
+    assert_eq!(Number::from(f64::INFINITY).to_u32_saturating(), u32::MAX);
+
+    // Negative values become zero for unsigned integers.
+    assert_eq!(Number::from(-5).to_u8_saturating(), 0);
+    assert_eq!(Number::from(-0.7).to_u64_saturating(), 0);
+
+    assert_eq!(Number::from(f64::NAN).to_i8_saturating(), 0);
+}
+
+#[test]
+fn test_number_to_fixed() {
+    assert_eq!(Number::from(3).to_fixed(2), "3.00");
+    assert_eq!(Number::from(3.145).to_fixed(2), "3.15");
+    assert_eq!(Number::from(-2.675).to_fixed(2), "-2.68");
+    assert_eq!(Number::from(-0.001).to_fixed(2), "0.00");
+    assert_eq!(Number::from(9.999).to_fixed(2), "10.00");
+    assert_eq!(Number::from(2.5).to_fixed(0), "3");
+    assert_eq!(Number::from(0.1).to_fixed(3), "0.100");
+    assert_eq!(Number::from(f64::NAN).to_fixed(2), "NaN");
+}
+
+#[test]
+fn test_number_to_string_with_default_opts() {
+    let opts = FormatOptions::default();
+    assert_eq!(Number::from(f64::NAN).to_string_with_opts(&opts), "NaN");
+    assert_eq!(
+        Number::from(f64::INFINITY).to_string_with_opts(&opts),
+        "inf"
+    );
+    assert_eq!(
+        Number::from(f64::NEG_INFINITY).to_string_with_opts(&opts),
+        "-inf"
+    );
+    assert_eq!(Number::from(2.5).to_string_with_opts(&opts), "2.5");
+}
+
+#[test]
+fn test_number_to_string_with_custom_opts() {
+    let opts = FormatOptions {
+        nan: "undefined".to_string(),
+        inf: "∞".to_string(),
+        neg_inf: "-∞".to_string(),
+    };
+    assert_eq!(
+        Number::from(f64::NAN).to_string_with_opts(&opts),
+        "undefined"
+    );
+    assert_eq!(Number::from(f64::INFINITY).to_string_with_opts(&opts), "∞");
+    assert_eq!(
+        Number::from(f64::NEG_INFINITY).to_string_with_opts(&opts),
+        "-∞"
+    );
+    assert_eq!(Number::from(-3).to_string_with_opts(&opts), "-3");
+}
+
+#[test]
+fn test_number_to_string_with_layout() {
+    let opts = FormatOptions::default();
+    let layout = NumberLayout::default()
+        .with_decimals(2)
+        .with_group_separator(',')
+        .with_scientific_threshold(6);
+    for (n, expected) in [
+        (Number::from(1000), "1,000.00"),
+        (Number::from(-1234567.891), "-1.23e+06"),
+        (Number::from(999999.994), "999,999.99"),
+        (Number::from(0.00000015), "1.50e-07"),
+        (Number::from(0), "0.00"),
+        (Number::from(-12.5), "-12.50"),
+    ] {
+        assert_eq!(n.to_string_with_layout(&opts, &layout), expected);
+    }
+
+    let layout = NumberLayout::default()
+        .with_group_separator('.')
+        .with_decimal_separator(',')
+        .with_scientific_threshold(20);
+    let with_layout = |n: f64| Number::from(n).to_string_with_layout(&opts, &layout);
+    assert_eq!(with_layout(1234.5), "1.234,5");
+    assert_eq!(with_layout(1e-25), "1e-25");
+    assert_eq!(with_layout(123.0), "123");
+}
+
+#[test]
+fn test_number_primitive_ops() {
+    let n = Number::from(6);
+
+    assert_eq!(n.clone() + 1, Number::from(7));
+    assert_eq!(n.clone() - 10i32, Number::from(-4));
+    assert_eq!(n.clone() * 2.5, Number::from(15.0));
+    assert_eq!(n.clone() / 4u8, Number::from(1));
+    assert_eq!(1 - n.clone(), Number::from(-5));
+    assert_eq!(3.0 / n.clone(), Number::from(0.5));
+    assert_eq!(i128::MAX * n.clone(), Number::from(i128::MAX) * n.clone());
+
+    let mut m = n;
+    m += 4;
+    m *= 0.5f32;
+    assert_eq!(m, Number::from(5.0));
+}
+
+#[test]
+fn test_number_zero_one() {
+    assert!(matches!(Number::zero(), Number::Int(0)));
+    assert!(matches!(Number::one(), Number::Int(1)));
+}
+
+#[test]
+fn test_number_default() {
+    assert!(matches!(Number::default(), Number::Int(0)));
+
+    let mut n = Number::from(2.5);
+    assert!(matches!(std::mem::take(&mut n), Number::Flt(_)));
+    assert!(matches!(n, Number::Int(0)));
+}
+
+#[test]
+fn test_number_is_zero() {
+    assert!(Number::from(0).is_zero());
+    assert!(Number::from(0.0).is_zero());
+    assert!(Number::from(-0.0).is_zero());
+    assert!(Number::from(1e-20).is_zero());
+    assert!(!Number::from(1).is_zero());
+    assert!(!Number::from(0.001).is_zero());
+    assert!(!Number::from(f64::NAN).is_zero());
+
+    // Divisors that are only approximately zero are still divided by.
+    let tiny = Number::from(1e-14);
+    assert!(tiny.is_zero());
+    for n in [
+        Number::from(1) / tiny.clone(),
+        Number::from(1).floor_div(tiny.clone()),
+        tiny.clone().pow(Number::from(-1)),
+    ] {
+        assert!(n.is_finite(), "{:?}", n);
+    }
+    assert!(!Number::from(5).floor_mod(tiny).is_nan());
+}
+
+#[test]
+fn test_number_is_one() {
+    assert!(Number::from(1).is_one());
+    assert!(Number::from(1.0).is_one());
+    assert!(Number::from(1.0 + 1e-15).is_one());
+    assert!(!Number::from(-1).is_one());
+    assert!(!Number::from(1.001).is_one());
+    assert!(!Number::from(f64::NAN).is_one());
+}
+
+#[test]
+fn test_number_total_order_sort() {
+    let mut numbers = vec![
+        Number::from(f64::NAN),
+        Number::from(3),
+        Number::from(-1.5),
+        Number::from(f64::INFINITY),
+        Number::from(2.0),
+        Number::from(2),
+        Number::from(-f64::NAN),
+        Number::from(i128::MIN),
+    ]
+    .into_iter()
+    .map(TotalNumber)
+    .collect::<Vec<_>>();
+    numbers.sort();
+
+    let sorted = numbers.iter().map(|n| n.0.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        sorted,
+        [
+            i128::MIN.to_string().as_str(),
+            "-1.5",
+            "2",
+            "2",
+            "3",
+            "inf",
+            "NaN",
+            "NaN"
+        ]
+    );
+
+    // Equal values are ordered by kind, integers first.
+    assert!(matches!(numbers[2].0, Number::Int(2)));
+    assert!(matches!(numbers[3].0, Number::Flt(_)));
+}
+
+#[test]
+fn test_number_total_order_exact() {
+    let a = TotalNumber(Number::from(1_000_000_000_000_000_000i64));
+    let b = TotalNumber(Number::from(1_000_000_000_000_000_001i64));
+    assert!(a < b);
+
+    // Unlike PartialEq, there is no tolerance.
+    let a = TotalNumber(Number::from(0.1 + 0.2));
+    let b = TotalNumber(Number::from(0.3));
+    assert!(a > b);
+    assert_eq!(a.clone(), a);
+
+    let nan = TotalNumber(Number::from(f64::NAN));
+    assert_eq!(nan, nan.clone());
+    assert!(nan > TotalNumber(Number::from(f64::INFINITY)));
+}
+
+#[test]
+fn test_number_pow() {
+    let pow = |base: Number, exp: Number| base.pow(exp);
+
+    assert!(matches!(
+        pow(Number::from(2), Number::from(10)),
+        Number::Int(1024)
+    ));
+    assert!(matches!(
+        pow(Number::from(-3), Number::from(3)),
+        Number::Int(-27)
+    ));
+    assert_eq!(pow(Number::from(2), Number::from(-1)), Number::from(0.5));
+    assert_eq!(pow(Number::from(2.5), Number::from(2)), Number::from(6.25));
+    assert_eq!(pow(Number::from(4), Number::from(0.5)), Number::from(2.0));
+
+    // Zero to a negative power is a division by zero.
+    assert_eq!(
+        pow(Number::from(0), Number::from(-1)),
+        Number::from(f64::INFINITY)
+    );
+
+    // Overflowing integers are not wrapped.
+    assert_eq!(pow(Number::from(10), Number::from(40)), Number::from(1e40));
+}
@@ -0,0 +1,28 @@
+#![cfg(feature = "parallel")]
+
+use tilted::{Lexer, Parser};
+
+#[test]
+fn test_parallel_sample() {
+    let lexer = Lexer::from_source_code("sin(x)^2 + x/(x + 1) - log10(x)");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+    let points: Vec<f64> = (0..10_000).map(|i| i as f64 / 100.0 - 50.0).collect();
+
+    let serial = node.sample("x", &points);
+    let parallel = node.sample_par("x", &points);
+
+    assert_eq!(parallel.len(), points.len());
+    for (a, b) in serial.iter().zip(&parallel) {
+        assert!(a == b || (a.is_nan() && b.is_nan()), "{:?} {:?}", a, b);
+    }
+}
+
+#[test]
+fn test_parallel_sample_empty() {
+    let lexer = Lexer::from_source_code("x + 1");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+
+    assert!(node.sample_par("x", &[]).is_empty());
+    // Other variables are unbound.
+    assert!(node.sample_par("y", &[1.0])[0].is_nan());
+}
@@ -1,7 +1,13 @@
-use tilted::{Lexer, Number, Parser};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use tilted::{
+    CaretMeaning, CodeType, Function, GrammarRule, IntDivMode, LexError, Lexer, LexerOptions,
+    Number, Operator, ParseError, Parser, Span, TilError, TokenKind, UnknownPolicy, Variables,
+};
 
 macro_rules! make_parser_test {
-    ($name: ident, $source: literal, $expected: literal) => {
+    ($name: ident, $source: literal, $expected: expr) => {
         #[test]
         fn $name() {
             let lexer = Lexer::from_source_code($source);
@@ -16,6 +22,18 @@ macro_rules! make_parser_test {
             assert_eq!(actual, expected);
         }
     };
+    (E: $name: ident, $source: literal, $error: pat $(if $guard: expr)?) => {
+        #[test]
+        fn $name() {
+            let lexer = Lexer::from_source_code($source);
+            let mut parser = Parser::from_lexer(lexer);
+
+            match parser.parse() {
+                Err($error) $(if $guard)? => (),
+                r => panic!("Unexpected result {:?}", r),
+            }
+        }
+    };
 }
 
 make_parser_test!(test_parser_one_int, "5", 5);
@@ -31,3 +49,1341 @@ make_parser_test!(test_parser_impl_mul, "5(5)", 25);
 make_parser_test!(test_parser_impl_mul_expr, "5(5 + 5)", 50);
 make_parser_test!(test_parser_complex_expr, "2*-(3*(1+-(2)))^2", -18);
 make_parser_test!(test_parser_impl_mul_func, "5sin(0)", 0.0);
+make_parser_test!(test_parser_func_expr, "sin(0) + 1", 1.0);
+make_parser_test!(test_parser_inf, "inf", f64::INFINITY);
+make_parser_test!(test_parser_neg_inf, "-inf", f64::NEG_INFINITY);
+make_parser_test!(test_parser_div_by_zero, "1/0", f64::INFINITY);
+make_parser_test!(test_parser_neg_div_by_zero, "-1/0", f64::NEG_INFINITY);
+make_parser_test!(test_parser_flt_div_by_zero, "2.5/0.0", f64::INFINITY);
+make_parser_test!(test_parser_div_by_tiny, "1/1e-14", 1e14);
+make_parser_test!(test_parser_tiny_div_by_tiny, "1e-14/1e-14", 1.0);
+make_parser_test!(test_parser_inf_arithmetic, "1 - inf", f64::NEG_INFINITY);
+make_parser_test!(test_parser_zero_neg_pow, "0^(-1)", f64::INFINITY);
+make_parser_test!(test_parser_flt_zero_neg_pow, "0.0^(-2)", f64::INFINITY);
+make_parser_test!(test_parser_tiny_neg_pow, "1e-14^(-1)", 1e14);
+make_parser_test!(test_parser_neg_pow, "2^(-3)", 0.125);
+make_parser_test!(test_parser_floor_div, "7 // 2", 3);
+make_parser_test!(test_parser_neg_floor_div, "-7 // 2", -4);
+make_parser_test!(test_parser_flt_floor_div, "7.5 // 2", 3.0);
+make_parser_test!(test_parser_floor_div_precedence, "1 + 7 // 2 * 2", 7);
+make_parser_test!(test_parser_floor_div_by_zero, "1 // 0", f64::INFINITY);
+make_parser_test!(test_parser_floor_div_by_tiny, "1 // 1e-14", 1e14);
+make_parser_test!(test_parser_mod, "7 mod 3", 1);
+make_parser_test!(test_parser_mod_symbol, "7 % 3", 1);
+make_parser_test!(test_parser_mod_neg, "-7 mod 3", 2);
+make_parser_test!(test_parser_mod_neg_divisor, "7 mod -3", -2);
+make_parser_test!(test_parser_mod_flt, "7.5 mod 2", 1.5);
+make_parser_test!(test_parser_mod_tiny, "5 mod 1e-14", 0.0);
+make_parser_test!(test_parser_mod_precedence, "1 + 2 * 7 mod 4", 3);
+make_parser_test!(test_parser_mod_implicit, "2(4) mod 3", 2);
+make_parser_test!(
+    E: test_parser_mod_variable,
+    "mod + 1",
+    TilError::Parse(ParseError::InvalidUnaryOperator(_))
+);
+make_parser_test!(test_parser_neg_base_neg_pow, "(-2)^(-2)", 0.25);
+make_parser_test!(test_parser_pow_overflow, "10^40", 1e40);
+make_parser_test!(test_parser_pow_huge_exponent, "1^5000000000", 1);
+make_parser_test!(test_parser_less, "1 < 2", 1);
+make_parser_test!(test_parser_less_equal, "2 <= 2", 1);
+make_parser_test!(test_parser_greater, "1 + 1 > 3", 0);
+make_parser_test!(test_parser_greater_equal, "3 >= 2 * 2", 0);
+make_parser_test!(test_parser_equal, "0.1 + 0.2 == 0.3", 1);
+make_parser_test!(test_parser_not_equal, "1 != 1.0", 0);
+make_parser_test!(test_parser_nan_comparison, "nan == nan", 0);
+make_parser_test!(test_parser_if_then, "if(1 < 2, 10, 20)", 10);
+make_parser_test!(test_parser_if_else, "if(1 > 2, 10, 20)", 20);
+make_parser_test!(test_parser_if_untaken_division, "if(0, 1/0, 5)", 5);
+make_parser_test!(test_parser_if_expr, "2if(3, 4, 5) + 1", 9);
+make_parser_test!(
+    E: test_parser_chained_comparison,
+    "1 < 2 < 3",
+    TilError::Parse(ParseError::TrailingTokens { .. })
+);
+make_parser_test!(
+    E: test_parser_if_wrong_arity,
+    "if(1, 2)",
+    TilError::Parse(ParseError::WrongArity { func: Function::If, expected: 3, got: 2 })
+);
+make_parser_test!(test_parser_not_precedence, "!1 < 0", 1);
+make_parser_test!(test_parser_not_keyword, "not 0 && 2", 1);
+make_parser_test!(test_parser_double_not, "!!5", 1);
+make_parser_test!(test_parser_and_or_precedence, "1 || 0 && 0", 1);
+make_parser_test!(test_parser_logic_cmp, "1 < 2 && 3 > 4 || 5 == 5", 1);
+make_parser_test!(test_parser_and_short_circuit, "0 && nan", 0);
+make_parser_test!(test_parser_or_short_circuit, "2 || nan", 1);
+make_parser_test!(
+    E: test_parser_not_in_sum,
+    "1 + !0",
+    TilError::Parse(ParseError::InvalidUnaryOperator(_))
+);
+
+#[test]
+fn test_parser_invalid_unary_operator() {
+    for (source, operator, message) in [
+        (
+            "*5",
+            Operator::Star,
+            "'*' cannot start an expression at index 0",
+        ),
+        (
+            "/3",
+            Operator::Slash,
+            "'/' cannot start an expression at index 0",
+        ),
+        (
+            "^2",
+            Operator::Caret,
+            "'^' cannot start an expression at index 0",
+        ),
+        (
+            "2 * (^2)",
+            Operator::Caret,
+            "'^' cannot start an expression at index 5",
+        ),
+        (
+            "1 + !0",
+            Operator::Not,
+            "'!' must be wrapped in parentheses here at index 4",
+        ),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+
+        match parser.parse() {
+            Err(TilError::Parse(e @ ParseError::InvalidUnaryOperator(token))) => {
+                assert_eq!(token.kind, TokenKind::Op(operator));
+                assert_eq!(e.to_string(), message);
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+}
+
+make_parser_test!(test_parser_hex, "hex(FF)", 255);
+make_parser_test!(test_parser_bin, "bin(1010)", 10);
+make_parser_test!(test_parser_radix_expr, "hex(10) * oct(10) + bin(1)", 129);
+make_parser_test!(test_parser_unicode_mul, "2 × 3", 6);
+make_parser_test!(test_parser_unicode_div, "6 ÷ 2", 3);
+make_parser_test!(test_parser_leading_dot, ".25 + .25", 0.5);
+make_parser_test!(test_parser_trailing_dot, "5. + 1", 6.0);
+make_parser_test!(test_parser_log10, "log10(1000)", 3.0);
+make_parser_test!(test_parser_log2, "log2(8)", 3.0);
+make_parser_test!(test_parser_log_expr, "log2(2^10) - log10(0.01)", 12.0);
+make_parser_test!(test_parser_cbrt, "cbrt(27)", 3.0);
+make_parser_test!(test_parser_cbrt_neg, "cbrt(-8)", -2.0);
+make_parser_test!(test_parser_cbrt_zero, "cbrt(0)", 0.0);
+make_parser_test!(test_parser_rad, "rad(180)", std::f64::consts::PI);
+make_parser_test!(
+    test_parser_rad_neg,
+    "rad(-90)",
+    -std::f64::consts::FRAC_PI_2
+);
+make_parser_test!(test_parser_deg, "deg(3.141592653589793)", 180.0);
+make_parser_test!(test_parser_deg_rad, "deg(rad(45))", 45.0);
+make_parser_test!(test_parser_hypot, "hypot(3, 4)", 5.0);
+make_parser_test!(test_parser_hypot_expr, "2hypot(1 + 2, 2 * 2) - 1", 9.0);
+make_parser_test!(
+    test_parser_hypot_large,
+    "hypot(3 * 10.0^200, 4 * 10.0^200)",
+    5e200
+);
+make_parser_test!(
+    test_parser_hypot_naive_overflow,
+    "(3 * 10.0^200)^2 + (4 * 10.0^200)^2",
+    f64::INFINITY
+);
+
+#[test]
+fn test_parser_round_sig() {
+    for (source, expected) in [
+        ("round_sig(12345, 2)", "12000"),
+        ("round_sig(0.012345, 2)", "0.012"),
+        ("round_sig(-0.012355, 3)", "-0.0124"),
+        ("round_sig(-98765, 1)", "-100000"),
+        ("round_sig(6.02e20, 1)", "600000000000000000000"),
+        ("round_sig(1.5e-20, 1)", "0.00000000000000000002"),
+        ("round_sig(0, 3)", "0"),
+        ("round_sig(123, 0)", "NaN"),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        assert_eq!(node.evaluate().to_string(), expected, "{:?}", source);
+        assert!(node.to_infix().starts_with("round_sig("));
+    }
+}
+
+#[test]
+fn test_parser_pct_change() {
+    for (source, expected) in [
+        ("pct_change(100, 120)", "20"),
+        ("pct_change(80, 60)", "-25"),
+        ("pct_change(-50, -25)", "-50"),
+        ("pct_change(3, 3)", "0"),
+        ("pct_change(0, 5)", "NaN"),
+        ("pct_change(0, 0)", "NaN"),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        assert_eq!(node.evaluate().to_string(), expected, "{:?}", source);
+    }
+}
+
+#[test]
+fn test_parser_nan() {
+    for source in [
+        "0/0", "0.0/0", "nan", "nan/0", "log10(0)", "log2(-1)", "1 mod 0",
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        match node.evaluate() {
+            Number::Flt(n) => assert!(n.is_nan()),
+            n => panic!("Expected NaN, found {}", n),
+        }
+    }
+}
+
+macro_rules! make_missing_operand_test {
+    ($name: ident, $source: literal, $operator_index: literal) => {
+        #[test]
+        fn $name() {
+            let lexer = Lexer::from_source_code($source);
+            let mut parser = Parser::from_lexer(lexer);
+
+            match parser.parse() {
+                Err(TilError::Parse(ParseError::MissingOperand { operator_span })) => {
+                    assert_eq!(operator_span.start_index, $operator_index)
+                }
+                r => panic!("Expected MissingOperand, found {:?}", r),
+            }
+        }
+    };
+}
+
+make_missing_operand_test!(test_parser_missing_operand_add, "1 +", 2);
+make_missing_operand_test!(test_parser_missing_operand_chain, "1 + 2 +", 6);
+make_missing_operand_test!(test_parser_missing_operand_mul, "3 *", 2);
+make_missing_operand_test!(test_parser_missing_operand_pow, "3 ^", 2);
+
+make_parser_test!(
+    E: test_parser_unrecognised_function,
+    "1 + sn(0)",
+    TilError::Lex(LexError::UnrecognisedFunction(..))
+);
+make_parser_test!(
+    E: test_parser_too_few_args,
+    "hypot(3)",
+    TilError::Parse(ParseError::WrongArity { func: Function::Hypot, expected: 2, got: 1 })
+);
+make_parser_test!(
+    E: test_parser_too_many_args,
+    "hypot(3, 4, 5)",
+    TilError::Parse(ParseError::WrongArity { func: Function::Hypot, expected: 2, got: 3 })
+);
+make_parser_test!(
+    E: test_parser_no_args,
+    "sin()",
+    TilError::Parse(ParseError::WrongArity { func: Function::Sin, expected: 1, got: 0 })
+);
+make_parser_test!(
+    E: test_parser_missing_comma,
+    "hypot(3 4)",
+    TilError::Parse(ParseError::CommaExpected(_))
+);
+
+#[test]
+fn test_parser_wrong_arity_message() {
+    let lexer = Lexer::from_source_code("sin(1, 2)");
+    let mut parser = Parser::from_lexer(lexer);
+
+    let error = parser.parse().unwrap_err();
+    assert_eq!(error.to_string(), "sin expects 1 argument, got 2");
+}
+
+make_parser_test!(test_parser_no_trailing_tokens, "1 + 2", 3);
+make_parser_test!(
+    E: test_parser_trailing_tokens,
+    "1 + 2 3",
+    TilError::Parse(ParseError::TrailingTokens { span }) if span.start_index == 6
+);
+make_parser_test!(
+    E: test_parser_consecutive_numbers,
+    "7 6",
+    TilError::Parse(ParseError::TrailingTokens { span }) if span.start_index == 2
+);
+make_parser_test!(
+    E: test_parser_trailing_comma,
+    "1 + 2, 3",
+    TilError::Parse(ParseError::TrailingTokens { span }) if span.start_index == 5
+);
+make_parser_test!(
+    E: test_parser_trailing_right_paren,
+    "(1 + 2))",
+    TilError::Parse(ParseError::MismatchRightParen(7))
+);
+make_parser_test!(
+    E: test_parser_unclosed_paren,
+    "(1 + 2",
+    TilError::Parse(ParseError::UnclosedParens { open: 1, .. })
+);
+make_parser_test!(test_parser_trailing_arg_comma, "hypot(3, 4,)", 5.0);
+make_parser_test!(test_parser_trailing_arg_comma_unary, "cbrt(8,) + 1", 3.0);
+make_parser_test!(
+    E: test_parser_empty_args_comma,
+    "hypot(,)",
+    TilError::Parse(ParseError::MissingArgument { comma_span }) if comma_span.start_index == 6
+);
+make_parser_test!(
+    E: test_parser_double_arg_comma,
+    "hypot(3,,4)",
+    TilError::Parse(ParseError::MissingArgument { comma_span }) if comma_span.start_index == 8
+);
+make_parser_test!(
+    E: test_parser_double_trailing_arg_comma,
+    "hypot(3, 4,,)",
+    TilError::Parse(ParseError::MissingArgument { comma_span }) if comma_span.start_index == 11
+);
+make_parser_test!(test_parser_abs_neg, "|-5|", 5);
+make_parser_test!(test_parser_abs_pos, "|5|", 5);
+make_parser_test!(test_parser_abs_sum, "|3| + |-4|", 7);
+make_parser_test!(test_parser_abs_expr, "2 * |1 - 4| ^ 2", 18);
+make_parser_test!(test_parser_abs_nested, "|1 - |-5| |", 4);
+make_parser_test!(test_parser_abs_nested_paren, "|(|-5|)|", 5);
+make_parser_test!(test_parser_abs_flt, "|-2.5|", 2.5);
+make_parser_test!(
+    E: test_parser_abs_unclosed,
+    "|1 + 2",
+    TilError::Parse(ParseError::BarExpected(_))
+);
+make_parser_test!(
+    E: test_parser_abs_adjacent,
+    "|3| |4|",
+    TilError::Parse(ParseError::TrailingTokens { span }) if span.start_index == 4
+);
+make_parser_test!(
+    E: test_parser_func_without_paren,
+    "sin 5",
+    TilError::Parse(ParseError::LeftParenExpected(_))
+);
+
+#[test]
+fn test_parser_parse_partial() {
+    let lexer = Lexer::from_source_code("1+2, 3");
+    let mut parser = Parser::from_lexer(lexer);
+
+    let node = parser.parse_partial().unwrap();
+    assert_eq!(node.evaluate(), Number::from(3));
+    assert_eq!(parser.peek().kind, TokenKind::Comma);
+    assert_eq!(parser.peek().span.start_index, 3);
+}
+
+#[test]
+fn test_parser_parse_partial_repeated() {
+    let lexer = Lexer::from_source_code("(1+2) 3");
+    let mut parser = Parser::from_lexer(lexer);
+
+    let node = parser.parse_partial().unwrap();
+    assert_eq!(node.evaluate(), Number::from(3));
+    assert_eq!(parser.peek().kind, TokenKind::Int(3));
+
+    let node = parser.parse_partial().unwrap();
+    assert_eq!(node.evaluate(), Number::from(3));
+    assert_eq!(parser.peek().kind, TokenKind::Eof);
+}
+
+#[test]
+fn test_parser_summary() {
+    let lexer = Lexer::from_source_code("7 + 6 * 2");
+    let mut parser = Parser::from_lexer(lexer);
+    let node = parser.parse().unwrap();
+
+    assert_eq!(node.summary(), "7 + 6*2 = 19");
+}
+
+#[test]
+fn test_parser_to_infix() {
+    for (source, expected) in [
+        ("7+6*(2-1)", "7 + 6*(2 - 1)"),
+        ("(1 - 2) - (3 - 4)", "1 - 2 - (3 - 4)"),
+        ("8 / (4 / 2)", "8/(4/2)"),
+        ("-(1 + 2)^2", "-(1 + 2)^2"),
+        ("2^(-1)", "2^(-1)"),
+        ("(2^3)^2", "(2^3)^2"),
+        ("2 * -3", "2*-3"),
+        ("2sin(0) + hypot(3, 4 - 1)", "2*sin(0) + hypot(3, 4 - 1)"),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        assert_eq!(node.to_infix(), expected);
+    }
+}
+
+#[test]
+fn test_parser_to_rust() {
+    for (source, expected) in [
+        ("sin(x) + 2", "(x.sin() + (2i128 as f64))"),
+        ("7 + 6*2", "(7i128 + (6i128 * 2i128))"),
+        ("1.5*x - 3", "((1.5f64 * x) - (3i128 as f64))"),
+        ("x^2 + 2^0.5", "(x.powi(2) + (2i128 as f64).powf(0.5f64))"),
+        ("-|x - 1|", "(-(x - (1i128 as f64)).abs())"),
+        ("csc(x)", "(1.0 / x.sin())"),
+        ("hypot(3, x)", "(3i128 as f64).hypot(x)"),
+        (
+            "1 < 2 && 0",
+            "(((((1i128 < 2i128) as i128) != 0) && (0i128 != 0)) as i128)",
+        ),
+        (
+            "if(x, 1, 2.5)",
+            "(if (x != 0.0) { (1i128 as f64) } else { 2.5f64 })",
+        ),
+        (
+            "inf + 2^(1-2)",
+            "(f64::INFINITY + (2i128 as f64).powf(((1i128 - 2i128) as f64)))",
+        ),
+        ("x^3000000000", "x.powf((3000000000i128 as f64))"),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        assert_eq!(node.to_rust().as_deref(), Some(expected), "{:?}", source);
+    }
+
+    let lexer = Lexer::from_source_code("7 + 6*2");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+    assert_eq!(node.to_rust_typed().unwrap().1, CodeType::Int);
+
+    // Constructs without an equivalent fail instead of becoming `NaN`.
+    for source in [
+        "round_sig(x, 2)",
+        "1 + hist(1)",
+        "2.5 xor 1",
+        "sin(x xor 1.5)",
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let node = Parser::from_lexer(lexer).parse().unwrap();
+        assert_eq!(node.to_rust(), None, "{:?}", source);
+    }
+}
+
+#[test]
+fn test_parser_to_c() {
+    for (source, expected) in [
+        ("2^3", "pow(2LL, 3LL)"),
+        ("sin(x)", "sin(x)"),
+        ("sin(x) + 2", "(sin(x) + 2LL)"),
+        ("7/2 + 1.5", "((7LL / 2LL) + 1.5)"),
+        (
+            "-7 % 3",
+            "(((double)(-7LL)) - 3LL * floor(((double)(-7LL)) / 3LL))",
+        ),
+        ("|x| + |-2|", "(fabs(x) + llabs((-2LL)))"),
+        ("acot(x) && !x", "(atan(1.0 / x) && (!x))"),
+        ("if(x < 0, 1, 2.5)", "((x < 0LL) ? 1LL : 2.5)"),
+        ("hypot(3, x) - inf", "(hypot(3LL, x) - INFINITY)"),
+        ("2^70", "pow(2LL, 70LL)"),
+        ("100000*100000", "(100000LL * 100000LL)"),
+        ("2^64 + 1", "(pow(2LL, 64LL) + 1LL)"),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        assert_eq!(node.to_c().as_deref(), Some(expected), "{:?}", source);
+    }
+
+    let lexer = Lexer::from_source_code("1/2");
+    let mut parser = Parser::from_lexer(lexer).with_int_div_mode(IntDivMode::Float);
+    assert_eq!(
+        parser.parse().unwrap().to_c_typed(),
+        Some((String::from("(((double)1LL) / 2LL)"), CodeType::Float))
+    );
+
+    // Constructs without an equivalent fail instead of becoming `NAN`.
+    for source in [
+        "round_sig(x, 2)",
+        "1 + hist(1)",
+        "2.5 xor 1",
+        "sin(x xor 1.5)",
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let node = Parser::from_lexer(lexer).parse().unwrap();
+        assert_eq!(node.to_c(), None, "{:?}", source);
+    }
+}
+
+#[test]
+fn test_parser_pow_exact() {
+    let lexer = Lexer::from_source_code("2^62");
+    let mut parser = Parser::from_lexer(lexer);
+    let node = parser.parse().unwrap();
+
+    match node.evaluate() {
+        Number::Int(n) => assert_eq!(n, 1 << 62),
+        n => panic!("Expected an integer, found {}", n),
+    }
+}
+
+#[test]
+fn test_parser_identity_folding() {
+    for (source, fold_identity, expected) in [
+        ("+5", true, vec!["5"]),
+        ("--5", true, vec!["5"]),
+        ("+5", false, vec!["Op(+)", "`-- 5"]),
+        ("5", false, vec!["5"]),
+        ("-5", true, vec!["Op(-)", "`-- 5"]),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer).with_identity_folding(fold_identity);
+        let node = parser.parse().unwrap();
+
+        assert_eq!(node.to_tree(), expected);
+    }
+
+    // Identities are folded by default.
+    let lexer = Lexer::from_source_code("+5");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+    assert_eq!(node.to_tree(), ["5"]);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_parser_variables() {
+    let lexer = Lexer::from_source_code("x^2 + 2x*y - y / x");
+    let mut parser = Parser::from_lexer(lexer);
+    let node = parser.parse().unwrap();
+
+    assert_eq!(parser.variables(), ["x", "y"]);
+
+    let mut vars = HashMap::new();
+    vars.insert("x".to_string(), Number::from(2));
+    vars.insert("y".to_string(), Number::from(4.0));
+
+    let expected = Number::from(18.0);
+    assert_eq!(node.evaluate_with(&vars), expected);
+    assert_eq!(
+        node.evaluate_indexed(&[Number::from(2), Number::from(4.0)]),
+        expected
+    );
+}
+
+#[test]
+fn test_parser_unbound_variable() {
+    let lexer = Lexer::from_source_code("1 + x");
+    let mut parser = Parser::from_lexer(lexer);
+    let node = parser.parse().unwrap();
+
+    match node.evaluate() {
+        Number::Flt(n) => assert!(n.is_nan()),
+        n => panic!("Expected NaN, found {}", n),
+    }
+}
+
+/// Variables that must not be looked up, apart from `x`.
+struct OnlyX(Number);
+
+impl Variables for OnlyX {
+    fn get(&self, name: &str, _index: usize) -> Option<Number> {
+        assert_eq!(name, "x", "Untaken branch was evaluated");
+        Some(self.0.clone())
+    }
+}
+
+#[test]
+fn test_parser_if_lazy() {
+    let lexer = Lexer::from_source_code("if(x < 0, -x, if(x > 0, x, boom))");
+    let mut parser = Parser::from_lexer(lexer);
+    let node = parser.parse().unwrap();
+
+    assert_eq!(
+        node.evaluate_with(&OnlyX(Number::from(-3))),
+        Number::from(3)
+    );
+    assert_eq!(node.evaluate_with(&OnlyX(Number::from(2))), Number::from(2));
+    assert_eq!(node.to_infix(), "if(x < 0, -x, if(x > 0, x, boom))");
+}
+
+#[test]
+fn test_parser_comparison_to_infix() {
+    for (source, expected) in [
+        ("1+2 < 3*4", "1 + 2 < 3*4"),
+        ("(1 < 2) == (2 < 3)", "(1 < 2) == (2 < 3)"),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        assert_eq!(node.to_infix(), expected);
+    }
+}
+
+#[test]
+fn test_parser_truth_tables() {
+    for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+        for (op, expected) in [("&&", a & b), ("||", a | b)] {
+            let source = format!("{} {} {}", a, op, b);
+            let lexer = Lexer::from_source_code(&source);
+            let mut parser = Parser::from_lexer(lexer);
+            let node = parser.parse().unwrap();
+
+            assert_eq!(node.evaluate(), Number::from(expected), "{}", source);
+        }
+    }
+
+    for (source, expected) in [("!0", 1), ("!1", 0), ("!-2.5", 0), ("!0.0", 1)] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        assert_eq!(node.evaluate(), Number::from(expected), "{}", source);
+    }
+}
+
+#[test]
+fn test_parser_tiny_truthiness() {
+    // Only exact zeros are false, however tiny other numbers are.
+    for (source, expected) in [
+        ("if(1e-20, 1, 2)", 1),
+        ("1e-20 && 1", 1),
+        ("0 || 1e-20", 1),
+        ("!1e-20", 0),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        assert_eq!(node.evaluate(), Number::from(expected), "{}", source);
+    }
+}
+
+#[test]
+fn test_parser_logic_lazy() {
+    let lexer = Lexer::from_source_code("x < 0 && boom || x > 0 || boom");
+    let mut parser = Parser::from_lexer(lexer);
+    let node = parser.parse().unwrap();
+
+    assert_eq!(node.evaluate_with(&OnlyX(Number::from(1))), Number::from(1));
+    assert_eq!(node.to_infix(), "x < 0 && boom || x > 0 || boom");
+}
+
+#[test]
+fn test_parser_logic_nan() {
+    for source in ["nan && 1", "nan || 1", "1 && nan", "!nan"] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        match node.evaluate() {
+            Number::Flt(n) => assert!(n.is_nan()),
+            n => panic!("Expected NaN, found {}", n),
+        }
+    }
+}
+
+#[test]
+fn test_parser_logic_to_infix() {
+    for (source, expected) in [
+        ("(1 || 0) && !(1 < 2)", "(1 || 0) && !1 < 2"),
+        ("!(0 && 1)", "!(0 && 1)"),
+        ("1 || (0 || 1)", "1 || (0 || 1)"),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        assert_eq!(node.to_infix(), expected);
+    }
+}
+
+#[test]
+fn test_parser_parse_rule() {
+    for (source, rule, expected, next) in [
+        (
+            "2*3 + 1",
+            GrammarRule::Term,
+            6,
+            TokenKind::Op(Operator::Plus),
+        ),
+        (
+            "2*3 + 1",
+            GrammarRule::Factor,
+            2,
+            TokenKind::Op(Operator::Star),
+        ),
+        ("2^3*4", GrammarRule::Pow, 8, TokenKind::Op(Operator::Star)),
+        (
+            "(1 + 2) * 3",
+            GrammarRule::ParenExpr,
+            3,
+            TokenKind::Op(Operator::Star),
+        ),
+        (
+            "1 + 2 < 4 && 0",
+            GrammarRule::Cmp,
+            1,
+            TokenKind::Op(Operator::And),
+        ),
+        ("1 + 2 < 4 && 0", GrammarRule::Expr, 0, TokenKind::Eof),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse_rule(rule).unwrap();
+
+        assert_eq!(node.evaluate(), Number::from(expected), "{:?}", rule);
+        assert_eq!(parser.peek().kind, next, "{:?}", rule);
+    }
+}
+
+#[test]
+fn test_parser_parse_rule_paren_expected() {
+    let lexer = Lexer::from_source_code("1 + 2");
+    let mut parser = Parser::from_lexer(lexer);
+
+    match parser.parse_rule(GrammarRule::ParenExpr) {
+        Err(TilError::Parse(ParseError::LeftParenExpected(_))) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_parser_unary_to_infix() {
+    for (source, expected) in [
+        ("-x", "-x"),
+        ("sin(-x)", "sin(-x)"),
+        ("+x", "x"),
+        ("2 * +(1 + x)", "2*(1 + x)"),
+        ("-sin(+x)", "-sin(x)"),
+        ("--x", "x"),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        assert_eq!(node.to_infix(), expected);
+    }
+}
+
+#[test]
+fn test_parser_program() {
+    for (source, expected) in [
+        ("1 + 2\n3 * 4", vec![3, 12]),
+        ("(1 +\n 2) * 3\n4", vec![9, 4]),
+        ("\n1\n\n  \n2\n", vec![1, 2]),
+        ("hypot(\n3,\n4\n)", vec![5]),
+        ("", vec![]),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let values: Vec<Number> = parser
+            .parse_program()
+            .unwrap()
+            .iter()
+            .map(|node| node.evaluate())
+            .collect();
+
+        assert_eq!(
+            values,
+            expected.into_iter().map(Number::from).collect::<Vec<_>>(),
+            "{:?}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_parser_program_errors() {
+    let lexer = Lexer::from_source_code("1\n2 3");
+    let mut parser = Parser::from_lexer(lexer);
+
+    match parser.parse_program() {
+        Err(TilError::Parse(ParseError::TrailingTokens { span })) => {
+            assert_eq!(span.start_index, 4)
+        }
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    let lexer = Lexer::from_source_code("1 +\n2");
+    let mut parser = Parser::from_lexer(lexer);
+
+    match parser.parse_program() {
+        Err(TilError::Parse(ParseError::MissingOperand { operator_span })) => {
+            assert_eq!(operator_span.start_index, 2)
+        }
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_parser_line_col() {
+    let source = "1 + 2\n  3 * (4 +)";
+    let lexer = Lexer::from_source_code(source);
+
+    match Parser::from_lexer(lexer).parse_program() {
+        Err(TilError::Parse(e)) => assert_eq!(e.span().unwrap().line_col(source), (2, 11)),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    for (index, expected) in [(0, (1, 1)), (5, (1, 6)), (6, (2, 1)), (99, (2, 12))] {
+        let span = Span {
+            start_index: index,
+            end_index: index,
+        };
+        assert_eq!(span.line_col(source), expected, "{:?}", index);
+    }
+
+    // Columns are counted in characters.
+    let span = Span {
+        start_index: 3,
+        end_index: 3,
+    };
+    assert_eq!(span.line_col("é\nüx"), (2, 2));
+}
+
+#[test]
+fn test_parser_statements() {
+    let mut statements = tilted::parse_statements("1 + 2\n\n3 * 4\n2 3\n$");
+
+    assert_eq!(
+        statements.next().unwrap().unwrap().evaluate(),
+        Number::from(3)
+    );
+    assert_eq!(
+        statements.next().unwrap().unwrap().evaluate(),
+        Number::from(12)
+    );
+
+    // The invalid character on the last line is never reached.
+    match statements.next() {
+        Some(Err(TilError::Parse(ParseError::TrailingTokens { span }))) => {
+            assert_eq!(span.start_index, 15)
+        }
+        r => panic!("Unexpected result {:?}", r),
+    }
+    assert!(statements.next().is_none());
+
+    let lexer = Lexer::from_source_code("(1 +\n 2) * 3\n4");
+    let values: Vec<Number> = Parser::from_lexer(lexer)
+        .into_statements()
+        .map(|node| node.unwrap().evaluate())
+        .collect();
+    assert_eq!(values, [Number::from(9), Number::from(4)]);
+    assert_eq!(tilted::parse_statements("\n\n").count(), 0);
+}
+
+#[test]
+fn test_parser_bare_functions() {
+    for (source, expected) in [
+        ("sin 0", "sin(0)"),
+        ("cos pi", "cos(pi)"),
+        ("sin 2 * 3", "sin(2)*3"),
+        ("sin x^2", "sin(x)^2"),
+        ("sin 2x", "sin(2)*x"),
+        ("sin sin (1 + 2)", "sin(sin(1 + 2))"),
+        ("2 cos |x|", "2*cos(|x|)"),
+        ("hypot(3, 4)", "hypot(3, 4)"),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer).with_bare_functions(true);
+
+        assert_eq!(parser.parse().unwrap().to_infix(), expected, "{:?}", source);
+    }
+
+    // `pi` is a variable, bound by its index.
+    let lexer = Lexer::from_source_code("cos pi");
+    let mut parser = Parser::from_lexer(lexer).with_bare_functions(true);
+    let node = parser.parse().unwrap();
+    let pi = Number::from(std::f64::consts::PI);
+    assert_eq!(node.evaluate_indexed(&[pi]), Number::from(-1));
+
+    // Functions with several arguments and signed arguments need parentheses,
+    // as do all functions by default.
+    for (source, bare) in [("hypot 3", true), ("sin -1", true), ("sin 0", false)] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer).with_bare_functions(bare);
+
+        assert!(parser.parse().is_err(), "{:?}", source);
+    }
+
+    let source = format!("{}0", "sin ".repeat(1000));
+    let lexer = Lexer::from_source_code(source);
+    match Parser::from_lexer(lexer).with_bare_functions(true).parse() {
+        Err(TilError::Parse(ParseError::TooDeep(_))) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_parser_int_div_mode() {
+    for (source, truncate, float) in [
+        ("7/2", "3", "3.5"),
+        ("-7/2", "-3", "-3.5"),
+        ("6/2", "3", "3"),
+        ("7/0", "inf", "inf"),
+        ("1 + 1/4", "1", "1.25"),
+    ] {
+        for (mode, expected) in [(IntDivMode::Truncate, truncate), (IntDivMode::Float, float)] {
+            let lexer = Lexer::from_source_code(source);
+            let mut parser = Parser::from_lexer(lexer).with_int_div_mode(mode);
+            let node = parser.parse().unwrap();
+
+            assert_eq!(node.evaluate().to_string(), expected, "{:?}", source);
+
+            // Division promoting integers is written back as `fdiv`, so that it
+            // means the same whatever the mode it is parsed with.
+            let infix = match mode {
+                IntDivMode::Truncate => source.to_string(),
+                IntDivMode::Float => source.replace('/', " fdiv "),
+            };
+            assert_eq!(node.to_infix(), infix);
+            let lexer = Lexer::from_source_code(&infix);
+            let node = Parser::from_lexer(lexer).parse().unwrap();
+            assert_eq!(node.evaluate().to_string(), expected, "{:?}", infix);
+        }
+    }
+
+    assert!(matches!(
+        Number::from(7).float_div(Number::from(2)),
+        Number::Flt(n) if n == 3.5
+    ));
+    assert!(matches!(
+        Number::from(6).float_div(Number::from(-2)),
+        Number::Int(-3)
+    ));
+}
+
+#[test]
+fn test_parser_caret_meaning() {
+    for (source, power, xor) in [
+        ("2^3", "8", "1"),
+        ("-2^3", "-8", "-1"),
+        ("5^1*2", "10", "8"),
+        ("2^0.5 > 1", "1", "0"),
+    ] {
+        for (caret, expected) in [(CaretMeaning::Power, power), (CaretMeaning::Xor, xor)] {
+            let lexer = Lexer::from_source_code(source);
+            let mut parser = Parser::from_lexer(lexer).with_caret_meaning(caret);
+            let node = parser.parse().unwrap();
+
+            assert_eq!(node.evaluate().to_string(), expected, "{:?}", source);
+        }
+    }
+
+    let lexer = Lexer::from_source_code("(1 + 2)^x");
+    let mut parser = Parser::from_lexer(lexer).with_caret_meaning(CaretMeaning::Xor);
+    assert_eq!(parser.parse().unwrap().to_infix(), "(1 + 2) xor x");
+    assert!(Number::from(2.0).xor(Number::from(3)).is_nan());
+
+    // `xor` means the same whatever `^` means, so it round-trips.
+    for caret in [CaretMeaning::Power, CaretMeaning::Xor] {
+        let lexer = Lexer::from_source_code("(1 + 2) xor 5 + 1");
+        let mut parser = Parser::from_lexer(lexer).with_caret_meaning(caret);
+        let node = parser.parse().unwrap();
+
+        assert_eq!(node.evaluate(), Number::Int(7));
+        assert_eq!(node.to_infix(), "(1 + 2) xor 5 + 1");
+        assert_eq!(node.to_tree()[1], "`-- Op(xor)");
+    }
+}
+
+#[test]
+fn test_parser_paren_balance() {
+    for (source, message) in [
+        (
+            "(1 + 2",
+            "Unexpected end-of-file, add 1 closing parenthesis",
+        ),
+        (
+            "((1 + sin(2",
+            "Unexpected end-of-file, add 3 closing parentheses",
+        ),
+        (
+            "(1 + (2)",
+            "Unexpected end-of-file, add 1 closing parenthesis",
+        ),
+        (
+            "1 + 2))",
+            "Found a right parenthesis without a matching left one at index 5, \
+             remove 2 closing parentheses",
+        ),
+        (
+            "(1)) + (2) + 3)",
+            "Found a right parenthesis without a matching left one at index 3, \
+             remove 2 closing parentheses",
+        ),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let error = Parser::from_lexer(lexer).parse().unwrap_err();
+        assert_eq!(error.to_string(), message, "{:?}", source);
+    }
+
+    // Only the rest of the line is counted in a program.
+    let lexer = Lexer::from_source_code("1)\n2)");
+    match Parser::from_lexer(lexer).parse_program() {
+        Err(TilError::Parse(ParseError::MismatchRightParen(1))) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    let lexer = Lexer::from_source_code("1))\n2)");
+    match Parser::from_lexer(lexer).parse_program() {
+        Err(TilError::Parse(ParseError::ExtraRightParens { index: 1, count: 2 })) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_parser_operand_expected() {
+    for (source, kind, index) in [
+        ("1 + ,", TokenKind::Comma, 4),
+        (", 1", TokenKind::Comma, 0),
+        ("(1 + ]", TokenKind::RightBracket, 5),
+        ("1 + =", TokenKind::Assign, 4),
+        ("(1 + )", TokenKind::RightParen, 5),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        match Parser::from_lexer(lexer).parse() {
+            Err(TilError::Parse(ParseError::OperandExpected(token))) => {
+                assert_eq!(token.kind, kind, "{:?}", source);
+                assert_eq!(token.span.start_index, index, "{:?}", source);
+            }
+            r => panic!("Unexpected result {:?} for {:?}", r, source),
+        }
+    }
+}
+
+#[test]
+fn test_parser_did_you_mean() {
+    for (source, found, suggestion, index) in
+        [("2 ** 3", "**", "^", 2), ("(1 + 2)**2", "**", "^", 7)]
+    {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+
+        match parser.parse() {
+            Err(TilError::Parse(ParseError::DidYouMean {
+                found: f,
+                suggestion: s,
+                span,
+            })) => {
+                assert_eq!((f, s), (found, suggestion));
+                assert_eq!((span.start_index, span.end_index), (index, index + 1));
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+}
+
+#[test]
+fn test_parser_floor_div_keeps_int() {
+    for (source, expected) in [
+        ("7 // 2", 3),
+        ("-7 // 2", -4),
+        ("7 // -2", -4),
+        ("-8 // 2", -4),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+
+        match parser.parse().unwrap().evaluate() {
+            Number::Int(n) => assert_eq!(n, expected, "{:?}", source),
+            n => panic!("Unexpected result {:?}", n),
+        }
+    }
+}
+
+#[test]
+fn test_parser_floor_div_to_infix() {
+    let lexer = Lexer::from_source_code("7 // (2 // 1)");
+    let mut parser = Parser::from_lexer(lexer);
+
+    assert_eq!(parser.parse().unwrap().to_infix(), "7 // (2 // 1)");
+}
+
+#[test]
+fn test_parser_locale_options() {
+    let options = LexerOptions {
+        decimal_separator: ',',
+        argument_separator: ';',
+    };
+    let lexer = Lexer::from_source_code("hypot(3; 4) + 0,5").with_options(options);
+    let mut parser = Parser::from_lexer(lexer);
+
+    assert_eq!(parser.parse().unwrap().evaluate(), Number::from(5.5));
+}
+
+#[test]
+fn test_parser_unknown_policy() {
+    // Unknown characters are errors by default.
+    let mut parser = Parser::from_lexer(Lexer::from_source_code("1 $ 2"));
+    match parser.parse() {
+        Err(TilError::Lex(LexError::UnrecognisedCharacter('$', 2))) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+
+    // Skipping them leaves `1 2`, which is only valid with implicit numbers.
+    let lexer = Lexer::from_source_code("1 $ 2").with_unknown_policy(UnknownPolicy::Skip);
+    let mut parser = Parser::from_lexer(lexer);
+    assert!(parser.parse().is_err());
+
+    let lexer = Lexer::from_source_code("1 $ 2").with_unknown_policy(UnknownPolicy::Skip);
+    let mut parser = Parser::from_lexer(lexer).with_implicit_numbers(true);
+    assert_eq!(parser.parse().unwrap().evaluate(), Number::from(2));
+    assert_eq!(parser.warnings().len(), 1);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_parser_partial_eval() {
+    let mut vars = HashMap::new();
+    vars.insert("y".to_string(), Number::from(4));
+
+    for (source, expected) in [
+        ("x + (2 * 3)", "x + 6"),
+        ("y * x + y^2", "4*x + 16"),
+        ("sin(x) * (y - 4)", "sin(x)*0"),
+        ("hypot(x, y) + hypot(3, y)", "hypot(x, 4) + 5"),
+        ("if(y > 3, x, 1/0)", "x"),
+        ("if(x, y, 2)", "if(x, 4, 2)"),
+        ("0 && x", "0"),
+        ("y + 1", "5"),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap().partial_eval(&vars);
+
+        assert_eq!(node.to_infix(), expected, "{:?}", source);
+    }
+}
+
+#[test]
+fn test_parser_feed() {
+    let lexer = Lexer::from_source_code("x + (1");
+    let mut parser = Parser::from_lexer(lexer).with_identity_folding(true);
+    assert!(parser.parse().is_err());
+    assert_eq!(parser.variables(), ["x"]);
+
+    parser.feed(Lexer::from_source_code("--y * 2"));
+    let node = parser.parse().unwrap();
+    assert_eq!(parser.variables(), ["y"]);
+    assert_eq!(node.to_tree()[1], "`-- Var(y)");
+    assert_eq!(node.evaluate_indexed(&[Number::from(3)]), Number::from(6));
+
+    parser.feed(Lexer::from_source_code("7 + 6 * 2"));
+    assert_eq!(parser.parse().unwrap().evaluate(), Number::from(19));
+    assert!(parser.variables().is_empty());
+}
+
+#[test]
+fn test_parser_root_span() {
+    for source in [
+        "1 + 2 * 3",
+        "-(1 + 2)",
+        "!!x && y",
+        "hypot(3, 4)",
+        "sin(x) ^ 2",
+        "2x",
+        "(1)",
+        "  1 < 2 ",
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+        let node = parser.parse().unwrap();
+
+        let start_index = source.len() - source.trim_start().len();
+        let end_index = source.trim_end().len() - 1;
+        let expected = Span {
+            start_index,
+            end_index,
+        };
+        assert_eq!(node.span(), Some(expected), "{:?}", source);
+    }
+}
+
+#[test]
+fn test_parser_span_merge() {
+    let a = Span {
+        start_index: 4,
+        end_index: 6,
+    };
+    let b = Span {
+        start_index: 0,
+        end_index: 2,
+    };
+    let merged = Span {
+        start_index: 0,
+        end_index: 6,
+    };
+
+    assert_eq!(Span::merge(a, b), merged);
+    assert_eq!(Span::merge(b, a), merged);
+    assert_eq!(Span::merge(a, a), a);
+}
+
+#[test]
+fn test_parser_implicit_numbers() {
+    for (source, expected) in [
+        ("7 6", 42),
+        ("2x 3", 18),
+        ("(1 + 1) 4 5", 40),
+        ("2 3^2", 18),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer).with_implicit_numbers(true);
+        let node = parser.parse().unwrap();
+
+        assert_eq!(
+            node.evaluate_indexed(&[Number::from(3)]),
+            Number::from(expected),
+            "{:?}",
+            source
+        );
+    }
+
+    // Trailing operators are still missing their operand.
+    let lexer = Lexer::from_source_code("7 6 +");
+    let mut parser = Parser::from_lexer(lexer).with_implicit_numbers(true);
+    assert!(matches!(
+        parser.parse(),
+        Err(TilError::Parse(ParseError::MissingOperand { .. }))
+    ));
+}
+
+#[test]
+fn test_parser_aliases() {
+    for (alias, name) in [("arcsin(0)", "asin(0)"), ("2tg(x)", "2*tan(x)")] {
+        let aliased = Parser::from_lexer(Lexer::from_source_code(alias))
+            .parse()
+            .unwrap();
+        let named = Parser::from_lexer(Lexer::from_source_code(name))
+            .parse()
+            .unwrap();
+
+        assert_eq!(aliased.to_tree(), named.to_tree());
+        assert_eq!(aliased.to_infix(), name);
+    }
+}
+
+#[test]
+fn test_parser_missing_argument_message() {
+    let lexer = Lexer::from_source_code("hypot(3,,4)");
+    let error = Parser::from_lexer(lexer).parse().unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "Expected an argument before the comma at index 8"
+    );
+}
+
+#[test]
+fn test_parser_to_tree_limited() {
+    let lexer = Lexer::from_source_code("1 + 2 * (3 - x)");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+    assert_eq!(node.depth(), 4);
+    assert_eq!(node.node_count(), 7);
+
+    assert_eq!(
+        node.to_tree_limited(2),
+        [
+            "Op(+)",
+            "`-- 1",
+            "`-- Op(*)",
+            "    `-- ... (1 node)",
+            "    `-- ... (3 nodes)",
+        ]
+    );
+    assert_eq!(
+        node.to_tree_limited(1),
+        ["Op(+)", "`-- ... (1 node)", "`-- ... (5 nodes)"]
+    );
+    assert_eq!(node.to_tree_limited(4), node.to_tree());
+}
+
+#[test]
+fn test_parser_to_tree_evaluated() {
+    let lexer = Lexer::from_source_code("7 + 6*2");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+
+    assert_eq!(
+        node.to_tree_evaluated(),
+        [
+            "Op(+) = 19",
+            "`-- 7 = 7",
+            "`-- Op(*) = 12",
+            "    `-- 6 = 6",
+            "    `-- 2 = 2",
+        ]
+    );
+
+    // Subtrees that cannot be evaluated are NaN.
+    let lexer = Lexer::from_source_code("asin(2) + x");
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+
+    assert_eq!(
+        node.to_tree_evaluated(),
+        [
+            "Op(+) = NaN",
+            "`-- Func(Asin) = NaN",
+            "|   `-- 2 = 2",
+            "`-- Var(x) = NaN",
+        ]
+    );
+}
+
+#[test]
+fn test_parser_comments() {
+    for (source, expected) in [("5 -- comment", 5), ("5 - -3", 8), ("5--3", 8)] {
+        let lexer = Lexer::from_source_code(source);
+        let node = Parser::from_lexer(lexer).parse().unwrap();
+
+        assert_eq!(node.evaluate(), Number::from(expected), "{:?}", source);
+    }
+
+    let mut lexer = Lexer::from_source_code("1 -- one\n2 -- two");
+    lexer.set_newlines(true);
+    let program = Parser::from_lexer(lexer).parse_program().unwrap();
+    assert_eq!(program.len(), 2);
+}
+
+#[test]
+fn test_parser_abs_to_infix() {
+    for (source, expected) in [
+        ("|x - 1|", "|x - 1|"),
+        ("-|x|", "-|x|"),
+        ("|(|x|)|", "|(|x|)|"),
+        ("|3| + |-y|", "|3| + |-y|"),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let node = Parser::from_lexer(lexer).parse().unwrap();
+
+        assert_eq!(node.to_infix(), expected, "{:?}", source);
+    }
+}
+
+#[test]
+fn test_parser_allowed_identifiers() {
+    let parse = |source| {
+        let lexer = Lexer::from_source_code(source);
+        Parser::from_lexer(lexer)
+            .with_allowed_identifiers(["sin", "x"])
+            .parse()
+    };
+
+    assert!(parse("sin(x)").is_ok());
+    assert!(parse("2x + 1").is_ok());
+
+    for (source, expected, index) in [("cos(x)", "cos", 0), ("sin(y)", "y", 4)] {
+        match parse(source) {
+            Err(TilError::Parse(ParseError::DisallowedIdentifier { name, span })) => {
+                assert_eq!((name.as_str(), span.start_index), (expected, index))
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "physics")]
+
+use tilted::{Lexer, Number, Parser};
+
+macro_rules! make_physics_test {
+    ($name: ident, $source: literal, $expected: expr) => {
+        #[test]
+        fn $name() {
+            let lexer = Lexer::from_source_code($source).with_physical_constants(true);
+            let mut parser = Parser::from_lexer(lexer);
+            let node = parser.parse().unwrap();
+
+            assert_eq!(node.evaluate(), Number::from($expected));
+        }
+    };
+}
+
+make_physics_test!(test_physics_c, "c", 299_792_458.0);
+make_physics_test!(test_physics_na, "NA", 6.022_140_76e23);
+make_physics_test!(test_physics_h, "h", 6.626_070_15e-34);
+make_physics_test!(
+    test_physics_photon_energy,
+    "h*c / 500e-9",
+    3.972_891_714_297_857e-19
+);
+make_physics_test!(test_physics_mole, "2*NA", 1.204_428_152e24);
+
+#[test]
+fn test_physics_not_variables() {
+    let lexer = Lexer::from_source_code("c + NA + x").with_physical_constants(true);
+    let mut parser = Parser::from_lexer(lexer);
+    parser.parse().unwrap();
+
+    assert_eq!(parser.variables(), ["x"]);
+
+    // Without the option, the feature alone does not change any input.
+    let lexer = Lexer::from_source_code("c + NA + x");
+    let mut parser = Parser::from_lexer(lexer);
+    parser.parse().unwrap();
+    assert_eq!(parser.variables(), ["c", "NA", "x"]);
+}
+
+#[cfg(feature = "dimensions")]
+#[test]
+fn test_physics_dimension_mismatch() {
+    use tilted::DimensionTag;
+
+    let lexer = Lexer::from_source_code("2 * (c + NA)").with_physical_constants(true);
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+    let (value, mismatches) = node.evaluate_dimensioned();
+
+    assert_eq!(value, node.evaluate());
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].left, DimensionTag("c"));
+    assert_eq!(mismatches[0].right, DimensionTag("NA"));
+    assert_eq!(mismatches[0].span.unwrap().start_index, 4);
+}
+
+#[cfg(feature = "dimensions")]
+#[test]
+fn test_physics_dimension_match() {
+    for source in ["c - 2*c", "-c < |c|", "h*c + NA", "sin(c) + h", "c + 1"] {
+        let lexer = Lexer::from_source_code(source).with_physical_constants(true);
+        let node = Parser::from_lexer(lexer).parse().unwrap();
+
+        assert!(node.evaluate_dimensioned().1.is_empty(), "{:?}", source);
+    }
+
+    // Mismatches deep inside other actions are found too.
+    let lexer = Lexer::from_source_code("sin(h - G) * 2").with_physical_constants(true);
+    let node = Parser::from_lexer(lexer).parse().unwrap();
+    assert_eq!(node.evaluate_dimensioned().1.len(), 1);
+}
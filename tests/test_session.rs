@@ -0,0 +1,139 @@
+#![cfg(feature = "std")]
+
+use tilted::{EvalError, Number, Session, TilError};
+
+#[test]
+fn test_session_definitions() {
+    let mut session = Session::new();
+
+    assert_eq!(session.run("a = 2").unwrap(), Number::from(2));
+    assert_eq!(session.run("b = a + 3").unwrap(), Number::from(5));
+    assert_eq!(session.run("a * b").unwrap(), Number::from(10));
+
+    // Symbolic definitions follow later changes.
+    session.run("a = 4").unwrap();
+    assert_eq!(session.run("b").unwrap(), Number::from(7));
+}
+
+#[test]
+fn test_session_constant_folding() {
+    let mut session = Session::new().with_constant_folding(true);
+
+    session.run("a = 2").unwrap();
+    session.run("b = a + 3").unwrap();
+    assert_eq!(session.definition("b").unwrap().to_tree(), ["5"]);
+
+    // Folded definitions keep their values.
+    session.run("a = 4").unwrap();
+    assert_eq!(session.run("b").unwrap(), Number::from(5));
+
+    // Definitions referring to unbound variables stay symbolic.
+    session.run("d = a + x").unwrap();
+    assert_eq!(session.definition("d").unwrap().to_infix(), "a + x");
+    session.run("x = 1").unwrap();
+    assert_eq!(session.run("d").unwrap(), Number::from(5));
+}
+
+#[test]
+fn test_session_cyclic_definition() {
+    let mut session = Session::new();
+
+    session.run("a = b + 1").unwrap();
+    match session.run("b = 2a") {
+        Err(TilError::Eval(EvalError::CyclicDefinition(name))) => assert_eq!(name, "b"),
+        r => panic!("Unexpected result {:?}", r),
+    }
+    assert!(session.run("c = c").is_err());
+}
+
+#[test]
+fn test_session_self_reference() {
+    // Constants may be redefined in terms of their previous values, whether or
+    // not constant folding is enabled.
+    for fold_constants in [false, true] {
+        let mut session = Session::new().with_constant_folding(fold_constants);
+
+        session.run("x = 2").unwrap();
+        session.run("y = x * 10").unwrap();
+        assert_eq!(session.run("x = x + 1").unwrap(), Number::from(3));
+        assert_eq!(session.run("x").unwrap(), Number::from(3));
+        assert_eq!(session.definition("x").unwrap().to_tree(), ["3"]);
+
+        let y = if fold_constants { 20 } else { 30 };
+        assert_eq!(
+            session.run("y").unwrap(),
+            Number::from(y),
+            "{}",
+            fold_constants
+        );
+    }
+}
+
+#[test]
+fn test_session_constant_declarations() {
+    let mut session = Session::new();
+
+    assert_eq!(session.run("let r = 2").unwrap(), Number::from(2));
+    let area = session.run("3.14159 * r^2").unwrap();
+    assert_eq!(area.to_string(), "12.56636");
+
+    // Declarations are folded, even without constant folding.
+    session.run("let d = 2r").unwrap();
+    assert_eq!(session.definition("d").unwrap().to_tree(), ["4"]);
+    assert_eq!(session.run("d").unwrap(), Number::from(4));
+
+    // Declared constants cannot be redefined.
+    for source in ["r = 3", "let r = 3"] {
+        match session.run(source) {
+            Err(TilError::Eval(EvalError::ConstantRedefined(name))) => assert_eq!(name, "r"),
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+    assert_eq!(session.run("r").unwrap(), Number::from(2));
+
+    // Declarations may only refer to constants.
+    session.run("s = x + 1").unwrap();
+    for source in ["let t = x", "let t = s * 2"] {
+        match session.run(source) {
+            Err(TilError::Eval(EvalError::NonConstantDeclaration(name))) => {
+                assert_eq!(name, "t")
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+    assert!(session.definition("t").is_none());
+
+    // `let` is an ordinary variable otherwise.
+    session.run("let = 5").unwrap();
+    assert_eq!(session.run("let + 1").unwrap(), Number::from(6));
+}
+
+#[test]
+fn test_session_history() {
+    let mut session = Session::new();
+
+    session.run("1 + 2").unwrap();
+    session.run("a = 10").unwrap();
+    session.run("a * 2").unwrap();
+    assert_eq!(
+        session.history(),
+        [Number::from(3), Number::from(10), Number::from(20)]
+    );
+
+    assert_eq!(session.run("hist(1)").unwrap(), Number::from(3));
+    assert_eq!(session.run("hist(3) + hist(2)").unwrap(), Number::from(30));
+    assert_eq!(session.history().len(), 5);
+
+    // Failed statements are not recorded.
+    assert!(session.run("1 +").is_err());
+    assert_eq!(session.history().len(), 5);
+
+    // Results that do not exist (yet) cannot be recalled.
+    for (source, n) in [("hist(0)", 0.0), ("hist(1.5)", 1.5), ("hist(100)", 100.0)] {
+        match session.run(source) {
+            Err(TilError::Eval(EvalError::HistoryOutOfRange(m))) => assert_eq!(m, n),
+            r => panic!("Unexpected result {:?} for {:?}", r, source),
+        }
+    }
+    assert_eq!(session.history().len(), 5);
+}
@@ -0,0 +1,209 @@
+#![cfg(feature = "vector")]
+
+use tilted::{
+    EvalError, EvalResult, Function, Lexer, Number, NumberKind, ParseError, Parser, TilError,
+};
+
+macro_rules! make_vector_test {
+    ($name: ident, $source: literal, $expected: literal) => {
+        #[test]
+        fn $name() {
+            let lexer = Lexer::from_source_code($source);
+            let mut parser = Parser::from_lexer(lexer);
+            let node = parser.parse().unwrap();
+
+            assert_eq!(node.evaluate().to_string(), $expected);
+        }
+    };
+}
+
+make_vector_test!(test_vector_literal, "[1, 2 + 3, 4]", "[1, 5, 4]");
+make_vector_test!(test_vector_empty, "[]", "[]");
+make_vector_test!(test_vector_trailing_comma, "[1, 2,]", "[1, 2]");
+make_vector_test!(test_vector_add, "[1, 2, 3] + [10, 20, 30]", "[11, 22, 33]");
+make_vector_test!(test_vector_broadcast, "[1, 2, 3] * 2", "[2, 4, 6]");
+make_vector_test!(test_vector_broadcast_left, "12 / [1, 2, 3]", "[12, 6, 4]");
+make_vector_test!(test_vector_pow, "[1, 2, 3]^2", "[1, 4, 9]");
+make_vector_test!(test_vector_neg, "-[1, -2]", "[-1, 2]");
+make_vector_test!(test_vector_func, "sin([0, 0])", "[0, 0]");
+make_vector_test!(test_vector_cmp, "[1, 2, 3] < 2", "[1, 0, 0]");
+make_vector_test!(test_vector_nested, "[[1, 2], 3] + 1", "[[2, 3], 4]");
+make_vector_test!(test_vector_length_mismatch, "[1, 2, 3] + [1, 2]", "NaN");
+make_vector_test!(test_vector_condition, "if([1], 2, 3)", "NaN");
+make_vector_test!(test_vector_dot, "dot([1, 2], [3, 4])", "11");
+make_vector_test!(test_vector_dot_mismatch, "dot([1, 2], [3])", "NaN");
+make_vector_test!(test_vector_dot_scalar, "dot([1, 2], 3)", "NaN");
+make_vector_test!(test_vector_len, "len([3, 4])", "5");
+make_vector_test!(test_vector_len_nested, "len([[2, 4], 4])", "6");
+make_vector_test!(test_vector_len_scalar, "len(-2)", "2");
+make_vector_test!(
+    test_vector_len_elementwise,
+    "len([3, 4]) * [1, 2]",
+    "[5, 10]"
+);
+
+#[test]
+fn test_vector_to_infix() {
+    let lexer = Lexer::from_source_code("[1, x + 2]*2");
+    let mut parser = Parser::from_lexer(lexer);
+
+    assert_eq!(parser.parse().unwrap().to_infix(), "[1, x + 2]*2");
+}
+
+#[test]
+fn test_vector_to_code() {
+    for source in ["[1, x + 2]*2", "len([3, 4])", "dot([1], [x])"] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+
+        let node = parser.parse().unwrap();
+        assert_eq!(node.to_rust(), None, "{:?}", source);
+        assert_eq!(node.to_c(), None, "{:?}", source);
+    }
+}
+
+#[test]
+fn test_vector_equality() {
+    let a = Number::Vec(vec![Number::from(1), Number::from(2.0)]);
+    let b = Number::Vec(vec![Number::from(1.0), Number::from(2)]);
+
+    assert_eq!(a, b);
+    assert_ne!(a, Number::Vec(vec![Number::from(1)]));
+    assert_ne!(a, Number::from(1));
+}
+
+#[test]
+fn test_vector_unclosed() {
+    let lexer = Lexer::from_source_code("[1, 2");
+    let mut parser = Parser::from_lexer(lexer);
+
+    match parser.parse() {
+        Err(TilError::Parse(ParseError::RightBracketExpected(_))) => (),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_vector_missing_comma() {
+    let lexer = Lexer::from_source_code("[1 2]");
+    let mut parser = Parser::from_lexer(lexer);
+
+    match parser.parse() {
+        Err(TilError::Parse(ParseError::CommaExpected(t))) => assert_eq!(t.span.start_index, 3),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_vector_try_length_mismatch() {
+    for (source, left, right) in [
+        ("[1, 2, 3] + [1, 2]", 3, 2),
+        ("[[1, 2], 3] * [[1], 3]", 2, 1),
+        ("2 - [[1], [1, 2]] < [[1, 2], [1]]", 1, 2),
+        ("dot([1, 2], [3])", 2, 1),
+        ("dot([[1], 2], [[1, 2], 2])", 1, 2),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+
+        match parser.parse().unwrap().try_evaluate() {
+            Err(EvalError::LengthMismatch { left: l, right: r }) => {
+                assert_eq!((l, r), (left, right), "{}", source)
+            }
+            r => panic!("Unexpected result {:?} for {}", r, source),
+        }
+    }
+
+    let lexer = Lexer::from_source_code("[[1, 2], 3] * [[1, 2], 3]");
+    let mut parser = Parser::from_lexer(lexer);
+    let result = parser.parse().unwrap().try_evaluate().unwrap();
+    assert_eq!(result.to_string(), "[[1, 4], 9]");
+}
+
+#[test]
+fn test_vector_program() {
+    let lexer = Lexer::from_source_code("[1,\n 2]\n3");
+    let mut parser = Parser::from_lexer(lexer);
+    let program = parser.parse_program().unwrap();
+
+    assert_eq!(program.len(), 2);
+    assert_eq!(program[0].evaluate().to_string(), "[1, 2]");
+}
+
+#[test]
+fn test_vector_functions_arity() {
+    for (source, func, expected) in [
+        ("dot([1])", Function::Dot, 2),
+        ("len([1], [2])", Function::Len, 1),
+    ] {
+        let lexer = Lexer::from_source_code(source);
+        let mut parser = Parser::from_lexer(lexer);
+
+        match parser.parse() {
+            Err(TilError::Parse(ParseError::WrongArity {
+                func: f,
+                expected: e,
+                ..
+            })) => assert_eq!((f, e), (func, expected)),
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+}
+
+#[test]
+fn test_vector_dot_is_exact() {
+    let lexer = Lexer::from_source_code("dot([1, 2], [3, 4])");
+    let mut parser = Parser::from_lexer(lexer);
+
+    assert_eq!(
+        parser.parse().unwrap().evaluate_typed(),
+        EvalResult::Exact(Number::from(11))
+    );
+}
+
+#[test]
+fn test_vector_empty_element() {
+    for (source, index) in [("[,]", 1), ("[1,,2]", 3)] {
+        let lexer = Lexer::from_source_code(source);
+        match Parser::from_lexer(lexer).parse() {
+            Err(TilError::Parse(ParseError::MissingArgument { comma_span })) => {
+                assert_eq!(comma_span.start_index, index)
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+}
+
+#[test]
+fn test_vector_checked_ops() {
+    let v = Number::Vec(vec![Number::from(1), Number::from(i128::MAX)]);
+
+    assert_eq!(
+        v.clone().checked_sub(Number::from(1)),
+        Some(Number::Vec(vec![
+            Number::from(0),
+            Number::from(i128::MAX - 1)
+        ]))
+    );
+    assert!(v.checked_add(Number::from(1)).is_none());
+}
+
+#[test]
+fn test_vector_kind() {
+    let v = Number::Vec(vec![Number::from(1), Number::from(2.5)]);
+    assert_eq!(v.kind(), NumberKind::Vec);
+
+    match v.promote_to_float() {
+        Number::Vec(v) => assert!(v.iter().all(|n| n.kind() == NumberKind::Flt)),
+        n => panic!("Unexpected result {:?}", n),
+    }
+}
+
+#[test]
+fn test_vector_predicates() {
+    let v = Number::Vec(vec![Number::from(1), Number::from(f64::INFINITY)]);
+    assert!(v.is_infinite() && !v.is_finite() && !v.is_nan());
+
+    let v = Number::Vec(vec![Number::from(1), Number::from(2.5)]);
+    assert!(v.is_finite() && !v.is_infinite() && !v.is_nan());
+}